@@ -0,0 +1,103 @@
+//! Works out which libalpm API to bind.
+//!
+//! The version comes from the `ALPM_SYS_LIBALPM_VERSION` environment variable if set (useful
+//! when cross compiling), otherwise from pkg-config. If neither is available the build fails,
+//! rather than guessing an API that might not match the library linked against.
+//!
+//! The version is passed to the crate as cumulative cfgs: `alpm_v12` (pacman 5.2, deltas
+//! removed), `alpm_v13` (pacman 6.0, callback contexts and parallel downloads) and `alpm_v14`
+//! (pacman 6.1, extended package data). It's also exported to dependent build scripts as
+//! `DEP_ALPM_VERSION`.
+//!
+//! With the `generate` feature, the enum constants are generated from the installed `alpm.h`
+//! with bindgen instead of being hand-written. Functions and types are always hand-written.
+
+#[cfg(feature = "generate")]
+extern crate bindgen;
+extern crate pkg_config;
+
+use std::env;
+
+/// The newest libalpm major version with known API changes.
+const NEWEST: u32 = 14;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=ALPM_SYS_LIBALPM_VERSION");
+    let library = pkg_config::Config::new()
+        .cargo_metadata(false)
+        .probe("libalpm")
+        .ok();
+    let version = match env::var("ALPM_SYS_LIBALPM_VERSION") {
+        Ok(version) => version,
+        Err(_) => match library {
+            Some(ref library) => library.version.clone(),
+            None => panic!("cannot find libalpm with pkg-config; install libalpm (pacman) or set \
+                            ALPM_SYS_LIBALPM_VERSION to the version to bind (e.g. 13.0.1)"),
+        },
+    };
+    let major: u32 = version
+        .split('.')
+        .next()
+        .and_then(|major| major.parse().ok())
+        .unwrap_or_else(|| panic!("cannot parse libalpm version {:?}", version));
+    if major > NEWEST {
+        println!("cargo:warning=libalpm {} is newer than any this crate knows about, using the \
+                  libalpm {} API",
+                 version,
+                 NEWEST);
+    }
+    for cfg in 12..NEWEST + 1 {
+        println!("cargo:rustc-check-cfg=cfg(alpm_v{})", cfg);
+        if major >= cfg {
+            println!("cargo:rustc-cfg=alpm_v{}", cfg);
+        }
+    }
+    println!("cargo:version={}", version);
+
+    if let Some(ref library) = library {
+        for path in &library.link_paths {
+            println!("cargo:rustc-link-search=native={}", path.display());
+        }
+    }
+
+    #[cfg(feature = "generate")]
+    generate(library.as_ref());
+}
+
+/// Generates bindings for the installed `alpm.h` into `$OUT_DIR/alpm.rs`.
+///
+/// Enums become constants with their C names, so they can stand in for the hand-written ones.
+/// Types that `libc` already has are imported from there to avoid ambiguous names.
+#[cfg(feature = "generate")]
+fn generate(library: Option<&pkg_config::Library>) {
+    use std::path::PathBuf;
+
+    let include_paths: Vec<PathBuf> = library
+        .map(|library| library.include_paths.clone())
+        .unwrap_or_default();
+    let header = include_paths
+        .iter()
+        .map(|path| path.join("alpm.h"))
+        .chain(Some(PathBuf::from("/usr/include/alpm.h")))
+        .find(|header| header.exists())
+        .unwrap_or_else(|| panic!("cannot find alpm.h to generate bindings from"));
+    println!("cargo:rerun-if-changed={}", header.display());
+
+    let bindings = bindgen::Builder::default()
+        .header(header.to_string_lossy())
+        .clang_args(include_paths.iter().map(|path| format!("-I{}", path.display())))
+        .allowlist_function("alpm_.*")
+        .allowlist_type("alpm_.*")
+        .allowlist_var("ALPM_.*")
+        .blocklist_type("off_t|__off_t|mode_t|__mode_t|int64_t|__int64_t")
+        .raw_line("use libc::{off_t, mode_t, int64_t};")
+        .default_enum_style(bindgen::EnumVariation::Consts)
+        .prepend_enum_name(false)
+        .layout_tests(false)
+        .generate()
+        .unwrap_or_else(|_| panic!("cannot generate bindings for {}", header.display()));
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("alpm.rs");
+    bindings
+        .write_to_file(&out)
+        .unwrap_or_else(|e| panic!("cannot write {}: {}", out.display(), e));
+}
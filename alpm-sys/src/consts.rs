@@ -0,0 +1,259 @@
+//! The hand-written enum constants, used unless bindings are generated.
+//!
+//! libalpm numbers its enums implicitly, so removing a variant renumbers the ones after it.
+
+use ffi::*;
+
+// alpm_errno_t
+pub const ALPM_ERR_MEMORY: alpm_errno_t = 1;
+pub const ALPM_ERR_SYSTEM: alpm_errno_t = 2;
+pub const ALPM_ERR_BADPERMS: alpm_errno_t = 3;
+pub const ALPM_ERR_NOT_A_FILE: alpm_errno_t = 4;
+pub const ALPM_ERR_NOT_A_DIR: alpm_errno_t = 5;
+pub const ALPM_ERR_WRONG_ARGS: alpm_errno_t = 6;
+pub const ALPM_ERR_DISK_SPACE: alpm_errno_t = 7;
+pub const ALPM_ERR_HANDLE_NULL: alpm_errno_t = 8;
+pub const ALPM_ERR_HANDLE_NOT_NULL: alpm_errno_t = 9;
+pub const ALPM_ERR_HANDLE_LOCK: alpm_errno_t = 10;
+pub const ALPM_ERR_DB_OPEN: alpm_errno_t = 11;
+pub const ALPM_ERR_DB_CREATE: alpm_errno_t = 12;
+pub const ALPM_ERR_DB_NULL: alpm_errno_t = 13;
+pub const ALPM_ERR_DB_NOT_NULL: alpm_errno_t = 14;
+pub const ALPM_ERR_DB_NOT_FOUND: alpm_errno_t = 15;
+pub const ALPM_ERR_DB_INVALID: alpm_errno_t = 16;
+pub const ALPM_ERR_DB_INVALID_SIG: alpm_errno_t = 17;
+pub const ALPM_ERR_DB_VERSION: alpm_errno_t = 18;
+pub const ALPM_ERR_DB_WRITE: alpm_errno_t = 19;
+pub const ALPM_ERR_DB_REMOVE: alpm_errno_t = 20;
+pub const ALPM_ERR_SERVER_BAD_URL: alpm_errno_t = 21;
+pub const ALPM_ERR_SERVER_NONE: alpm_errno_t = 22;
+pub const ALPM_ERR_TRANS_NOT_NULL: alpm_errno_t = 23;
+pub const ALPM_ERR_TRANS_NULL: alpm_errno_t = 24;
+pub const ALPM_ERR_TRANS_DUP_TARGET: alpm_errno_t = 25;
+pub const ALPM_ERR_TRANS_NOT_INITIALIZED: alpm_errno_t = 26;
+pub const ALPM_ERR_TRANS_NOT_PREPARED: alpm_errno_t = 27;
+pub const ALPM_ERR_TRANS_ABORT: alpm_errno_t = 28;
+pub const ALPM_ERR_TRANS_TYPE: alpm_errno_t = 29;
+pub const ALPM_ERR_TRANS_NOT_LOCKED: alpm_errno_t = 30;
+pub const ALPM_ERR_TRANS_HOOK_FAILED: alpm_errno_t = 31;
+pub const ALPM_ERR_PKG_NOT_FOUND: alpm_errno_t = 32;
+pub const ALPM_ERR_PKG_IGNORED: alpm_errno_t = 33;
+pub const ALPM_ERR_PKG_INVALID: alpm_errno_t = 34;
+pub const ALPM_ERR_PKG_INVALID_CHECKSUM: alpm_errno_t = 35;
+pub const ALPM_ERR_PKG_INVALID_SIG: alpm_errno_t = 36;
+pub const ALPM_ERR_PKG_MISSING_SIG: alpm_errno_t = 37;
+pub const ALPM_ERR_PKG_OPEN: alpm_errno_t = 38;
+pub const ALPM_ERR_PKG_CANT_REMOVE: alpm_errno_t = 39;
+pub const ALPM_ERR_PKG_INVALID_NAME: alpm_errno_t = 40;
+pub const ALPM_ERR_PKG_INVALID_ARCH: alpm_errno_t = 41;
+pub const ALPM_ERR_PKG_REPO_NOT_FOUND: alpm_errno_t = 42;
+pub const ALPM_ERR_SIG_MISSING: alpm_errno_t = 43;
+pub const ALPM_ERR_SIG_INVALID: alpm_errno_t = 44;
+#[cfg(not(alpm_v12))]
+pub const ALPM_ERR_DLT_INVALID: alpm_errno_t = 45;
+#[cfg(not(alpm_v12))]
+pub const ALPM_ERR_DLT_PATCHFAILED: alpm_errno_t = 46;
+// the delta errors were removed from the middle of the enum in libalpm 12
+#[cfg(not(alpm_v12))]
+const ERR_DLT: alpm_errno_t = 2;
+#[cfg(alpm_v12)]
+const ERR_DLT: alpm_errno_t = 0;
+pub const ALPM_ERR_UNSATISFIED_DEPS: alpm_errno_t = 45 + ERR_DLT;
+pub const ALPM_ERR_CONFLICTING_DEPS: alpm_errno_t = 46 + ERR_DLT;
+pub const ALPM_ERR_FILE_CONFLICTS: alpm_errno_t = 47 + ERR_DLT;
+pub const ALPM_ERR_RETRIEVE: alpm_errno_t = 48 + ERR_DLT;
+pub const ALPM_ERR_INVALID_REGEX: alpm_errno_t = 49 + ERR_DLT;
+pub const ALPM_ERR_LIBARCHIVE: alpm_errno_t = 50 + ERR_DLT;
+pub const ALPM_ERR_LIBCURL: alpm_errno_t = 51 + ERR_DLT;
+pub const ALPM_ERR_EXTERNAL_DOWNLOAD: alpm_errno_t = 52 + ERR_DLT;
+pub const ALPM_ERR_GPGME: alpm_errno_t = 53 + ERR_DLT;
+// alpm_pkgreason_t
+pub const ALPM_PKG_REASON_EXPLICIT: alpm_pkgreason_t = 0;
+pub const ALPM_PKG_REASON_DEPEND: alpm_pkgreason_t = 1;
+// alpm_pkgfrom_t
+pub const ALPM_PKG_FROM_FILE: alpm_pkgfrom_t = 1;
+pub const ALPM_PKG_FROM_LOCALDB: alpm_pkgfrom_t = 2;
+pub const ALPM_PKG_FROM_SYNCDB: alpm_pkgfrom_t = 3;
+// alpm_pkgvalidation_t
+pub const ALPM_PKG_VALIDATION_UNKNOWN: alpm_pkgvalidation_t = 0;
+pub const ALPM_PKG_VALIDATION_NONE: alpm_pkgvalidation_t = 1 << 0;
+pub const ALPM_PKG_VALIDATION_MD5SUM: alpm_pkgvalidation_t = 1 << 1;
+pub const ALPM_PKG_VALIDATION_SHA256SUM: alpm_pkgvalidation_t = 1 << 2;
+pub const ALPM_PKG_VALIDATION_SIGNATURE: alpm_pkgvalidation_t = 1 << 3;
+// alpm_depmod_t
+pub const ALPM_DEP_MOD_ANY: alpm_depmod_t = 1;
+pub const ALPM_DEP_MOD_EQ: alpm_depmod_t = 2;
+pub const ALPM_DEP_MOD_GE: alpm_depmod_t = 3;
+pub const ALPM_DEP_MOD_LE: alpm_depmod_t = 4;
+pub const ALPM_DEP_MOD_GT: alpm_depmod_t = 5;
+pub const ALPM_DEP_MOD_LT: alpm_depmod_t = 6;
+// alpm_siglevel_t
+pub const ALPM_SIG_PACKAGE: alpm_siglevel_t = 1 << 0;
+pub const ALPM_SIG_PACKAGE_OPTIONAL: alpm_siglevel_t = 1 << 1;
+pub const ALPM_SIG_PACKAGE_MARGINAL_OK: alpm_siglevel_t = 1 << 2;
+pub const ALPM_SIG_PACKAGE_UNKNOWN_OK: alpm_siglevel_t = 1 << 3;
+pub const ALPM_SIG_DATABASE: alpm_siglevel_t = 1 << 10;
+pub const ALPM_SIG_DATABASE_OPTIONAL: alpm_siglevel_t = 1 << 11;
+pub const ALPM_SIG_DATABASE_MARGINAL_OK: alpm_siglevel_t = 1 << 12;
+pub const ALPM_SIG_DATABASE_UNKNOWN_OK: alpm_siglevel_t = 1 << 13;
+pub const ALPM_SIG_USE_DEFAULT: alpm_siglevel_t = 1 << 31;
+// alpm_fileconflicttype_t
+pub const ALPM_FILECONFLICT_TARGET: alpm_fileconflicttype_t = 1;
+pub const ALPM_FILECONFLICT_FILESYSTEM: alpm_fileconflicttype_t = 2;
+// alpm_sigstatus_t
+pub const ALPM_SIGSTATUS_VALID: alpm_sigstatus_t = 0;
+pub const ALPM_SIGSTATUS_KEY_EXPIRED: alpm_sigstatus_t = 1;
+pub const ALPM_SIGSTATUS_SIG_EXPIRED: alpm_sigstatus_t = 2;
+pub const ALPM_SIGSTATUS_KEY_UNKNOWN: alpm_sigstatus_t = 3;
+pub const ALPM_SIGSTATUS_KEY_DISABLED: alpm_sigstatus_t = 4;
+pub const ALPM_SIGSTATUS_INVALID: alpm_sigstatus_t = 5;
+// alpm_sigvalidity_t
+pub const ALPM_SIGVALIDITY_FULL: alpm_sigvalidity_t = 0;
+pub const ALPM_SIGVALIDITY_MARGINAL: alpm_sigvalidity_t = 1;
+pub const ALPM_SIGVALIDITY_NEVER: alpm_sigvalidity_t = 2;
+pub const ALPM_SIGVALIDITY_UNKNOWN: alpm_sigvalidity_t = 3;
+// alpm_hook_when_t
+pub const ALPM_HOOK_PRE_TRANSACTION: alpm_hook_when_t = 1;
+pub const ALPM_HOOK_POST_TRANSACTION: alpm_hook_when_t = 2;
+// alpm_loglevel_t
+pub const ALPM_LOG_ERROR: alpm_loglevel_t = 1 << 0;
+pub const ALPM_LOG_WARNING: alpm_loglevel_t = 1 << 1;
+pub const ALPM_LOG_DEBUG: alpm_loglevel_t = 1 << 2;
+pub const ALPM_LOG_FUNCTION: alpm_loglevel_t = 1 << 3;
+// alpm_event_type_t {
+pub const ALPM_EVENT_CHECKDEPS_START: alpm_event_type_t = 1;
+pub const ALPM_EVENT_CHECKDEPS_DONE: alpm_event_type_t = 2;
+pub const ALPM_EVENT_FILECONFLICTS_START: alpm_event_type_t = 3;
+pub const ALPM_EVENT_FILECONFLICTS_DONE: alpm_event_type_t = 4;
+pub const ALPM_EVENT_RESOLVEDEPS_START: alpm_event_type_t = 5;
+pub const ALPM_EVENT_RESOLVEDEPS_DONE: alpm_event_type_t = 6;
+pub const ALPM_EVENT_INTERCONFLICTS_START: alpm_event_type_t = 7;
+pub const ALPM_EVENT_INTERCONFLICTS_DONE: alpm_event_type_t = 8;
+pub const ALPM_EVENT_TRANSACTION_START: alpm_event_type_t = 9;
+pub const ALPM_EVENT_TRANSACTION_DONE: alpm_event_type_t = 10;
+pub const ALPM_EVENT_PACKAGE_OPERATION_START: alpm_event_type_t = 11;
+pub const ALPM_EVENT_PACKAGE_OPERATION_DONE: alpm_event_type_t = 12;
+pub const ALPM_EVENT_INTEGRITY_START: alpm_event_type_t = 13;
+pub const ALPM_EVENT_INTEGRITY_DONE: alpm_event_type_t = 14;
+pub const ALPM_EVENT_LOAD_START: alpm_event_type_t = 15;
+pub const ALPM_EVENT_LOAD_DONE: alpm_event_type_t = 16;
+#[cfg(not(alpm_v12))]
+pub const ALPM_EVENT_DELTA_INTEGRITY_START: alpm_event_type_t = 17;
+#[cfg(not(alpm_v12))]
+pub const ALPM_EVENT_DELTA_INTEGRITY_DONE: alpm_event_type_t = 18;
+#[cfg(not(alpm_v12))]
+pub const ALPM_EVENT_DELTA_PATCHES_START: alpm_event_type_t = 19;
+#[cfg(not(alpm_v12))]
+pub const ALPM_EVENT_DELTA_PATCHES_DONE: alpm_event_type_t = 20;
+#[cfg(not(alpm_v12))]
+pub const ALPM_EVENT_DELTA_PATCH_START: alpm_event_type_t = 21;
+#[cfg(not(alpm_v12))]
+pub const ALPM_EVENT_DELTA_PATCH_DONE: alpm_event_type_t = 22;
+#[cfg(not(alpm_v12))]
+pub const ALPM_EVENT_DELTA_PATCH_FAILED: alpm_event_type_t = 23;
+// the delta events were removed from the middle of the enum in libalpm 12
+#[cfg(not(alpm_v12))]
+const EVENT_DELTA: alpm_event_type_t = 7;
+#[cfg(alpm_v12)]
+const EVENT_DELTA: alpm_event_type_t = 0;
+pub const ALPM_EVENT_SCRIPTLET_INFO: alpm_event_type_t = 17 + EVENT_DELTA;
+// renamed in libalpm 13, when downloads became parallel
+#[cfg(not(alpm_v13))]
+pub const ALPM_EVENT_RETRIEVE_START: alpm_event_type_t = 18 + EVENT_DELTA;
+#[cfg(alpm_v13)]
+pub const ALPM_EVENT_DB_RETRIEVE_START: alpm_event_type_t = 18 + EVENT_DELTA;
+#[cfg(not(alpm_v13))]
+pub const ALPM_EVENT_RETRIEVE_DONE: alpm_event_type_t = 19 + EVENT_DELTA;
+#[cfg(alpm_v13)]
+pub const ALPM_EVENT_DB_RETRIEVE_DONE: alpm_event_type_t = 19 + EVENT_DELTA;
+#[cfg(not(alpm_v13))]
+pub const ALPM_EVENT_RETRIEVE_FAILED: alpm_event_type_t = 20 + EVENT_DELTA;
+#[cfg(alpm_v13)]
+pub const ALPM_EVENT_DB_RETRIEVE_FAILED: alpm_event_type_t = 20 + EVENT_DELTA;
+#[cfg(not(alpm_v13))]
+pub const ALPM_EVENT_PKGDOWNLOAD_START: alpm_event_type_t = 21 + EVENT_DELTA;
+#[cfg(alpm_v13)]
+pub const ALPM_EVENT_PKG_RETRIEVE_START: alpm_event_type_t = 21 + EVENT_DELTA;
+#[cfg(not(alpm_v13))]
+pub const ALPM_EVENT_PKGDOWNLOAD_DONE: alpm_event_type_t = 22 + EVENT_DELTA;
+#[cfg(alpm_v13)]
+pub const ALPM_EVENT_PKG_RETRIEVE_DONE: alpm_event_type_t = 22 + EVENT_DELTA;
+#[cfg(not(alpm_v13))]
+pub const ALPM_EVENT_PKGDOWNLOAD_FAILED: alpm_event_type_t = 23 + EVENT_DELTA;
+#[cfg(alpm_v13)]
+pub const ALPM_EVENT_PKG_RETRIEVE_FAILED: alpm_event_type_t = 23 + EVENT_DELTA;
+pub const ALPM_EVENT_DISKSPACE_START: alpm_event_type_t = 24 + EVENT_DELTA;
+pub const ALPM_EVENT_DISKSPACE_DONE: alpm_event_type_t = 25 + EVENT_DELTA;
+pub const ALPM_EVENT_OPTDEP_REMOVAL: alpm_event_type_t = 26 + EVENT_DELTA;
+pub const ALPM_EVENT_DATABASE_MISSING: alpm_event_type_t = 27 + EVENT_DELTA;
+pub const ALPM_EVENT_KEYRING_START: alpm_event_type_t = 28 + EVENT_DELTA;
+pub const ALPM_EVENT_KEYRING_DONE: alpm_event_type_t = 29 + EVENT_DELTA;
+pub const ALPM_EVENT_KEY_DOWNLOAD_START: alpm_event_type_t = 30 + EVENT_DELTA;
+pub const ALPM_EVENT_KEY_DOWNLOAD_DONE: alpm_event_type_t = 31 + EVENT_DELTA;
+pub const ALPM_EVENT_PACNEW_CREATED: alpm_event_type_t = 32 + EVENT_DELTA;
+pub const ALPM_EVENT_PACSAVE_CREATED: alpm_event_type_t = 33 + EVENT_DELTA;
+pub const ALPM_EVENT_HOOK_START: alpm_event_type_t = 34 + EVENT_DELTA;
+pub const ALPM_EVENT_HOOK_DONE: alpm_event_type_t = 35 + EVENT_DELTA;
+pub const ALPM_EVENT_HOOK_RUN_START: alpm_event_type_t = 36 + EVENT_DELTA;
+pub const ALPM_EVENT_HOOK_RUN_DONE: alpm_event_type_t = 37 + EVENT_DELTA;
+// alpm_download_event_type_t
+#[cfg(alpm_v13)]
+pub const ALPM_DOWNLOAD_INIT: alpm_download_event_type_t = 0;
+#[cfg(alpm_v13)]
+pub const ALPM_DOWNLOAD_PROGRESS: alpm_download_event_type_t = 1;
+#[cfg(alpm_v13)]
+pub const ALPM_DOWNLOAD_RETRY: alpm_download_event_type_t = 2;
+#[cfg(alpm_v13)]
+pub const ALPM_DOWNLOAD_COMPLETED: alpm_download_event_type_t = 3;
+// alpm_package_operation_t
+pub const ALPM_PACKAGE_INSTALL: alpm_package_operation_t = 1;
+pub const ALPM_PACKAGE_UPGRADE: alpm_package_operation_t = 2;
+pub const ALPM_PACKAGE_REINSTALL: alpm_package_operation_t = 3;
+pub const ALPM_PACKAGE_DOWNGRADE: alpm_package_operation_t = 4;
+pub const ALPM_PACKAGE_REMOVE: alpm_package_operation_t = 5;
+// alpm_question_type_t
+pub const ALPM_QUESTION_INSTALL_IGNOREPKG: alpm_question_type_t = 1;
+pub const ALPM_QUESTION_REPLACE_PKG: alpm_question_type_t = 2;
+pub const ALPM_QUESTION_CONFLICT_PKG: alpm_question_type_t = 4;
+pub const ALPM_QUESTION_CORRUPTED_PKG: alpm_question_type_t = 8;
+pub const ALPM_QUESTION_REMOVE_PKGS: alpm_question_type_t = 16;
+pub const ALPM_QUESTION_SELECT_PROVIDER: alpm_question_type_t = 32;
+pub const ALPM_QUESTION_IMPORT_KEY: alpm_question_type_t = 64;
+// alpm_progress_t
+pub const ALPM_PROGRESS_ADD_START: alpm_progress_t = 0;
+pub const ALPM_PROGRESS_UPGRADE_START: alpm_progress_t = 1;
+pub const ALPM_PROGRESS_DOWNGRADE_START: alpm_progress_t = 2;
+pub const ALPM_PROGRESS_REINSTALL_START: alpm_progress_t = 3;
+pub const ALPM_PROGRESS_REMOVE_START: alpm_progress_t = 4;
+pub const ALPM_PROGRESS_CONFLICTS_START: alpm_progress_t = 5;
+pub const ALPM_PROGRESS_DISKSPACE_START: alpm_progress_t = 6;
+pub const ALPM_PROGRESS_INTEGRITY_START: alpm_progress_t = 7;
+pub const ALPM_PROGRESS_LOAD_START: alpm_progress_t = 8;
+pub const ALPM_PROGRESS_KEYRING_START: alpm_progress_t = 9;
+// alpm_db_usage_t;
+pub const ALPM_DB_USAGE_SYNC: alpm_db_usage_t = 1 << 0;
+pub const ALPM_DB_USAGE_SEARCH: alpm_db_usage_t = 1 << 1;
+pub const ALPM_DB_USAGE_INSTALL: alpm_db_usage_t = 1 << 2;
+pub const ALPM_DB_USAGE_UPGRADE: alpm_db_usage_t = 1 << 3;
+pub const ALPM_DB_USAGE_ALL: alpm_db_usage_t = (1 << 4) - 1;
+// alpm_transflag_t
+pub const ALPM_TRANS_FLAG_NODEPS: alpm_transflag_t = 1 << 0;
+pub const ALPM_TRANS_FLAG_FORCE: alpm_transflag_t = 1 << 1;
+pub const ALPM_TRANS_FLAG_NOSAVE: alpm_transflag_t = 1 << 2;
+pub const ALPM_TRANS_FLAG_NODEPVERSION: alpm_transflag_t = 1 << 3;
+pub const ALPM_TRANS_FLAG_CASCADE: alpm_transflag_t = 1 << 4;
+pub const ALPM_TRANS_FLAG_RECURSE: alpm_transflag_t = 1 << 5;
+pub const ALPM_TRANS_FLAG_DBONLY: alpm_transflag_t = 1 << 6;
+pub const ALPM_TRANS_FLAG_ALLDEPS: alpm_transflag_t = 1 << 8;
+pub const ALPM_TRANS_FLAG_DOWNLOADONLY: alpm_transflag_t = 1 << 9;
+pub const ALPM_TRANS_FLAG_NOSCRIPTLET: alpm_transflag_t = 1 << 10;
+pub const ALPM_TRANS_FLAG_NOCONFLICTS: alpm_transflag_t = 1 << 11;
+pub const ALPM_TRANS_FLAG_NEEDED: alpm_transflag_t = 1 << 13;
+pub const ALPM_TRANS_FLAG_ALLEXPLICIT: alpm_transflag_t = 1 << 14;
+pub const ALPM_TRANS_FLAG_UNNEEDED: alpm_transflag_t = 1 << 15;
+pub const ALPM_TRANS_FLAG_RECURSEALL: alpm_transflag_t = 1 << 16;
+pub const ALPM_TRANS_FLAG_NOLOCK: alpm_transflag_t = 1 << 17;
+// alpm_caps
+pub const ALPM_CAPABILITY_NLS: alpm_caps = 1;
+pub const ALPM_CAPABILITY_DOWNLOADER: alpm_caps = 2;
+pub const ALPM_CAPABILITY_SIGNATURES: alpm_caps = 4;
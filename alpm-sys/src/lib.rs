@@ -1,9 +1,20 @@
-#![feature(untagged_unions)]
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 extern crate libc;
 extern crate libarchive3_sys;
 
 mod ffi;
+#[cfg(not(feature = "generate"))]
+mod consts;
+/// Bindings generated from the installed `alpm.h` (only with the `generate` feature).
+///
+/// Only the constants are meant to be used from here, and they're re-exported at the top level
+/// in place of the hand-written ones. Other items are re-exported too unless a hand-written one of
+/// the same name exists, which is the case for every function and type the crate binds.
+#[cfg(feature = "generate")]
+#[allow(non_upper_case_globals, dead_code)]
+pub mod generated {
+    include!(concat!(env!("OUT_DIR"), "/alpm.rs"));
+}
 pub use ffi::*;
 
@@ -0,0 +1,85 @@
+//! Opening the compressed tar archives used for packages and sync databases.
+
+use std::fs;
+use std::io::{self, BufReader};
+use std::io::prelude::*;
+use std::path::Path;
+
+use flate2;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use tar::Archive;
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
+use zstd;
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+/// The compression used for an archive, worked out from its first few bytes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// Detects the compression from the magic bytes at the start of a file.
+    pub fn detect(magic: &[u8]) -> Compression {
+        if magic.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Compression::Xz
+        } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Works out the compression from the extension of an archive, e.g. `.tar.zst`.
+    pub fn from_extension(ext: &str) -> Option<Compression> {
+        match ext {
+            ".tar" => Some(Compression::None),
+            ".tar.gz" => Some(Compression::Gzip),
+            ".tar.xz" => Some(Compression::Xz),
+            ".tar.zst" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Wraps a reader in the right decompressor, detecting the compression from the data.
+pub fn decompress<'a, R: Read + 'a>(reader: R) -> io::Result<Box<Read + 'a>> {
+    let mut reader = BufReader::new(reader);
+    let compression = Compression::detect(reader.fill_buf()?);
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(GzDecoder::new(reader)),
+        Compression::Xz => Box::new(XzDecoder::new(reader)),
+        Compression::Zstd => Box::new(ZstdDecoder::with_buffer(reader)?),
+    })
+}
+
+/// Compresses `data` in memory.
+pub fn compress(data: &[u8], compression: Compression) -> io::Result<Vec<u8>> {
+    Ok(match compression {
+        Compression::None => data.to_vec(),
+        Compression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()?
+        }
+        Compression::Xz => {
+            let mut encoder = XzEncoder::new(Vec::new(), 6);
+            encoder.write_all(data)?;
+            encoder.finish()?
+        }
+        Compression::Zstd => zstd::stream::encode_all(data, 0)?,
+    })
+}
+
+/// Opens a (possibly compressed) tar archive.
+pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Archive<Box<Read>>> {
+    Ok(Archive::new(decompress(fs::File::open(path)?)?))
+}
@@ -0,0 +1,99 @@
+//! Owned dependency strings, as found in package metadata (e.g. `glibc>=2.26`).
+
+use std::fmt;
+use std::str::FromStr;
+
+use libalpm::VersionConstraintType;
+
+/// A dependency, conflict, provision or replacement of a package.
+///
+/// This is an owned equivalent of `libalpm::Dependency` for metadata that hasn't been loaded
+/// through libalpm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Depend {
+    /// The name of the package (or virtual package).
+    pub name: String,
+    /// The version, if the dependency has a version constraint.
+    pub version: Option<String>,
+    /// How `version` constrains the dependency (`Any` if there is no version).
+    pub constraint: VersionConstraintType,
+    /// The description of an optional dependency (the part after `: `).
+    pub description: Option<String>,
+}
+
+impl Depend {
+    /// Parses a dependency string like `name`, `name>=1.0` or `name: why it is needed`. This
+    /// follows `alpm_dep_from_string`, so it never fails.
+    pub fn parse(s: &str) -> Depend {
+        let (dep, description) = match s.find(": ") {
+            Some(idx) => (&s[..idx], Some(s[idx + 2..].trim().to_owned())),
+            None => (s, None),
+        };
+        let dep = dep.trim();
+        let (name, constraint, version) = match dep.find(|c| c == '<' || c == '>' || c == '=') {
+            Some(idx) => {
+                let (name, rest) = dep.split_at(idx);
+                let (constraint, len) = if rest.starts_with(">=") {
+                    (VersionConstraintType::GreaterOrEqual, 2)
+                } else if rest.starts_with("<=") {
+                    (VersionConstraintType::LessOrEqual, 2)
+                } else if rest.starts_with('>') {
+                    (VersionConstraintType::Greater, 1)
+                } else if rest.starts_with('<') {
+                    (VersionConstraintType::Less, 1)
+                } else {
+                    (VersionConstraintType::Equal, 1)
+                };
+                (name, constraint, Some(rest[len..].to_owned()))
+            }
+            None => (dep, VersionConstraintType::Any, None),
+        };
+        Depend {
+            name: name.to_owned(),
+            version: version,
+            constraint: constraint,
+            description: description,
+        }
+    }
+}
+
+impl FromStr for Depend {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Depend, ()> {
+        Ok(Depend::parse(s))
+    }
+}
+
+impl fmt::Display for Depend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(ref version) = self.version {
+            let op = match self.constraint {
+                VersionConstraintType::Any | VersionConstraintType::Equal => "=",
+                VersionConstraintType::GreaterOrEqual => ">=",
+                VersionConstraintType::LessOrEqual => "<=",
+                VersionConstraintType::Greater => ">",
+                VersionConstraintType::Less => "<",
+            };
+            write!(f, "{}{}", op, version)?;
+        }
+        if let Some(ref description) = self.description {
+            write!(f, ": {}", description)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_parse_depend() {
+    let dep = Depend::parse("glibc>=2.26");
+    assert_eq!(dep.name, "glibc");
+    assert_eq!(dep.version, Some("2.26".into()));
+    assert_eq!(dep.constraint, VersionConstraintType::GreaterOrEqual);
+    let dep = Depend::parse("python: for the scripts");
+    assert_eq!(dep.constraint, VersionConstraintType::Any);
+    assert_eq!(dep.description, Some("for the scripts".into()));
+    for s in &["sh", "libfoo.so=1-64", "bar<1:2.0-1", "baz: needed for x"] {
+        assert_eq!(Depend::parse(s).to_string(), *s);
+    }
+}
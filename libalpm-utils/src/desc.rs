@@ -0,0 +1,249 @@
+//! The `%KEY%`-style metadata files used in the local and sync databases (`desc`, `files` and
+//! `depends`), and the owned package records read from them.
+
+use libalpm::Reason;
+
+use depend::Depend;
+use ini::Error;
+
+/// A file backed up when a package is upgraded or removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    /// The path relative to the installation root, e.g. `etc/pacman.conf`.
+    pub name: String,
+    /// The md5 hash of the file as it was installed.
+    pub hash: String,
+}
+
+/// An owned package record, read from a database without going through libalpm.
+///
+/// Fields that are missing from the database are left as `None` or empty. Some fields only
+/// appear in the local database (e.g. `install_date`, `files`) and others only in sync databases
+/// (e.g. `filename`, `sha256sum`).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PackageRecord {
+    pub name: String,
+    pub version: String,
+    pub base: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub arch: Option<String>,
+    /// Seconds since the unix epoch.
+    pub build_date: Option<i64>,
+    /// Seconds since the unix epoch.
+    pub install_date: Option<i64>,
+    pub packager: Option<String>,
+    /// The size of the package when it is installed.
+    pub installed_size: Option<u64>,
+    /// The size of the package file.
+    pub download_size: Option<u64>,
+    pub reason: Option<Reason>,
+    pub licenses: Vec<String>,
+    /// How the package was validated when it was installed, e.g. `sha256` or `pgp`.
+    pub validation: Vec<String>,
+    pub groups: Vec<String>,
+    pub replaces: Vec<Depend>,
+    pub depends: Vec<Depend>,
+    pub optional_depends: Vec<Depend>,
+    pub make_depends: Vec<Depend>,
+    pub check_depends: Vec<Depend>,
+    pub conflicts: Vec<Depend>,
+    pub provides: Vec<Depend>,
+    /// Extra `key=value` data, e.g. `pkgtype=pkg`.
+    pub xdata: Vec<String>,
+    /// The name of the package file.
+    pub filename: Option<String>,
+    pub md5sum: Option<String>,
+    pub sha256sum: Option<String>,
+    /// The base64 encoded detached signature.
+    pub pgp_signature: Option<String>,
+    /// Installed files and directories, relative to the root. Directories end in `/`.
+    pub files: Vec<String>,
+    pub backup: Vec<Backup>,
+}
+
+impl PackageRecord {
+    /// Parses a metadata file (e.g. `desc`) and fills in the fields it contains.
+    ///
+    /// `filename` is only used in error messages. Unknown keys are ignored.
+    pub(crate) fn add_metadata(&mut self, filename: &str, text: &str) -> Result<(), Error> {
+        for (line_no, key, values) in parse_sections(filename, text)? {
+            self.add_field(filename, line_no, &key, values)?;
+        }
+        Ok(())
+    }
+
+    fn add_field(&mut self, filename: &str, line_no: usize, key: &str, values: Vec<String>)
+        -> Result<(), Error>
+    {
+        let single = || values.first().cloned().unwrap_or_default();
+        let number = || -> Result<i64, Error> {
+            single().parse().map_err(|_| {
+                Error::Parse(filename.into(), line_no + 1, values.join(" "))
+            })
+        };
+        let depends = || values.iter().map(|s| Depend::parse(s)).collect::<Vec<_>>();
+        match key {
+            "NAME" => self.name = single(),
+            "VERSION" => self.version = single(),
+            "BASE" => self.base = Some(single()),
+            "DESC" => self.description = Some(single()),
+            "URL" => self.url = Some(single()),
+            "ARCH" => self.arch = Some(single()),
+            "BUILDDATE" => self.build_date = Some(number()?),
+            "INSTALLDATE" => self.install_date = Some(number()?),
+            "PACKAGER" => self.packager = Some(single()),
+            "SIZE" | "ISIZE" => self.installed_size = Some(number()? as u64),
+            "CSIZE" => self.download_size = Some(number()? as u64),
+            "REASON" => self.reason = Some(match number()? {
+                0 => Reason::Explicit,
+                _ => Reason::Depend,
+            }),
+            "LICENSE" => self.licenses = values,
+            "VALIDATION" => self.validation = values,
+            "GROUPS" => self.groups = values,
+            "REPLACES" => self.replaces = depends(),
+            "DEPENDS" => self.depends = depends(),
+            "OPTDEPENDS" => self.optional_depends = depends(),
+            "MAKEDEPENDS" => self.make_depends = depends(),
+            "CHECKDEPENDS" => self.check_depends = depends(),
+            "CONFLICTS" => self.conflicts = depends(),
+            "PROVIDES" => self.provides = depends(),
+            "XDATA" => self.xdata = values,
+            "FILENAME" => self.filename = Some(single()),
+            "MD5SUM" => self.md5sum = Some(single()),
+            "SHA256SUM" => self.sha256sum = Some(single()),
+            "PGPSIG" => self.pgp_signature = Some(single()),
+            "FILES" => self.files = values,
+            "BACKUP" => {
+                let mut backup = Vec::new();
+                for value in values.iter() {
+                    let mut parts = value.splitn(2, '\t');
+                    match (parts.next(), parts.next()) {
+                        (Some(name), Some(hash)) => backup.push(Backup {
+                            name: name.into(),
+                            hash: hash.into(),
+                        }),
+                        _ => {
+                            return Err(Error::Parse(filename.into(), line_no + 1, value.clone()));
+                        }
+                    }
+                }
+                self.backup = backup;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Writes the `desc` entry for a sync database, in the same order as `repo-add`. Empty
+    /// fields are left out.
+    pub(crate) fn write_desc(&self) -> String {
+        fn optional<T: ToString>(value: &Option<T>) -> Vec<String> {
+            value.iter().map(|v| v.to_string()).collect()
+        }
+        fn depends(deps: &[Depend]) -> Vec<String> {
+            deps.iter().map(|d| d.to_string()).collect()
+        }
+        let sections = [
+            ("FILENAME", optional(&self.filename)),
+            ("NAME", vec![self.name.clone()]),
+            ("BASE", optional(&self.base)),
+            ("VERSION", vec![self.version.clone()]),
+            ("DESC", optional(&self.description)),
+            ("GROUPS", self.groups.clone()),
+            ("CSIZE", optional(&self.download_size)),
+            ("ISIZE", optional(&self.installed_size)),
+            ("MD5SUM", optional(&self.md5sum)),
+            ("SHA256SUM", optional(&self.sha256sum)),
+            ("PGPSIG", optional(&self.pgp_signature)),
+            ("URL", optional(&self.url)),
+            ("LICENSE", self.licenses.clone()),
+            ("ARCH", optional(&self.arch)),
+            ("BUILDDATE", optional(&self.build_date)),
+            ("PACKAGER", optional(&self.packager)),
+            ("REPLACES", depends(&self.replaces)),
+            ("CONFLICTS", depends(&self.conflicts)),
+            ("PROVIDES", depends(&self.provides)),
+            ("DEPENDS", depends(&self.depends)),
+            ("OPTDEPENDS", depends(&self.optional_depends)),
+            ("MAKEDEPENDS", depends(&self.make_depends)),
+            ("CHECKDEPENDS", depends(&self.check_depends)),
+        ];
+        let mut out = String::new();
+        for &(key, ref values) in sections.iter() {
+            if !values.is_empty() {
+                out.push_str(&format!("%{}%\n{}\n\n", key, values.join("\n")));
+            }
+        }
+        out
+    }
+
+    /// Writes the `files` entry for a sync database.
+    pub(crate) fn write_files(&self) -> String {
+        if self.files.is_empty() {
+            String::new()
+        } else {
+            format!("%FILES%\n{}\n\n", self.files.join("\n"))
+        }
+    }
+}
+
+/// Splits a metadata file into its `%KEY%` sections.
+///
+/// Returns the (0-based) line number of each key along with the key (without `%`) and its
+/// values. A section ends at the first blank line.
+pub(crate) fn parse_sections(filename: &str, text: &str)
+    -> Result<Vec<(usize, String, Vec<String>)>, Error>
+{
+    let mut sections = Vec::new();
+    let mut current: Option<(usize, String, Vec<String>)> = None;
+    for (line_no, line) in text.lines().enumerate() {
+        if line.is_empty() {
+            if let Some(section) = current.take() {
+                sections.push(section);
+            }
+        } else if let Some((_, _, ref mut values)) = current {
+            values.push(line.into());
+        } else if line.len() > 2 && line.starts_with('%') && line.ends_with('%') {
+            current = Some((line_no, line[1..line.len() - 1].into(), Vec::new()));
+        } else {
+            return Err(Error::Parse(filename.into(), line_no + 1, line.into()));
+        }
+    }
+    if let Some(section) = current.take() {
+        sections.push(section);
+    }
+    Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_desc() {
+        let desc = "%NAME%\nzlib\n\n%VERSION%\n1:1.2.11-1\n\n%SIZE%\n337920\n\n\
+                    %REASON%\n1\n\n%LICENSE%\ncustom\n\n%DEPENDS%\nglibc\n\n";
+        let files = "%FILES%\nusr/\nusr/lib/libz.so\n\n%BACKUP%\netc/z.conf\t0123abcd\n\n";
+        let mut pkg = PackageRecord::default();
+        pkg.add_metadata("desc", desc).unwrap();
+        pkg.add_metadata("files", files).unwrap();
+        assert_eq!(pkg.name, "zlib");
+        assert_eq!(pkg.version, "1:1.2.11-1");
+        assert_eq!(pkg.installed_size, Some(337920));
+        assert_eq!(pkg.reason, Some(Reason::Depend));
+        assert_eq!(pkg.depends, vec![Depend::parse("glibc")]);
+        assert_eq!(pkg.files, vec!["usr/", "usr/lib/libz.so"]);
+        assert_eq!(pkg.backup[0].hash, "0123abcd");
+        assert!(pkg.add_metadata("desc", "zlib\n").is_err());
+        assert!(pkg.add_metadata("desc", "%SIZE%\nbig\n").is_err());
+
+        let mut written = PackageRecord::default();
+        written.add_metadata("desc", &pkg.write_desc()).unwrap();
+        written.add_metadata("files", &pkg.write_files()).unwrap();
+        assert_eq!(written.depends, pkg.depends);
+        assert_eq!(written.files, pkg.files);
+        assert_eq!(written.installed_size, pkg.installed_size);
+    }
+}
@@ -0,0 +1,470 @@
+//! A lossless model of a pacman-style INI file.
+//!
+//! `parse_ini` flattens a file (and everything it includes) into a `Config`, which is fine for
+//! reading but throws away everything needed to write the file back. A `Document` instead keeps
+//! every line as it was written - comments, blank lines, ordering and `Include` directives - and
+//! only touches the lines that are edited.
+//!
+//! ```ignore
+//! use libalpm_utils::document::Document;
+//!
+//! let mut doc = Document::from_file("/etc/pacman.conf").unwrap();
+//! doc.set_ignore_pkgs(&["linux", "linux-headers"]);
+//! doc.add_repo("internal");
+//! doc.add_server("internal", "https://repo.example.com/$repo/os/$arch");
+//! doc.write_to_file("/etc/pacman.conf").unwrap();
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::io::prelude::*;
+
+use libalpm::Config;
+
+use ini::{self, Error, Section, Token};
+
+/// A single line of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    /// The line exactly as it appears in the file, without the newline.
+    raw: String,
+    /// The lexed line, or `None` for blank lines and comments.
+    token: Option<Token>,
+}
+
+impl Line {
+    /// Lexes a line of text.
+    pub fn new(raw: &str) -> Line {
+        Line {
+            raw: raw.into(),
+            token: ini::lex_line(raw),
+        }
+    }
+
+    fn header(name: &str) -> Line {
+        Line::new(&format!("[{}]", name))
+    }
+
+    fn pair(key: &str, value: &str) -> Line {
+        Line::new(&format!("{} = {}", key, value))
+    }
+
+    /// The line exactly as it will be written out.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// The lexed line, or `None` for blank lines and comments.
+    pub fn token(&self) -> Option<&Token> {
+        self.token.as_ref()
+    }
+
+    /// Gets the key of a pair or valueless line.
+    fn key(&self) -> Option<&str> {
+        match self.token {
+            Some(Token::Pair(ref key, _)) | Some(Token::Valueless(ref key)) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Returns true if this is a comment containing a (disabled) line with the given key, e.g.
+    /// `#IgnorePkg   =` in the default pacman.conf.
+    fn is_commented_key(&self, key: &str) -> bool {
+        if self.token.is_some() {
+            return false;
+        }
+        let uncommented = self.raw.trim().trim_start_matches('#');
+        match ini::lex_line(uncommented) {
+            Some(Token::Pair(ref k, _)) | Some(Token::Valueless(ref k)) => k == key,
+            _ => false,
+        }
+    }
+
+    /// Replaces the value of a pair, keeping the key and its padding as they were written.
+    fn set_value(&mut self, value: &str) {
+        let raw = match self.raw.find('=') {
+            Some(idx) => {
+                let (key, rest) = self.raw.split_at(idx + 1);
+                let padding = &rest[..rest.len() - rest.trim_start().len()];
+                format!("{}{}{}", key, padding, value)
+            }
+            None => format!("{} = {}", self.raw, value),
+        };
+        *self = Line::new(&raw);
+    }
+}
+
+/// A pacman-style INI file that can be edited and written back without losing comments,
+/// ordering or `Include` lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document {
+    lines: Vec<Line>,
+    /// Whether the last line was terminated by a newline.
+    trailing_newline: bool,
+}
+
+impl Document {
+    /// Parses the text of an INI file. This never fails, lines that are not understood are kept
+    /// as they are.
+    pub fn parse(text: &str) -> Document {
+        let (raw_lines, trailing_newline) = split_lines(text);
+        Document {
+            lines: raw_lines.into_iter().map(Line::new).collect(),
+            trailing_newline: trailing_newline,
+        }
+    }
+
+    /// Reads and parses an INI file. `Include` directives are kept, not expanded.
+    pub fn from_file(filename: &str) -> Result<Document, Error> {
+        let mut text = String::new();
+        fs::File::open(filename)?.read_to_string(&mut text)?;
+        Ok(Document::parse(&text))
+    }
+
+    /// Writes the document to a file, replacing it atomically.
+    pub fn write_to_file(&self, filename: &str) -> Result<(), Error> {
+        write_atomic(filename, &self.to_string())
+    }
+
+    /// Gets all the lines in the document.
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Gets the names of all sections in the order they appear.
+    pub fn sections(&self) -> Vec<&str> {
+        self.lines.iter().filter_map(|line| match line.token {
+            Some(Token::Header(ref name)) => Some(name.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    /// Whether the document has a section with the given name.
+    pub fn has_section(&self, section: &str) -> bool {
+        self.section_range(section).is_some()
+    }
+
+    /// Gets the first value for a key in a section.
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.get_all(section, key).into_iter().next()
+    }
+
+    /// Gets every value for a key in a section, in order.
+    pub fn get_all(&self, section: &str, key: &str) -> Vec<&str> {
+        let (start, end) = match self.section_range(section) {
+            Some(range) => range,
+            None => return Vec::new(),
+        };
+        self.lines[start..end].iter().filter_map(|line| match line.token {
+            Some(Token::Pair(ref k, ref value)) if k == key => Some(value.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    /// Whether a valueless key (e.g. `Color`) is set in a section.
+    pub fn has_flag(&self, section: &str, key: &str) -> bool {
+        match self.section_range(section) {
+            Some((start, end)) => self.lines[start..end].iter().any(|line| match line.token {
+                Some(Token::Valueless(ref k)) => k == key,
+                _ => false,
+            }),
+            None => false,
+        }
+    }
+
+    /// Sets the value of a key in a section.
+    ///
+    /// The first existing line for the key is rewritten in place and any later lines for the
+    /// same key are removed. If the key is not present it is added, either just after a
+    /// commented out line for the key (e.g. `#IgnorePkg   =`) or at the end of the section. The
+    /// section is created if it doesn't exist.
+    pub fn set(&mut self, section: &str, key: &str, value: &str) {
+        let (start, end) = self.section_range_or_create(section);
+        let mut existing = (start..end).filter(|&idx| match self.lines[idx].token {
+            Some(Token::Pair(ref k, _)) => k == key,
+            _ => false,
+        });
+        match existing.next() {
+            Some(first) => {
+                let rest: Vec<usize> = existing.collect();
+                self.lines[first].set_value(value);
+                for idx in rest.into_iter().rev() {
+                    self.lines.remove(idx);
+                }
+            }
+            None => {
+                let idx = self.insert_position(start, end, key);
+                self.lines.insert(idx, Line::pair(key, value));
+            }
+        }
+    }
+
+    /// Adds a value for a key in a section, after any existing lines for that key. Use this for
+    /// keys that can appear more than once, like `Server` or `Include`.
+    pub fn append(&mut self, section: &str, key: &str, value: &str) {
+        let (start, end) = self.section_range_or_create(section);
+        let idx = self.insert_position(start, end, key);
+        self.lines.insert(idx, Line::pair(key, value));
+    }
+
+    /// Sets or clears a valueless key (e.g. `Color`) in a section.
+    pub fn set_flag(&mut self, section: &str, key: &str, enabled: bool) {
+        if enabled {
+            if !self.has_flag(section, key) {
+                let (start, end) = self.section_range_or_create(section);
+                let idx = self.insert_position(start, end, key);
+                self.lines.insert(idx, Line::new(key));
+            }
+        } else {
+            self.remove(section, key);
+        }
+    }
+
+    /// Removes every line for a key in a section. Returns the number of lines removed.
+    pub fn remove(&mut self, section: &str, key: &str) -> usize {
+        let (start, end) = match self.section_range(section) {
+            Some(range) => range,
+            None => return 0,
+        };
+        let to_remove: Vec<usize> = (start..end)
+            .filter(|&idx| self.lines[idx].key() == Some(key))
+            .collect();
+        for idx in to_remove.iter().rev() {
+            self.lines.remove(*idx);
+        }
+        to_remove.len()
+    }
+
+    /// Adds an empty section at the end of the document, if it doesn't already exist.
+    pub fn add_section(&mut self, section: &str) {
+        if !self.has_section(section) {
+            if self.lines.last().map(|line| !line.raw.trim().is_empty()).unwrap_or(false) {
+                self.lines.push(Line::new(""));
+            }
+            self.lines.push(Line::header(section));
+            self.trailing_newline = true;
+        }
+    }
+
+    /// Removes a section and all its keys. Returns false if the section didn't exist.
+    ///
+    /// Comments and blank lines directly above the next section are assumed to belong to it, and
+    /// are kept.
+    pub fn remove_section(&mut self, section: &str) -> bool {
+        let (start, end) = match self.section_range(section) {
+            Some(range) => range,
+            None => return false,
+        };
+        let mut end = end;
+        if end < self.lines.len() {
+            while end > start + 1 && self.lines[end - 1].token.is_none() {
+                end -= 1;
+            }
+        }
+        self.lines.drain(start - 1..end);
+        true
+    }
+
+    /// Adds a repository section (e.g. `[internal]`), if it doesn't already exist.
+    pub fn add_repo(&mut self, name: &str) {
+        self.add_section(name);
+    }
+
+    /// Adds a mirror to a repository, creating the repository if necessary.
+    pub fn add_server(&mut self, repo: &str, url: &str) {
+        self.append(repo, "Server", url);
+    }
+
+    /// Replaces the packages in `IgnorePkg` in the options section. An empty list removes the
+    /// key.
+    pub fn set_ignore_pkgs(&mut self, pkgs: &[&str]) {
+        let section = self.options_section().to_owned();
+        if pkgs.is_empty() {
+            self.remove(&section, "IgnorePkg");
+        } else {
+            self.set(&section, "IgnorePkg", &pkgs.join(" "));
+        }
+    }
+
+    /// Converts the document into a `Config`, expanding `Include` directives exactly like
+    /// `parse_ini`.
+    pub fn to_config(&self) -> Result<Config, Error> {
+        let mut section = Section::None;
+        let mut config = Config::default();
+        for line in self.lines.iter() {
+            match line.token {
+                None => (),
+                Some(Token::Pair(ref key, ref value)) if key == "Include" => {
+                    // silently ignore if file doesn't exist, like `parse_ini`
+                    let toks = match ini::lex_ini(value) {
+                        Ok(toks) => toks,
+                        Err(Error::Io(_)) => Vec::new(),
+                        Err(e) => { return Err(e); }
+                    };
+                    for tok in toks.into_iter() {
+                        ini::parse_token(tok, &mut config, &mut section)?;
+                    }
+                }
+                Some(ref tok) => ini::parse_token(tok.clone(), &mut config, &mut section)?,
+            }
+        }
+        Ok(config)
+    }
+
+    /// The name used for the options section, "options" unless the file spells it differently.
+    fn options_section(&self) -> &str {
+        self.sections().into_iter()
+            .find(|name| *name == "options" || *name == "Options")
+            .unwrap_or("options")
+    }
+
+    /// Gets the line indices of the body of a section (excluding the header).
+    fn section_range(&self, section: &str) -> Option<(usize, usize)> {
+        let start = self.lines.iter().position(|line| match line.token {
+            Some(Token::Header(ref name)) => name == section,
+            _ => false,
+        })? + 1;
+        let end = self.lines[start..].iter()
+            .position(|line| match line.token {
+                Some(Token::Header(_)) => true,
+                _ => false,
+            })
+            .map(|idx| idx + start)
+            .unwrap_or(self.lines.len());
+        Some((start, end))
+    }
+
+    fn section_range_or_create(&mut self, section: &str) -> (usize, usize) {
+        self.add_section(section);
+        self.section_range(section).expect("section was just added")
+    }
+
+    /// Works out where a new line for `key` should go in the section body `start..end`: after the
+    /// last line for that key, else after a commented out line for it, else after the last
+    /// non-comment line of the section.
+    fn insert_position(&self, start: usize, end: usize, key: &str) -> usize {
+        let body = &self.lines[start..end];
+        if let Some(idx) = body.iter().rposition(|line| line.key() == Some(key)) {
+            return start + idx + 1;
+        }
+        if let Some(idx) = body.iter().rposition(|line| line.is_commented_key(key)) {
+            return start + idx + 1;
+        }
+        match body.iter().rposition(|line| line.token.is_some()) {
+            Some(idx) => start + idx + 1,
+            // an empty section, so directly after the header
+            None => start,
+        }
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_lines(f, self.lines.iter().map(|line| line.raw()), self.trailing_newline)
+    }
+}
+
+/// Splits text into lines, without their newlines. Also returns whether the last line had a
+/// newline.
+pub(crate) fn split_lines(text: &str) -> (Vec<&str>, bool) {
+    let mut raw_lines: Vec<&str> = text.split('\n').collect();
+    // `split` gives a final empty string if the text ended with a newline
+    let trailing_newline = raw_lines.len() > 1 && raw_lines.last() == Some(&"");
+    if trailing_newline || text.is_empty() {
+        raw_lines.pop();
+    }
+    (raw_lines, trailing_newline)
+}
+
+/// The inverse of `split_lines`.
+pub(crate) fn write_lines<'a, I>(f: &mut fmt::Formatter, lines: I, trailing_newline: bool)
+    -> fmt::Result
+    where I: Iterator<Item=&'a str>
+{
+    let mut empty = true;
+    for line in lines {
+        if !empty {
+            f.write_str("\n")?;
+        }
+        f.write_str(line)?;
+        empty = false;
+    }
+    if trailing_newline && !empty {
+        f.write_str("\n")?;
+    }
+    Ok(())
+}
+
+/// Writes a file by writing a temporary file next to it and then renaming it over the original,
+/// so a failure part way through never leaves a truncated file behind.
+pub(crate) fn write_atomic<T: AsRef<[u8]>>(filename: &str, data: T) -> Result<(), Error> {
+    let tmp_filename = format!("{}.tmp", filename);
+    {
+        let mut file = fs::File::create(&tmp_filename)?;
+        file.write_all(data.as_ref())?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_filename, filename)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONF: &'static str = "\
+# /etc/pacman.conf
+[options]
+HoldPkg     = pacman glibc
+#IgnorePkg   =
+Architecture = auto
+
+# The testing repositories are disabled by default.
+#[testing]
+#Include = /etc/pacman.d/mirrorlist
+
+[core]
+Include = /etc/pacman.d/mirrorlist
+";
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(Document::parse(CONF).to_string(), CONF);
+        assert_eq!(Document::parse("[options]").to_string(), "[options]");
+        assert_eq!(Document::parse("").to_string(), "");
+    }
+
+    #[test]
+    fn edit() {
+        let mut doc = Document::parse(CONF);
+        doc.set_ignore_pkgs(&["linux", "linux-headers"]);
+        doc.set("options", "HoldPkg", "pacman");
+        doc.add_server("core", "https://mirror.example.com/$repo/os/$arch");
+        doc.add_repo("internal");
+        doc.add_server("internal", "file:///srv/repo");
+        assert_eq!(doc.get("options", "IgnorePkg"), Some("linux linux-headers"));
+        assert_eq!(doc.to_string(), "\
+# /etc/pacman.conf
+[options]
+HoldPkg     = pacman
+#IgnorePkg   =
+IgnorePkg = linux linux-headers
+Architecture = auto
+
+# The testing repositories are disabled by default.
+#[testing]
+#Include = /etc/pacman.d/mirrorlist
+
+[core]
+Include = /etc/pacman.d/mirrorlist
+Server = https://mirror.example.com/$repo/os/$arch
+
+[internal]
+Server = file:///srv/repo
+");
+        assert!(doc.remove_section("internal"));
+        doc.set_ignore_pkgs(&[]);
+        assert_eq!(doc.get("options", "IgnorePkg"), None);
+        assert_eq!(doc.sections(), vec!["options", "core"]);
+    }
+}
@@ -0,0 +1,438 @@
+//! Parsing alpm hook files (`*.hook`) and working out which hooks a transaction will run.
+//!
+//! See `alpm-hooks(5)` for the file format. Hooks are evaluated against an owned `Plan` of the
+//! transaction, which can be built from a libalpm `Transaction` or by hand.
+//!
+//! ```ignore
+//! use libalpm_utils::hook::{self, Plan};
+//!
+//! let hooks = hook::load_hooks(&["/usr/share/libalpm/hooks", "/etc/pacman.d/hooks"]).unwrap();
+//! let plan = Plan::from_transaction(&alpm, &transaction);
+//! for m in hook::evaluate(&hooks, &plan) {
+//!     println!("{} would run with {:?}", m.hook.name, m.targets);
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::prelude::*;
+use std::path::Path;
+
+use glob::Pattern;
+use libalpm::{Alpm, PackageRef, Transaction};
+
+use ini::{self, Error, Token};
+
+/// The kind of transaction operation a trigger matches.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Install,
+    Upgrade,
+    Remove,
+}
+
+/// What a trigger's targets are matched against.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TriggerType {
+    /// Package names.
+    Package,
+    /// File paths, relative to the root (e.g. `usr/lib/modules/*`). `File` is accepted as an
+    /// old name for this.
+    Path,
+}
+
+/// When a hook runs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum When {
+    PreTransaction,
+    PostTransaction,
+}
+
+/// A `[Trigger]` section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trigger {
+    pub kind: TriggerType,
+    pub operations: Vec<Operation>,
+    /// Glob patterns. A pattern starting with `!` excludes what it matches, and the last
+    /// pattern that matches wins.
+    pub targets: Vec<String>,
+}
+
+impl Trigger {
+    /// Whether a package name or path matches the targets.
+    pub fn matches(&self, target: &str) -> bool {
+        for pattern in self.targets.iter().rev() {
+            let (negated, pattern) = if pattern.starts_with('!') {
+                (true, &pattern[1..])
+            } else if pattern.starts_with('\\') {
+                (false, &pattern[1..])
+            } else {
+                (false, &pattern[..])
+            };
+            if Pattern::new(pattern).map(|p| p.matches(target)).unwrap_or(false) {
+                return !negated;
+            }
+        }
+        false
+    }
+}
+
+/// A parsed hook file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hook {
+    /// The file name without `.hook`, which decides the order hooks run in.
+    pub name: String,
+    pub triggers: Vec<Trigger>,
+    pub description: Option<String>,
+    pub when: When,
+    /// The command to run, split on whitespace.
+    pub exec: Vec<String>,
+    /// Packages that must be installed for the hook to run.
+    pub depends: Vec<String>,
+    /// Whether to abort the transaction if the hook fails (only for `PreTransaction` hooks).
+    pub abort_on_fail: bool,
+    /// Whether the matched targets are passed to the command on stdin.
+    pub needs_targets: bool,
+}
+
+impl Hook {
+    /// Parses the text of a hook file. `filename` is used for the hook name and in error
+    /// messages.
+    pub fn parse(filename: &str, text: &str) -> Result<Hook, Error> {
+        let name = Path::new(filename).file_name().and_then(|n| n.to_str()).unwrap_or(filename);
+        let mut triggers: Vec<Trigger> = Vec::new();
+        let mut section = None;
+        let mut description = None;
+        let mut when = None;
+        let mut exec = Vec::new();
+        let mut depends = Vec::new();
+        let mut abort_on_fail = false;
+        let mut needs_targets = false;
+        // whether each trigger has a type, for error checking
+        let mut trigger_types = Vec::new();
+        for (line_no, line) in text.lines().enumerate() {
+            let err = |msg: &str| Error::Parse(filename.into(), line_no + 1, msg.into());
+            let tok = match ini::lex_line(line) {
+                Some(tok) => tok,
+                None => continue,
+            };
+            match (section, tok) {
+                (_, Token::Header(ref name)) if name == "Trigger" => {
+                    triggers.push(Trigger {
+                        kind: TriggerType::Package,
+                        operations: Vec::new(),
+                        targets: Vec::new(),
+                    });
+                    trigger_types.push(false);
+                    section = Some("Trigger");
+                }
+                (_, Token::Header(ref name)) if name == "Action" => section = Some("Action"),
+                (_, Token::Header(_)) => return Err(err("unknown section")),
+                (Some("Trigger"), Token::Pair(key, value)) => {
+                    let trigger = triggers.last_mut().unwrap();
+                    match key.as_str() {
+                        "Operation" => trigger.operations.push(match value.as_str() {
+                            "Install" => Operation::Install,
+                            "Upgrade" => Operation::Upgrade,
+                            "Remove" => Operation::Remove,
+                            _ => return Err(err("invalid Operation")),
+                        }),
+                        "Type" => {
+                            trigger.kind = match value.as_str() {
+                                "Package" => TriggerType::Package,
+                                "Path" | "File" => TriggerType::Path,
+                                _ => return Err(err("invalid Type")),
+                            };
+                            *trigger_types.last_mut().unwrap() = true;
+                        }
+                        "Target" => trigger.targets.push(value),
+                        _ => return Err(err("unknown option")),
+                    }
+                }
+                (Some("Action"), Token::Pair(key, value)) => match key.as_str() {
+                    "Description" => description = Some(value),
+                    "When" => when = Some(match value.as_str() {
+                        "PreTransaction" => When::PreTransaction,
+                        "PostTransaction" => When::PostTransaction,
+                        _ => return Err(err("invalid When")),
+                    }),
+                    "Exec" => exec = value.split_whitespace().map(|s| s.to_owned()).collect(),
+                    "Depends" => depends.push(value),
+                    _ => return Err(err("unknown option")),
+                },
+                (Some("Action"), Token::Valueless(ref key)) if key == "AbortOnFail" => {
+                    abort_on_fail = true;
+                }
+                (Some("Action"), Token::Valueless(ref key)) if key == "NeedsTargets" => {
+                    needs_targets = true;
+                }
+                (None, _) => return Err(err("option outside of a section")),
+                _ => return Err(err("unknown option")),
+            }
+        }
+
+        let err = |msg: &str| Error::Parse(filename.into(), 0, msg.into());
+        if triggers.is_empty() {
+            return Err(err("missing [Trigger]"));
+        }
+        for (trigger, has_type) in triggers.iter().zip(trigger_types) {
+            if !has_type || trigger.operations.is_empty() || trigger.targets.is_empty() {
+                return Err(err("a trigger needs a Type, an Operation and a Target"));
+            }
+        }
+        let when = when.ok_or_else(|| err("missing When"))?;
+        if exec.is_empty() {
+            return Err(err("missing Exec"));
+        }
+        if abort_on_fail && when != When::PreTransaction {
+            return Err(err("AbortOnFail is only allowed for PreTransaction hooks"));
+        }
+        Ok(Hook {
+            name: name.trim_end_matches(".hook").into(),
+            triggers: triggers,
+            description: description,
+            when: when,
+            exec: exec,
+            depends: depends,
+            abort_on_fail: abort_on_fail,
+            needs_targets: needs_targets,
+        })
+    }
+
+    /// Reads and parses a hook file.
+    pub fn from_file(filename: &str) -> Result<Hook, Error> {
+        let mut text = String::new();
+        fs::File::open(filename)?.read_to_string(&mut text)?;
+        Hook::parse(filename, &text)
+    }
+}
+
+/// Loads the hooks from hook directories (e.g. `Config::hook_dirs`), sorted by name.
+///
+/// Like libalpm, a hook in a later directory replaces one with the same file name in an earlier
+/// directory, and a hook that is a symlink to `/dev/null` disables it. Missing directories and
+/// files not ending in `.hook` are skipped.
+pub fn load_hooks<P: AsRef<Path>>(dirs: &[P]) -> Result<Vec<Hook>, Error> {
+    let mut hooks: BTreeMap<String, Option<Hook>> = BTreeMap::new();
+    for dir in dirs {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(ref e) if e.kind() == ::std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            let file_name = match path.file_name().and_then(|n| n.to_str()) {
+                Some(name) if name.ends_with(".hook") => name.to_owned(),
+                _ => continue,
+            };
+            let disabled = fs::read_link(&path).map(|target| target == Path::new("/dev/null"));
+            if disabled.unwrap_or(false) {
+                hooks.insert(file_name, None);
+            } else if path.is_file() {
+                hooks.insert(file_name, Some(Hook::from_file(&path.display().to_string())?));
+            }
+        }
+    }
+    Ok(hooks.into_iter().filter_map(|(_, hook)| hook).collect())
+}
+
+/// A package in a transaction plan.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PlanPackage {
+    pub name: String,
+    /// The package's files, relative to the root. Directories end in `/`.
+    pub files: Vec<String>,
+}
+
+/// The packages a transaction will install, upgrade and remove.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Plan {
+    /// The packages being added, each with the installed package it replaces (if it is an
+    /// upgrade, downgrade or reinstall).
+    pub added: Vec<(PlanPackage, Option<PlanPackage>)>,
+    pub removed: Vec<PlanPackage>,
+}
+
+impl Plan {
+    /// Builds a plan from a libalpm transaction, using `Transaction::added_packages` and
+    /// `Transaction::removed_packages`, and the local database for what is installed.
+    ///
+    /// Packages from sync databases only have file lists if the `.files` databases are in use,
+    /// so `Path` triggers may not match before the packages are loaded.
+    pub fn from_transaction<'a, S: ::std::any::Any>(alpm: &'a Alpm,
+                                                   trans: &'a Transaction<'a, S>)
+        -> Plan
+    {
+        let local = alpm.local_db();
+        Plan {
+            added: trans.added_packages().into_iter()
+                .map(|pkg| (plan_package(pkg), local.pkg(pkg.name()).ok().map(plan_package)))
+                .collect(),
+            removed: trans.removed_packages().into_iter().map(plan_package).collect(),
+        }
+    }
+}
+
+fn plan_package(pkg: &PackageRef) -> PlanPackage {
+    PlanPackage {
+        name: pkg.name().into(),
+        files: pkg.files().list.iter().map(|file| file.name.to_owned()).collect(),
+    }
+}
+
+/// A hook that a transaction will run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookMatch<'a> {
+    pub hook: &'a Hook,
+    /// The matched package names or paths, sorted and without duplicates. These are passed to
+    /// the hook if it `NeedsTargets`.
+    pub targets: Vec<String>,
+}
+
+/// Works out which hooks would run for a transaction, in the order libalpm runs them
+/// (`PreTransaction` hooks first, each group sorted by name).
+pub fn evaluate<'a>(hooks: &'a [Hook], plan: &Plan) -> Vec<HookMatch<'a>> {
+    let mut matches = Vec::new();
+    for &when in &[When::PreTransaction, When::PostTransaction] {
+        let mut sorted: Vec<&Hook> = hooks.iter().filter(|hook| hook.when == when).collect();
+        sorted.sort_by(|a, b| a.name.cmp(&b.name));
+        for hook in sorted {
+            let mut targets = Vec::new();
+            for trigger in &hook.triggers {
+                targets.extend(candidates(trigger, plan).into_iter()
+                    .filter(|target| trigger.matches(target)));
+            }
+            if !targets.is_empty() {
+                targets.sort();
+                targets.dedup();
+                matches.push(HookMatch {
+                    hook: hook,
+                    targets: targets,
+                });
+            }
+        }
+    }
+    matches
+}
+
+/// The package names or paths a trigger's operations apply to.
+fn candidates(trigger: &Trigger, plan: &Plan) -> Vec<String> {
+    let has = |op| trigger.operations.contains(&op);
+    let mut out = Vec::new();
+    match trigger.kind {
+        TriggerType::Package => {
+            for &(ref new, ref old) in &plan.added {
+                if (old.is_none() && has(Operation::Install))
+                    || (old.is_some() && has(Operation::Upgrade))
+                {
+                    out.push(new.name.clone());
+                }
+            }
+            if has(Operation::Remove) {
+                out.extend(plan.removed.iter().map(|pkg| pkg.name.clone()));
+            }
+        }
+        TriggerType::Path => {
+            for &(ref new, ref old) in &plan.added {
+                let old_files: &[String] = old.as_ref().map(|o| &o.files[..]).unwrap_or(&[]);
+                for file in &new.files {
+                    let op = if old_files.contains(file) {
+                        Operation::Upgrade
+                    } else {
+                        Operation::Install
+                    };
+                    if has(op) {
+                        out.push(file.clone());
+                    }
+                }
+                if has(Operation::Remove) {
+                    out.extend(old_files.iter().filter(|f| !new.files.contains(f)).cloned());
+                }
+            }
+            if has(Operation::Remove) {
+                for pkg in &plan.removed {
+                    out.extend(pkg.files.iter().cloned());
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOOK: &'static str = "\
+[Trigger]
+Type = Path
+Operation = Install
+Operation = Upgrade
+Target = usr/lib/modules/*/vmlinuz
+Target = !usr/lib/modules/*-lts/vmlinuz
+
+[Trigger]
+Type = Package
+Operation = Remove
+Target = linux
+
+[Action]
+Description = Updating linux initcpios...
+When = PostTransaction
+Exec = /usr/share/libalpm/scripts/mkinitcpio-install
+NeedsTargets
+";
+
+    fn package(name: &str, files: &[&str]) -> PlanPackage {
+        PlanPackage {
+            name: name.into(),
+            files: files.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let hook = Hook::parse("/etc/pacman.d/hooks/90-mkinitcpio.hook", HOOK).unwrap();
+        assert_eq!(hook.name, "90-mkinitcpio");
+        assert_eq!(hook.triggers.len(), 2);
+        assert_eq!(hook.triggers[0].kind, TriggerType::Path);
+        assert_eq!(hook.triggers[0].operations, vec![Operation::Install, Operation::Upgrade]);
+        assert_eq!(hook.when, When::PostTransaction);
+        assert_eq!(hook.exec, vec!["/usr/share/libalpm/scripts/mkinitcpio-install"]);
+        assert!(hook.needs_targets);
+        assert!(!hook.abort_on_fail);
+
+        assert!(hook.triggers[0].matches("usr/lib/modules/4.13.3-1-ARCH/vmlinuz"));
+        assert!(!hook.triggers[0].matches("usr/lib/modules/4.9.51-1-lts/vmlinuz"));
+        assert!(!hook.triggers[0].matches("usr/bin/bash"));
+
+        assert!(Hook::parse("a.hook", &HOOK.replace("When = PostTransaction", "")).is_err());
+        assert!(Hook::parse("a.hook", &HOOK.replace("NeedsTargets", "AbortOnFail")).is_err());
+        assert!(Hook::parse("a.hook", &HOOK.replace("Type = Path", "Type = Dir")).is_err());
+        assert!(Hook::parse("a.hook", &HOOK.replace("Type = Package\n", "")).is_err());
+    }
+
+    #[test]
+    fn evaluate_plan() {
+        let hooks = vec![Hook::parse("mkinitcpio.hook", HOOK).unwrap()];
+        let kernel = "usr/lib/modules/4.13.3-1-ARCH/vmlinuz";
+        let plan = Plan {
+            added: vec![
+                (package("linux", &["usr/", kernel]), Some(package("linux", &["usr/", kernel]))),
+                (package("linux-lts", &["usr/lib/modules/4.9.51-1-lts/vmlinuz"]), None),
+            ],
+            removed: vec![package("bash", &["usr/bin/bash"])],
+        };
+        let matches = evaluate(&hooks, &plan);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].targets, vec![kernel]);
+
+        let plan = Plan {
+            added: Vec::new(),
+            removed: vec![package("linux", &[kernel])],
+        };
+        assert_eq!(evaluate(&hooks, &plan)[0].targets, vec!["linux"]);
+        assert!(evaluate(&hooks, &Plan::default()).is_empty());
+    }
+}
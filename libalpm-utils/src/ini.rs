@@ -4,7 +4,7 @@ use std::io;
 use std::io::prelude::*;
 
 use nom::{IResult};
-use libalpm::{Config, RepoConfig};
+use libalpm::{Config, RepoConfig, CleanMethod};
 
 /// Library error type
 #[derive(Debug)]
@@ -21,8 +21,8 @@ impl From<io::Error> for Error {
 }
 
 /// A token TODO include file/line no.
-#[derive(Debug)]
-enum Token {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
     /// Start of a new section
     Header(String),
     /// A key/value pair
@@ -33,7 +33,7 @@ enum Token {
 
 /// What section we are in
 #[derive(Debug)]
-enum Section {
+pub(crate) enum Section {
     /// No section set yet
     None,
     /// In options section
@@ -62,7 +62,7 @@ named!(parse_key<&str, &str>, do_parse!(
 ///
 /// Returns a list of tokens. `Include` directives are expanded.
 ///
-fn lex_ini(filename: &str) -> Result<Vec<Token>, Error> {
+pub(crate) fn lex_ini(filename: &str) -> Result<Vec<Token>, Error> {
 
     let mut tok_list = Vec::new();
 
@@ -72,15 +72,10 @@ fn lex_ini(filename: &str) -> Result<Vec<Token>, Error> {
 
     for line in config_reader.lines() {
         let line = line?;
-        let line = line.trim();
-
-        if line.is_empty() || line.starts_with("#") {
-            // skip comments
-        } else if let IResult::Done(_, name) = parse_header(&line) {
-            tok_list.push(Token::Header(name.into()))
-        } else if let IResult::Done(value, key) = parse_key(&line) {
-            let value = value.trim();
-            if key == "Include" {
+
+        match lex_line(&line) {
+            None => (), // skip comments
+            Some(Token::Pair(ref key, ref value)) if key == "Include" => {
                 // Then we include the tokens from the filename in value into the token list
                 // silently ignore if file doesn't exist
                 match lex_ini(value) {
@@ -88,11 +83,8 @@ fn lex_ini(filename: &str) -> Result<Vec<Token>, Error> {
                     Err(Error::Io(_)) => (), // ignore
                     Err(e) => { return Err(e); }
                 }
-            } else {
-                tok_list.push(Token::Pair(key.into(), value.into()));
             }
-        } else {
-            tok_list.push(Token::Valueless(line.into()));
+            Some(tok) => tok_list.push(tok),
         }
 
     }
@@ -100,6 +92,24 @@ fn lex_ini(filename: &str) -> Result<Vec<Token>, Error> {
     Ok(tok_list)
 }
 
+/// Lexes a single line of a pacman-style INI file.
+///
+/// Returns `None` for blank lines and comments. `Include` directives are returned as a normal
+/// `Token::Pair`.
+pub fn lex_line(line: &str) -> Option<Token> {
+    let line = line.trim();
+
+    if line.is_empty() || line.starts_with("#") {
+        None
+    } else if let IResult::Done(_, name) = parse_header(&line) {
+        Some(Token::Header(name.into()))
+    } else if let IResult::Done(value, key) = parse_key(&line) {
+        Some(Token::Pair(key.into(), value.trim().into()))
+    } else {
+        Some(Token::Valueless(line.into()))
+    }
+}
+
 /// Parses an ini file into an `Config` object.
 pub fn parse_ini(filename: &str) -> Result<Config, Error> {
     let mut section = Section::None;
@@ -112,7 +122,11 @@ pub fn parse_ini(filename: &str) -> Result<Config, Error> {
 }
 
 /// Parses a single token a token
-fn parse_token(tok: Token, conf: &mut Config, section: &mut Section) -> Result<(), Error> {
+pub(crate) fn parse_token(tok: Token, conf: &mut Config, section: &mut Section) -> Result<(), Error> {
+    // pacman only checks for space when `CheckSpace` is given, so it's off once a config starts
+    if let (&mut Section::None, &Token::Header(_)) = (&mut *section, &tok) {
+        conf.check_space = false;
+    }
     match &tok {
         &Token::Header(ref name) if name == "Options" || name == "options" => {
             *section = Section::Options;
@@ -159,8 +173,12 @@ fn parse_pair_option(key: &str, value: &str, config: &mut Config) {
     } else if key == "HookDir" {
         config.hook_dirs.append(&mut split_whitespace(value));
     } else if key == "Architecture" {
-        // TODO their lib uses first, this uses last
-        config.arch = value.into();
+        // the C lib uses the first Architecture line
+        if config.arch.is_empty() {
+            config.arch = split_whitespace(value);
+        } else {
+            println!("Architecture already set, ignoring \"{}\"", value);
+        }
     } else if key == "UseDelta" {
         if let Ok(parsed_val) = value.parse::<f32>() {
             let parsed_val = match parsed_val {
@@ -188,17 +206,29 @@ fn parse_pair_option(key: &str, value: &str, config: &mut Config) {
         config.log_file = value.into();
     } else if key == "XferCommand" {
         config.transfer_command = Some(value.into());
+    } else if key == "ParallelDownloads" {
+        match value.parse::<u32>() {
+            Ok(n) if n > 0 => config.parallel_downloads = n,
+            _ => println!("ParallelDownloads must be a positive integer, ignoring \"{}\"", value),
+        }
+    } else if key == "DownloadUser" {
+        config.download_user = Some(value.into());
     } else if key == "CleanMethod" {
+        let mut clean_method = CleanMethod { keep_installed: false, keep_current: false };
         for method in value.split_whitespace() {
             if method == "KeepInstalled" {
-                // TODO
+                clean_method.keep_installed = true;
             } else if method == "KeepCurrent" {
-                // TODO
+                clean_method.keep_current = true;
             } else {
                 println!("Unrecognised clean method: \"{}\".", method)
             }
         }
-        // TODO
+        if clean_method.keep_installed || clean_method.keep_current {
+            config.clean_method = clean_method;
+        } else {
+            println!("No valid clean method in \"{}\", ignoring", value);
+        }
     } else if key == "SigLevel" {
         // TODO
     } else if key == "LocalFileSigLevel" {
@@ -226,7 +256,12 @@ fn parse_valueless_option(key: &str, conf: &mut Config) {
     } else if key == "CheckSpace" {
         conf.check_space = true;
     } else if key == "Color" {
-        // todo check if we are a tty
+        // whether to actually use color (e.g. are we a tty) is up to the frontend
+        conf.color = true;
+    } else if key == "DisableDownloadTimeout" {
+        conf.disable_download_timeout = true;
+    } else if key == "NoProgressBar" {
+        conf.no_progress_bar = true;
     } else {
         println!("Unrecognised valueless option: {}.", key)
     }
@@ -249,7 +284,38 @@ fn split_whitespace(s: &str) -> Vec<String> {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn it_works() {
     }
+
+    #[test]
+    fn options() {
+        let mut conf = Config::default();
+        let mut section = Section::None;
+        assert!(conf.check_space);
+        parse_token(Token::Header("options".into()), &mut conf, &mut section).unwrap();
+        assert!(!conf.check_space);
+        parse_pair_option("Architecture", "x86_64 x86_64_v3", &mut conf);
+        parse_pair_option("Architecture", "i686", &mut conf);
+        parse_pair_option("CleanMethod", "KeepCurrent", &mut conf);
+        parse_pair_option("ParallelDownloads", "5", &mut conf);
+        parse_pair_option("ParallelDownloads", "0", &mut conf);
+        parse_pair_option("DownloadUser", "alpm", &mut conf);
+        parse_valueless_option("Color", &mut conf);
+        parse_valueless_option("NoProgressBar", &mut conf);
+        assert_eq!(conf.arch, vec!["x86_64", "x86_64_v3"]);
+        assert_eq!(conf.clean_method, CleanMethod::keep_current());
+        assert_eq!(conf.parallel_downloads, 5);
+        assert_eq!(conf.download_user, Some("alpm".into()));
+        assert!(conf.color);
+        assert!(conf.no_progress_bar);
+        assert!(!conf.check_space);
+        assert!(!conf.disable_download_timeout);
+
+        parse_token(Token::Valueless("CheckSpace".into()), &mut conf, &mut section).unwrap();
+        parse_token(Token::Header("core".into()), &mut conf, &mut section).unwrap();
+        assert!(conf.check_space);
+    }
 }
@@ -3,6 +3,31 @@
 //!
 //! VERY MUCH WIP
 #[macro_use] extern crate nom;
+extern crate flate2;
+extern crate tar;
+extern crate xz2;
+extern crate zstd;
+extern crate md5;
+extern crate sha2;
+extern crate base64;
+extern crate glob;
+extern crate chrono;
 extern crate libalpm;
 
 pub mod ini;
+pub mod document;
+pub mod mirrorlist;
+pub mod depend;
+pub mod desc;
+pub mod local_db;
+pub mod archive;
+pub mod sync_db;
+pub mod pkginfo;
+pub mod srcinfo;
+pub mod makepkg_conf;
+pub mod hook;
+pub mod pacman_log;
+pub mod repo_db;
+
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,322 @@
+//! A pure-rust reader for the local database (`/var/lib/pacman/local`).
+//!
+//! This doesn't need a libalpm handle, and doesn't take the database lock, so it can be used by
+//! read-only tools while pacman is running. The format is the one libalpm uses for
+//! `ALPM_DB_VERSION` 9: a directory per package named `name-version`, each containing `desc`,
+//! `files` and a gzipped `mtree`.
+//!
+//! ```ignore
+//! use libalpm_utils::local_db::LocalDb;
+//!
+//! let db = LocalDb::open("/var/lib/pacman").unwrap();
+//! for pkg in db.packages().unwrap() {
+//!     println!("{} {}", pkg.name, pkg.version);
+//! }
+//! ```
+
+use std::fs;
+use std::io::{self, BufReader};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+
+use desc::PackageRecord;
+use ini::Error;
+
+/// The version of the local database format this module understands.
+pub const DB_VERSION: u32 = 9;
+
+/// The local database of installed packages.
+#[derive(Debug, Clone)]
+pub struct LocalDb {
+    /// The path to the `local` directory.
+    path: PathBuf,
+    version: u32,
+}
+
+impl LocalDb {
+    /// Opens the local database under `db_path` (the `DBPath` from pacman.conf, usually
+    /// `/var/lib/pacman`).
+    ///
+    /// Fails if the database version is not `DB_VERSION`. An empty database (no
+    /// `ALPM_DB_VERSION` file and no packages) is accepted, like libalpm does.
+    pub fn open<P: AsRef<Path>>(db_path: P) -> Result<LocalDb, Error> {
+        let path = db_path.as_ref().join("local");
+        let version_path = path.join("ALPM_DB_VERSION");
+        let version = match read_to_string(&version_path) {
+            Ok(text) => text.trim().parse().map_err(|_| {
+                Error::Parse(version_path.display().to_string(), 1, text.trim().into())
+            })?,
+            Err(Error::Io(ref e)) if e.kind() == io::ErrorKind::NotFound => {
+                fs::metadata(&path)?;
+                DB_VERSION
+            }
+            Err(e) => return Err(e),
+        };
+        let db = LocalDb {
+            path: path,
+            version: version,
+        };
+        if version != DB_VERSION && !db.package_dirs()?.is_empty() {
+            return Err(Error::Parse(version_path.display().to_string(), 1,
+                                    format!("unsupported database version {}", version)));
+        }
+        Ok(db)
+    }
+
+    /// The path to the `local` directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The database version from `ALPM_DB_VERSION`.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Reads every installed package (`desc` and `files`), sorted by directory name.
+    pub fn packages(&self) -> Result<Vec<PackageRecord>, Error> {
+        self.package_dirs()?.iter().map(|dir| read_package(dir)).collect()
+    }
+
+    /// Reads the installed package with the given name, if there is one.
+    pub fn package(&self, name: &str) -> Result<Option<PackageRecord>, Error> {
+        for dir in self.package_dirs()? {
+            // the directory is `name-pkgver-pkgrel`, and names can contain `-`
+            let dir_name = dir.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if dir_name.len() > name.len() + 1 && dir_name.starts_with(name)
+                && dir_name.as_bytes()[name.len()] == b'-'
+                && dir_name[name.len() + 1..].matches('-').count() == 1
+            {
+                return read_package(&dir).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads the mtree of an installed package (the files as they were in the package,
+    /// with their permissions and checksums).
+    pub fn mtree(&self, pkg: &PackageRecord) -> Result<Vec<MtreeEntry>, Error> {
+        let path = self.path.join(format!("{}-{}", pkg.name, pkg.version)).join("mtree");
+        let mut text = String::new();
+        GzDecoder::new(BufReader::new(fs::File::open(&path)?)).read_to_string(&mut text)?;
+        parse_mtree(&path.display().to_string(), &text)
+    }
+
+    fn package_dirs(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut dirs = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                dirs.push(entry.path());
+            }
+        }
+        dirs.sort();
+        Ok(dirs)
+    }
+}
+
+/// Reads `desc` and `files` from a package directory.
+fn read_package(dir: &Path) -> Result<PackageRecord, Error> {
+    let mut pkg = PackageRecord::default();
+    for name in &["desc", "files"] {
+        let path = dir.join(name);
+        match read_to_string(&path) {
+            Ok(text) => pkg.add_metadata(&path.display().to_string(), &text)?,
+            // very old packages may not have a files entry
+            Err(Error::Io(ref e)) if *name == "files" && e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(pkg)
+}
+
+fn read_to_string(path: &Path) -> Result<String, Error> {
+    let mut text = String::new();
+    fs::File::open(path)?.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// An entry in a package's mtree.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MtreeEntry {
+    /// The path relative to the root, without the leading `./`.
+    pub path: String,
+    /// The entry type, e.g. `file`, `dir` or `link`.
+    pub kind: String,
+    pub mode: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub size: Option<u64>,
+    /// The modification time, in seconds since the unix epoch.
+    pub time: Option<f64>,
+    /// The target of a symlink.
+    pub link: Option<String>,
+    pub md5digest: Option<String>,
+    pub sha256digest: Option<String>,
+}
+
+impl MtreeEntry {
+    fn set(&mut self, keyword: &str, value: &str) -> Result<(), ()> {
+        match keyword {
+            "type" => self.kind = value.into(),
+            "mode" => self.mode = Some(u32::from_str_radix(value, 8).map_err(|_| ())?),
+            "uid" => self.uid = Some(value.parse().map_err(|_| ())?),
+            "gid" => self.gid = Some(value.parse().map_err(|_| ())?),
+            "size" => self.size = Some(value.parse().map_err(|_| ())?),
+            "time" => self.time = Some(value.parse().map_err(|_| ())?),
+            "link" => self.link = Some(unescape(value)),
+            "md5digest" => self.md5digest = Some(value.into()),
+            "sha256digest" => self.sha256digest = Some(value.into()),
+            // other keywords (e.g. `uname`) are not used by pacman
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn unset(&mut self, keyword: &str) {
+        match keyword {
+            "type" => self.kind = String::new(),
+            "mode" => self.mode = None,
+            "uid" => self.uid = None,
+            "gid" => self.gid = None,
+            "size" => self.size = None,
+            "time" => self.time = None,
+            "link" => self.link = None,
+            "md5digest" => self.md5digest = None,
+            "sha256digest" => self.sha256digest = None,
+            _ => (),
+        }
+    }
+}
+
+/// Parses the (decompressed) text of an mtree file, as written by libarchive.
+///
+/// `/set` and `/unset` lines change the defaults for the entries that follow. `filename` is only
+/// used in error messages.
+pub fn parse_mtree(filename: &str, text: &str) -> Result<Vec<MtreeEntry>, Error> {
+    let mut entries = Vec::new();
+    let mut defaults = MtreeEntry::default();
+    for (line_no, line) in text.lines().enumerate() {
+        let err = || Error::Parse(filename.into(), line_no + 1, line.into());
+        let mut words = line.split_whitespace();
+        let first = match words.next() {
+            Some(word) if !word.starts_with('#') => word,
+            _ => continue,
+        };
+        if first == "/set" {
+            for word in words {
+                let (keyword, value) = split_keyword(word).ok_or_else(&err)?;
+                defaults.set(keyword, value).map_err(|_| err())?;
+            }
+        } else if first == "/unset" {
+            for keyword in words {
+                defaults.unset(keyword);
+            }
+        } else {
+            let mut entry = defaults.clone();
+            entry.path = unescape(first.trim_start_matches("./"));
+            for word in words {
+                let (keyword, value) = split_keyword(word).ok_or_else(&err)?;
+                entry.set(keyword, value).map_err(|_| err())?;
+            }
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+fn split_keyword(word: &str) -> Option<(&str, &str)> {
+    let idx = word.find('=')?;
+    Some((&word[..idx], &word[idx + 1..]))
+}
+
+/// Decodes the `\ooo` octal escapes mtree uses for special characters (e.g. `\040` for a space).
+fn unescape(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'\\' && idx + 3 < bytes.len()
+            && bytes[idx + 1..idx + 4].iter().all(|b| *b >= b'0' && *b <= b'7')
+        {
+            let digits = &bytes[idx + 1..idx + 4];
+            let byte = digits.iter().fold(0u32, |acc, b| acc * 8 + (b - b'0') as u32);
+            out.push(byte as u8);
+            idx += 4;
+            continue;
+        }
+        out.push(bytes[idx]);
+        idx += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write;
+
+    use super::*;
+    use testing::TempDir;
+
+    #[test]
+    fn mtree() {
+        let text = "#mtree\n\
+                    /set type=file uid=0 gid=0 mode=644\n\
+                    ./.PKGINFO time=1500000000.0 size=512 md5digest=abc\n\
+                    ./usr time=1500000000.0 mode=755 type=dir\n\
+                    ./usr/share/a\\040b time=1500000000.0 size=3 sha256digest=def\n";
+        let entries = parse_mtree("mtree", text).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].kind, "dir");
+        assert_eq!(entries[1].mode, Some(0o755));
+        assert_eq!(entries[2].path, "usr/share/a b");
+        assert_eq!(entries[2].mode, Some(0o644));
+        assert_eq!(entries[2].size, Some(3));
+        assert!(parse_mtree("mtree", "./usr mode=999\n").is_err());
+    }
+
+    #[test]
+    fn local_db() {
+        let root = TempDir::new("local-db");
+        let local = root.join("local");
+        fs::create_dir_all(local.join("foo-bar-1.0-1")).unwrap();
+        fs::create_dir_all(local.join("foo-2.0-1")).unwrap();
+        fs::File::create(local.join("ALPM_DB_VERSION")).unwrap().write_all(b"9\n").unwrap();
+        fs::File::create(local.join("foo-bar-1.0-1/desc")).unwrap()
+            .write_all(b"%NAME%\nfoo-bar\n\n%VERSION%\n1.0-1\n\n").unwrap();
+        fs::File::create(local.join("foo-2.0-1/desc")).unwrap()
+            .write_all(b"%NAME%\nfoo\n\n%VERSION%\n2.0-1\n\n").unwrap();
+        fs::File::create(local.join("foo-2.0-1/files")).unwrap()
+            .write_all(b"%FILES%\nusr/\n\n").unwrap();
+
+        let db = LocalDb::open(&root).unwrap();
+        assert_eq!(db.version(), 9);
+        let pkgs = db.packages().unwrap();
+        assert_eq!(pkgs.len(), 2);
+        assert_eq!(pkgs[0].name, "foo");
+        assert_eq!(pkgs[0].files, vec!["usr/"]);
+        assert_eq!(db.package("foo-bar").unwrap().unwrap().version, "1.0-1");
+        assert_eq!(db.package("foo").unwrap().unwrap().version, "2.0-1");
+        assert!(db.package("bar").unwrap().is_none());
+    }
+
+    #[test]
+    fn empty_local_db() {
+        // like the database libalpm creates, which has no packages and may have no version yet
+        let root = TempDir::new("empty-local-db");
+        assert!(LocalDb::open(&root).is_err());
+        fs::create_dir(root.join("local")).unwrap();
+        let db = LocalDb::open(&root).unwrap();
+        assert_eq!(db.version(), 9);
+        assert!(db.packages().unwrap().is_empty());
+        assert!(db.package("foo").unwrap().is_none());
+
+        fs::File::create(root.join("local/ALPM_DB_VERSION")).unwrap().write_all(b"9\n").unwrap();
+        let db = LocalDb::open(&root).unwrap();
+        assert_eq!(db.version(), 9);
+        assert!(db.packages().unwrap().is_empty());
+    }
+}
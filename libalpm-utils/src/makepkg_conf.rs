@@ -0,0 +1,461 @@
+//! A parser for `makepkg.conf`.
+//!
+//! `makepkg.conf` is a bash script, but in practice it only uses a small part of bash: variable
+//! assignments of strings and arrays, quoting, and `$VAR` or `${VAR}` references to variables
+//! set earlier in the file. This parser understands that subset, and fails on anything that
+//! would need a shell to run (e.g. command substitution).
+//!
+//! ```ignore
+//! use libalpm_utils::makepkg_conf::MakepkgConf;
+//!
+//! let conf = MakepkgConf::from_file("/etc/makepkg.conf").unwrap();
+//! println!("building for {:?} with {:?}", conf.carch, conf.cflags);
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::prelude::*;
+
+use ini::Error;
+
+/// The value of a shell variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    String(String),
+    Array(Vec<String>),
+}
+
+impl Value {
+    /// The value as bash expands `$VAR`: the string, or the first element of an array.
+    pub fn as_str(&self) -> &str {
+        match *self {
+            Value::String(ref s) => s,
+            Value::Array(ref values) => values.first().map(|s| s.as_str()).unwrap_or(""),
+        }
+    }
+
+    /// The value as a list, like `${VAR[@]}`. A string is a list of one value.
+    pub fn as_slice(&self) -> &[String] {
+        match *self {
+            Value::String(ref s) => ::std::slice::from_ref(s),
+            Value::Array(ref values) => values,
+        }
+    }
+}
+
+/// The settings in `makepkg.conf`.
+///
+/// The commonly used settings are parsed into fields, and every variable is available through
+/// `var`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MakepkgConf {
+    pub carch: Option<String>,
+    pub chost: Option<String>,
+    pub cflags: Option<String>,
+    pub cxxflags: Option<String>,
+    pub ldflags: Option<String>,
+    pub makeflags: Option<String>,
+    /// e.g. `!distcc` or `color`.
+    pub build_env: Vec<String>,
+    /// e.g. `strip` or `!debug`.
+    pub options: Vec<String>,
+    /// e.g. `sha256`.
+    pub integrity_check: Vec<String>,
+    /// The package extension, e.g. `.pkg.tar.xz`.
+    pub pkgext: Option<String>,
+    /// The source package extension, e.g. `.src.tar.gz`.
+    pub srcext: Option<String>,
+    pub packager: Option<String>,
+    pub build_dir: Option<String>,
+    pub pkg_dest: Option<String>,
+    pub src_dest: Option<String>,
+    /// The command used to download each protocol, e.g. `https` ->
+    /// `/usr/bin/curl -fLC - --retry 3 -o %o %u`.
+    pub dl_agents: BTreeMap<String, String>,
+    vars: BTreeMap<String, Value>,
+}
+
+impl MakepkgConf {
+    /// Parses the text of a `makepkg.conf`. `filename` is only used in error messages.
+    pub fn parse(filename: &str, text: &str) -> Result<MakepkgConf, Error> {
+        let vars = Parser::new(filename, text).parse()?;
+        let string = |name: &str| vars.get(name).map(|v| v.as_str().to_owned());
+        let array = |name: &str| vars.get(name).map(|v| v.as_slice().to_vec()).unwrap_or_default();
+        let mut dl_agents = BTreeMap::new();
+        for agent in array("DLAGENTS") {
+            let idx = agent.find("::").ok_or_else(|| {
+                Error::Parse(filename.into(), 0, format!("invalid DLAGENTS entry {}", agent))
+            })?;
+            dl_agents.insert(agent[..idx].to_owned(), agent[idx + 2..].to_owned());
+        }
+        Ok(MakepkgConf {
+            carch: string("CARCH"),
+            chost: string("CHOST"),
+            cflags: string("CFLAGS"),
+            cxxflags: string("CXXFLAGS"),
+            ldflags: string("LDFLAGS"),
+            makeflags: string("MAKEFLAGS"),
+            build_env: array("BUILDENV"),
+            options: array("OPTIONS"),
+            integrity_check: array("INTEGRITY_CHECK"),
+            pkgext: string("PKGEXT"),
+            srcext: string("SRCEXT"),
+            packager: string("PACKAGER"),
+            build_dir: string("BUILDDIR"),
+            pkg_dest: string("PKGDEST"),
+            src_dest: string("SRCDEST"),
+            dl_agents: dl_agents,
+            vars: vars,
+        })
+    }
+
+    /// Reads and parses a `makepkg.conf`.
+    pub fn from_file(filename: &str) -> Result<MakepkgConf, Error> {
+        let mut text = String::new();
+        fs::File::open(filename)?.read_to_string(&mut text)?;
+        MakepkgConf::parse(filename, &text)
+    }
+
+    /// Gets the value of any variable set in the file.
+    pub fn var(&self, name: &str) -> Option<&Value> {
+        self.vars.get(name)
+    }
+
+    /// All the variables set in the file.
+    pub fn vars(&self) -> &BTreeMap<String, Value> {
+        &self.vars
+    }
+}
+
+/// A parser for bash variable assignments.
+struct Parser<'a> {
+    filename: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+    /// The (1-based) line `pos` is on.
+    line: usize,
+    vars: BTreeMap<String, Value>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(filename: &'a str, text: &str) -> Parser<'a> {
+        Parser {
+            filename: filename,
+            chars: text.chars().collect(),
+            pos: 0,
+            line: 1,
+            vars: BTreeMap::new(),
+        }
+    }
+
+    fn err(&self, msg: &str) -> Error {
+        Error::Parse(self.filename.into(), self.line, msg.into())
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+
+    /// Skips blanks, and newlines and comments if `newlines` is set.
+    fn skip_space(&mut self, newlines: bool) {
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' => (),
+                '\n' | ';' if newlines => (),
+                '#' if newlines => {
+                    while self.peek().map_or(false, |c| c != '\n') {
+                        self.next();
+                    }
+                    continue;
+                }
+                '\\' if self.chars.get(self.pos + 1) == Some(&'\n') => {
+                    self.next();
+                }
+                _ => return,
+            }
+            self.next();
+        }
+    }
+
+    fn parse(mut self) -> Result<BTreeMap<String, Value>, Error> {
+        loop {
+            self.skip_space(true);
+            if self.peek().is_none() {
+                return Ok(self.vars);
+            }
+            let mut name = self.name();
+            if name == "export" && self.peek() == Some(' ') {
+                self.skip_space(false);
+                name = self.name();
+            }
+            if name.is_empty() {
+                return Err(self.err("expected a variable assignment"));
+            }
+            let append = self.peek() == Some('+');
+            if append {
+                self.next();
+            }
+            if self.next() != Some('=') {
+                return Err(self.err("expected a variable assignment"));
+            }
+            let value = if self.peek() == Some('(') {
+                self.next();
+                let mut values = Vec::new();
+                loop {
+                    self.skip_space(true);
+                    match self.peek() {
+                        Some(')') => {
+                            self.next();
+                            break;
+                        }
+                        None => return Err(self.err("unterminated array")),
+                        _ => values.extend(self.word()?),
+                    }
+                }
+                Value::Array(values)
+            } else {
+                Value::String(self.word()?.join(" "))
+            };
+            let value = match (append, self.vars.remove(&name), value) {
+                (true, Some(Value::Array(mut old)), Value::Array(new)) => {
+                    old.extend(new);
+                    Value::Array(old)
+                }
+                (true, Some(old), Value::String(new)) => {
+                    Value::String(format!("{}{}", old.as_str(), new))
+                }
+                (_, _, value) => value,
+            };
+            self.vars.insert(name, value);
+            match self.peek() {
+                None | Some(' ') | Some('\t') | Some('\n') | Some(';') | Some('#') => (),
+                _ => return Err(self.err("unexpected character after assignment")),
+            }
+        }
+    }
+
+    fn name(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                name.push(c);
+                self.next();
+            } else {
+                break;
+            }
+        }
+        name
+    }
+
+    /// Reads a word, removing the quotes and expanding variables.
+    ///
+    /// Like in bash, `${VAR[@]}` expands to one word per element, so this returns several words
+    /// for an array (or none for an empty one).
+    fn word(&mut self) -> Result<Vec<String>, Error> {
+        let mut words = vec![String::new()];
+        // whether there is anything but empty arrays, which leave no word at all
+        let mut kept = false;
+        loop {
+            match self.peek() {
+                None | Some(' ') | Some('\t') | Some('\n') | Some(';') | Some(')') => {
+                    if !kept {
+                        words.clear();
+                    }
+                    return Ok(words);
+                }
+                Some('\'') => {
+                    self.next();
+                    kept = true;
+                    loop {
+                        match self.next() {
+                            Some('\'') => break,
+                            Some(c) => push(&mut words, c),
+                            None => return Err(self.err("unterminated quote")),
+                        }
+                    }
+                }
+                Some('"') => {
+                    self.next();
+                    kept = true;
+                    loop {
+                        match self.peek() {
+                            Some('"') => {
+                                self.next();
+                                break;
+                            }
+                            Some('\\') => {
+                                self.next();
+                                match self.next() {
+                                    Some('\n') => (),
+                                    Some(c) if "\"\\$`".contains(c) => push(&mut words, c),
+                                    Some(c) => {
+                                        push(&mut words, '\\');
+                                        push(&mut words, c);
+                                    }
+                                    None => return Err(self.err("unterminated quote")),
+                                }
+                            }
+                            Some('$') => {
+                                self.expand(&mut words)?;
+                            }
+                            Some('`') => return Err(self.err("command substitution")),
+                            Some(c) => {
+                                self.next();
+                                push(&mut words, c);
+                            }
+                            None => return Err(self.err("unterminated quote")),
+                        }
+                    }
+                }
+                Some('\\') => {
+                    self.next();
+                    kept = true;
+                    match self.next() {
+                        Some('\n') | None => (),
+                        Some(c) => push(&mut words, c),
+                    }
+                }
+                Some('$') => kept |= self.expand(&mut words)?,
+                Some('`') | Some('|') | Some('&') | Some('<') | Some('>') | Some('(') => {
+                    return Err(self.err("unsupported shell syntax"));
+                }
+                Some(c) => {
+                    self.next();
+                    kept = true;
+                    push(&mut words, c);
+                }
+            }
+        }
+    }
+
+    /// Expands a `$VAR`, `${VAR}`, `${VAR[@]}` or `${VAR[*]}` reference onto the end of `words`.
+    /// Unset variables expand to nothing, like in bash.
+    ///
+    /// `${VAR[@]}` adds each element after the first as a new word, and `${VAR[*]}` joins them
+    /// with spaces. Returns false if nothing was expanded from an array, which bash drops.
+    fn expand(&mut self, words: &mut Vec<String>) -> Result<bool, Error> {
+        self.next();
+        let (name, index) = match self.peek() {
+            Some('{') => {
+                self.next();
+                let name = self.name();
+                let index = match self.peek() {
+                    Some('[') => {
+                        self.next();
+                        match (self.next(), self.next()) {
+                            (Some(index), Some(']')) if index == '@' || index == '*' => {
+                                Some(index)
+                            }
+                            _ => return Err(self.err("unsupported array index")),
+                        }
+                    }
+                    _ => None,
+                };
+                if name.is_empty() || self.next() != Some('}') {
+                    return Err(self.err("unsupported parameter expansion"));
+                }
+                (name, index)
+            }
+            Some('(') => return Err(self.err("command substitution")),
+            _ => (self.name(), None),
+        };
+        if name.is_empty() {
+            push(words, '$');
+            return Ok(true);
+        }
+        let value = match self.vars.get(&name) {
+            Some(value) => value,
+            // an unset array is empty
+            None => return Ok(index.is_none()),
+        };
+        match index {
+            Some('@') => {
+                let mut values = value.as_slice().iter();
+                match values.next() {
+                    Some(first) => words.last_mut().unwrap().push_str(first),
+                    None => return Ok(false),
+                }
+                words.extend(values.cloned());
+            }
+            Some(_) => words.last_mut().unwrap().push_str(&value.as_slice().join(" ")),
+            None => words.last_mut().unwrap().push_str(value.as_str()),
+        }
+        Ok(true)
+    }
+}
+
+/// Adds a character to the last of `words`.
+fn push(words: &mut Vec<String>, c: char) {
+    words.last_mut().unwrap().push(c);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONF: &'static str = r#"
+#!/hint/bash
+#########################################################################
+# SOURCE ACQUISITION
+#########################################################################
+#-- The download utilities that makepkg should use to acquire sources
+#  Format: 'protocol::agent'
+DLAGENTS=('ftp::/usr/bin/curl -fC - --ftp-pasv --retry 3 --retry-delay 3 -o %o %u'
+          'https::/usr/bin/curl -fLC - --retry 3 --retry-delay 3 -o %o %u'
+          'scp::/usr/bin/scp -C %u %o')
+
+CARCH="x86_64"
+CHOST="x86_64-pc-linux-gnu"
+CFLAGS="-march=x86-64 -mtune=generic -O2 -pipe \
+        -fstack-protector-strong"
+CXXFLAGS="${CFLAGS} -Wp,-D_GLIBCXX_ASSERTIONS"
+#MAKEFLAGS="-j2"
+BUILDENV=(!distcc color !ccache check !sign)
+OPTIONS=(strip docs !libtool !staticlibs emptydirs zipman purge !debug) # defaults
+INTEGRITY_CHECK=(md5)
+PKGEXT='.pkg.tar.xz'; SRCEXT='.src.tar.gz'
+export PACKAGER="John Doe <john@$CARCH.example.com>"
+OPTIONS+=(lto)
+"#;
+
+    #[test]
+    fn parse() {
+        let conf = MakepkgConf::parse("makepkg.conf", CONF).unwrap();
+        assert_eq!(conf.carch, Some("x86_64".into()));
+        assert_eq!(conf.chost, Some("x86_64-pc-linux-gnu".into()));
+        assert_eq!(conf.cflags,
+                   Some("-march=x86-64 -mtune=generic -O2 -pipe         -fstack-protector-strong"
+                        .into()));
+        assert!(conf.cxxflags.as_ref().unwrap().ends_with("-strong -Wp,-D_GLIBCXX_ASSERTIONS"));
+        assert_eq!(conf.makeflags, None);
+        assert_eq!(conf.build_env, vec!["!distcc", "color", "!ccache", "check", "!sign"]);
+        assert_eq!(conf.options.len(), 9);
+        assert_eq!(conf.options.last().unwrap(), "lto");
+        assert_eq!(conf.pkgext, Some(".pkg.tar.xz".into()));
+        assert_eq!(conf.srcext, Some(".src.tar.gz".into()));
+        assert_eq!(conf.packager, Some("John Doe <john@x86_64.example.com>".into()));
+        assert_eq!(conf.dl_agents.len(), 3);
+        assert_eq!(conf.dl_agents["scp"], "/usr/bin/scp -C %u %o");
+        assert_eq!(conf.var("INTEGRITY_CHECK"), Some(&Value::Array(vec!["md5".into()])));
+
+        let text = "A=(a 'b c')\nE=()\nB=(x${A[@]}y \"${A[@]}\" ${E[@]} ${A[*]})\nC=${A[@]}\n";
+        let conf = MakepkgConf::parse("makepkg.conf", text).unwrap();
+        assert_eq!(conf.var("B").unwrap().as_slice(), ["xa", "b cy", "a", "b c", "a b c"]);
+        assert_eq!(conf.var("C"), Some(&Value::String("a b c".into())));
+
+        assert!(MakepkgConf::parse("makepkg.conf", "CARCH=$(uname -m)\n").is_err());
+        assert!(MakepkgConf::parse("makepkg.conf", "CARCH=\"x86_64\n").is_err());
+        assert!(MakepkgConf::parse("makepkg.conf", "echo hello\n").is_err());
+    }
+}
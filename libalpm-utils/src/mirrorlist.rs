@@ -0,0 +1,403 @@
+//! Reading and editing mirrorlist files (e.g. `/etc/pacman.d/mirrorlist`).
+//!
+//! A mirrorlist is a list of `Server = ...` lines, most of them commented out, grouped under
+//! `## Country` comments. Like `Document`, a `Mirrorlist` keeps every line as it was written so
+//! it can be written back after enabling, disabling or reordering servers.
+//!
+//! ```ignore
+//! use libalpm_utils::mirrorlist::Mirrorlist;
+//!
+//! let mut mirrors = Mirrorlist::from_file("/etc/pacman.d/mirrorlist").unwrap();
+//! mirrors.disable_all();
+//! mirrors.enable_country("Germany");
+//! let core = mirrors.repo_config("core", "x86_64");
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::io::prelude::*;
+use std::ops::Range;
+
+use libalpm::RepoConfig;
+
+use document::{split_lines, write_lines, write_atomic};
+use ini::{self, Error, Token};
+
+/// A server in a mirrorlist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mirror {
+    /// The server url, still containing `$repo` and `$arch`.
+    pub url: String,
+    /// False if the server line is commented out.
+    pub enabled: bool,
+    /// The `## Country` comment the server is listed under, if any.
+    pub country: Option<String>,
+}
+
+/// A line of a mirrorlist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Line {
+    raw: String,
+    /// The url and whether it is enabled, if this is a (possibly commented out) server line.
+    server: Option<(String, bool)>,
+}
+
+impl Line {
+    fn new(raw: &str) -> Line {
+        let trimmed = raw.trim();
+        let uncommented = trimmed.trim_start_matches('#');
+        let server = match ini::lex_line(uncommented) {
+            Some(Token::Pair(ref key, ref url)) if key == "Server" => {
+                Some((url.clone(), uncommented.len() == trimmed.len()))
+            }
+            _ => None,
+        };
+        Line {
+            raw: raw.into(),
+            server: server,
+        }
+    }
+
+    fn url(&self) -> Option<&str> {
+        self.server.as_ref().map(|&(ref url, _)| url.as_str())
+    }
+
+    /// Gets the name from a `## Country` comment.
+    fn country(&self) -> Option<&str> {
+        let trimmed = self.raw.trim();
+        if self.server.is_none() && trimmed.starts_with("##") {
+            let name = trimmed.trim_start_matches('#').trim();
+            if !name.is_empty() {
+                return Some(name);
+            }
+        }
+        None
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        let raw = match self.server {
+            Some((_, true)) if !enabled => format!("#{}", self.raw),
+            Some((_, false)) if enabled => {
+                self.raw.trim().trim_start_matches('#').trim_start().to_owned()
+            }
+            _ => return,
+        };
+        *self = Line::new(&raw);
+    }
+}
+
+/// A mirrorlist file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mirrorlist {
+    lines: Vec<Line>,
+    /// Whether the last line was terminated by a newline.
+    trailing_newline: bool,
+}
+
+impl Mirrorlist {
+    /// Parses the text of a mirrorlist. Lines that are not servers are kept as they are.
+    pub fn parse(text: &str) -> Mirrorlist {
+        let (raw_lines, trailing_newline) = split_lines(text);
+        Mirrorlist {
+            lines: raw_lines.into_iter().map(Line::new).collect(),
+            trailing_newline: trailing_newline,
+        }
+    }
+
+    /// Reads and parses a mirrorlist file.
+    pub fn from_file(filename: &str) -> Result<Mirrorlist, Error> {
+        let mut text = String::new();
+        fs::File::open(filename)?.read_to_string(&mut text)?;
+        Ok(Mirrorlist::parse(&text))
+    }
+
+    /// Writes the mirrorlist to a file, replacing it atomically.
+    pub fn write_to_file(&self, filename: &str) -> Result<(), Error> {
+        write_atomic(filename, &self.to_string())
+    }
+
+    /// Gets all servers, enabled or not, in the order they appear.
+    ///
+    /// A server's country is the last `## ...` comment above it, up to the previous blank line.
+    pub fn servers(&self) -> Vec<Mirror> {
+        let mut servers = Vec::new();
+        let mut country = None;
+        for line in self.lines.iter() {
+            if let Some((ref url, enabled)) = line.server {
+                servers.push(Mirror {
+                    url: url.clone(),
+                    enabled: enabled,
+                    country: country.map(|c: &str| c.to_owned()),
+                });
+            } else if line.raw.trim().is_empty() {
+                country = None;
+            } else if let Some(name) = line.country() {
+                country = Some(name);
+            }
+        }
+        servers
+    }
+
+    /// Gets the urls of the enabled servers, in order.
+    pub fn enabled(&self) -> Vec<&str> {
+        self.lines.iter().filter_map(|line| match line.server {
+            Some((ref url, true)) => Some(url.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    /// Gets the countries in the order they appear.
+    pub fn countries(&self) -> Vec<String> {
+        let mut countries: Vec<String> = Vec::new();
+        for country in self.servers().into_iter().filter_map(|mirror| mirror.country) {
+            if !countries.contains(&country) {
+                countries.push(country);
+            }
+        }
+        countries
+    }
+
+    /// Uncomments the server with the given url. Returns false if there is no such server.
+    pub fn enable(&mut self, url: &str) -> bool {
+        self.set_enabled(url, true)
+    }
+
+    /// Comments out the server with the given url. Returns false if there is no such server.
+    pub fn disable(&mut self, url: &str) -> bool {
+        self.set_enabled(url, false)
+    }
+
+    /// Uncomments every server listed under the given country.
+    pub fn enable_country(&mut self, country: &str) {
+        let urls: Vec<String> = self.servers().into_iter()
+            .filter(|mirror| mirror.country.as_ref().map(|c| c == country).unwrap_or(false))
+            .map(|mirror| mirror.url)
+            .collect();
+        for url in urls.iter() {
+            self.enable(url);
+        }
+    }
+
+    /// Comments out every server.
+    pub fn disable_all(&mut self) {
+        for line in self.lines.iter_mut() {
+            line.set_enabled(false);
+        }
+    }
+
+    /// Reorders servers within a section (the lines between two blank lines, usually one
+    /// country).
+    ///
+    /// The servers with the given urls swap places so they appear in the given order, each
+    /// taking one of the lines previously used by the listed servers. Every other line stays
+    /// where it is. Returns false (and does nothing) if a url isn't in the list, is given twice,
+    /// or is in a different section from the others, since moving it would change its country.
+    pub fn reorder(&mut self, urls: &[&str]) -> bool {
+        let mut positions = Vec::new();
+        for url in urls.iter() {
+            match self.position(url) {
+                Some(idx) if !positions.contains(&idx) => positions.push(idx),
+                _ => return false,
+            }
+        }
+        if let Some(&first) = positions.first() {
+            let section = self.section(first);
+            if positions.iter().any(|idx| !section.contains(idx)) {
+                return false;
+            }
+        }
+        let reordered: Vec<Line> = positions.iter().map(|&idx| self.lines[idx].clone()).collect();
+        positions.sort();
+        for (idx, line) in positions.into_iter().zip(reordered.into_iter()) {
+            self.lines[idx] = line;
+        }
+        true
+    }
+
+    /// Moves a server so it is tried first. Returns false if there is no such server.
+    ///
+    /// The server takes the line of the first enabled server in its section, and the enabled
+    /// servers before it move down; commented out servers stay where they are. If an enabled
+    /// server comes before the section, the whole section (with its country comment) moves up
+    /// to before that server's section, so every server keeps its country.
+    pub fn move_to_front(&mut self, url: &str) -> bool {
+        let idx = match self.position(url) {
+            Some(idx) => idx,
+            None => return false,
+        };
+        let section = self.section(idx);
+        let mut urls: Vec<String> = vec![url.to_owned()];
+        urls.extend(self.lines[section.clone()].iter().filter_map(|line| match line.server {
+            Some((ref other, true)) if other != url => Some(other.clone()),
+            _ => None,
+        }));
+        let urls: Vec<&str> = urls.iter().map(|url| url.as_str()).collect();
+        self.reorder(&urls);
+
+        let first = self.lines.iter().position(|line| match line.server {
+            Some((_, enabled)) => enabled,
+            None => false,
+        });
+        if let Some(first) = first.map(|first| self.section(first)) {
+            if first.start < section.start {
+                // take the blank line before the section along, and put it after instead
+                let mut moved: Vec<Line> = self.lines.drain(section.start - 1..section.end)
+                    .collect();
+                moved.rotate_left(1);
+                for (offset, line) in moved.into_iter().enumerate() {
+                    self.lines.insert(first.start + offset, line);
+                }
+            }
+        }
+        true
+    }
+
+    /// Gets the enabled servers for a repository, with `$repo` and `$arch` substituted.
+    pub fn servers_for(&self, repo: &str, arch: &str) -> Vec<String> {
+        self.enabled().into_iter()
+            .map(|url| url.replace("$repo", repo).replace("$arch", arch))
+            .collect()
+    }
+
+    /// Gets the config for a repository using the enabled servers of this mirrorlist.
+    pub fn repo_config(&self, repo: &str, arch: &str) -> RepoConfig {
+        RepoConfig { servers: self.servers_for(repo, arch) }
+    }
+
+    fn position(&self, url: &str) -> Option<usize> {
+        self.lines.iter().position(|line| line.url() == Some(url))
+    }
+
+    /// Gets the lines of the section containing a line, up to the blank lines around it.
+    fn section(&self, idx: usize) -> Range<usize> {
+        let blank = |line: &Line| line.raw.trim().is_empty();
+        let start = self.lines[..idx].iter().rposition(blank).map_or(0, |i| i + 1);
+        let end = self.lines[idx..].iter().position(blank).map_or(self.lines.len(), |i| idx + i);
+        start..end
+    }
+
+    fn set_enabled(&mut self, url: &str, enabled: bool) -> bool {
+        match self.position(url) {
+            Some(idx) => {
+                self.lines[idx].set_enabled(enabled);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl fmt::Display for Mirrorlist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_lines(f, self.lines.iter().map(|line| line.raw.as_str()), self.trailing_newline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIRRORLIST: &'static str = "\
+##
+## Arch Linux repository mirrorlist
+##
+
+## Worldwide
+#Server = https://geo.mirror.pkgbuild.com/$repo/os/$arch
+
+## Germany
+#Server = https://mirror.one.de/archlinux/$repo/os/$arch
+Server = https://mirror.two.de/archlinux/$repo/os/$arch
+";
+
+    #[test]
+    fn parse() {
+        let mirrors = Mirrorlist::parse(MIRRORLIST);
+        assert_eq!(mirrors.to_string(), MIRRORLIST);
+        assert_eq!(mirrors.countries(), vec!["Worldwide", "Germany"]);
+        assert_eq!(mirrors.servers()[1], Mirror {
+            url: "https://mirror.one.de/archlinux/$repo/os/$arch".into(),
+            enabled: false,
+            country: Some("Germany".into()),
+        });
+        assert_eq!(mirrors.servers_for("core", "x86_64"),
+                   vec!["https://mirror.two.de/archlinux/core/os/x86_64"]);
+    }
+
+    #[test]
+    fn edit() {
+        let mut mirrors = Mirrorlist::parse(MIRRORLIST);
+        mirrors.disable_all();
+        mirrors.enable_country("Germany");
+        assert!(mirrors.reorder(&["https://mirror.two.de/archlinux/$repo/os/$arch",
+                                  "https://mirror.one.de/archlinux/$repo/os/$arch"]));
+        assert!(!mirrors.enable("https://nowhere/"));
+        assert_eq!(mirrors.to_string(), "\
+##
+## Arch Linux repository mirrorlist
+##
+
+## Worldwide
+#Server = https://geo.mirror.pkgbuild.com/$repo/os/$arch
+
+## Germany
+Server = https://mirror.two.de/archlinux/$repo/os/$arch
+Server = https://mirror.one.de/archlinux/$repo/os/$arch
+");
+        // servers can't be moved into another country
+        assert!(!mirrors.reorder(&["https://mirror.one.de/archlinux/$repo/os/$arch",
+                                   "https://geo.mirror.pkgbuild.com/$repo/os/$arch"]));
+    }
+
+    #[test]
+    fn move_to_front() {
+        let text = "\
+## Worldwide
+Server = https://geo.mirror.pkgbuild.com/$repo/os/$arch
+
+## Germany
+Server = https://mirror.one.de/archlinux/$repo/os/$arch
+#Server = https://mirror.two.de/archlinux/$repo/os/$arch
+Server = https://mirror.three.de/archlinux/$repo/os/$arch
+
+## France
+Server = https://mirror.fr/archlinux/$repo/os/$arch
+";
+        let mut mirrors = Mirrorlist::parse(text);
+        let countries = |mirrors: &Mirrorlist| {
+            let mut countries: Vec<(String, Option<String>)> = mirrors.servers().into_iter()
+                .map(|mirror| (mirror.url, mirror.country))
+                .collect();
+            countries.sort();
+            countries
+        };
+        let before = countries(&mirrors);
+
+        // the last German server moves to the front, taking its section along
+        assert!(mirrors.move_to_front("https://mirror.three.de/archlinux/$repo/os/$arch"));
+        assert_eq!(countries(&mirrors), before);
+        assert_eq!(mirrors.to_string(), "\
+## Germany
+Server = https://mirror.three.de/archlinux/$repo/os/$arch
+#Server = https://mirror.two.de/archlinux/$repo/os/$arch
+Server = https://mirror.one.de/archlinux/$repo/os/$arch
+
+## Worldwide
+Server = https://geo.mirror.pkgbuild.com/$repo/os/$arch
+
+## France
+Server = https://mirror.fr/archlinux/$repo/os/$arch
+");
+
+        // the last section is followed by a blank line, and the new last one isn't
+        assert!(mirrors.move_to_front("https://mirror.fr/archlinux/$repo/os/$arch"));
+        assert_eq!(countries(&mirrors), before);
+        assert_eq!(mirrors.enabled(), vec!["https://mirror.fr/archlinux/$repo/os/$arch",
+                                           "https://mirror.three.de/archlinux/$repo/os/$arch",
+                                           "https://mirror.one.de/archlinux/$repo/os/$arch",
+                                           "https://geo.mirror.pkgbuild.com/$repo/os/$arch"]);
+        assert_eq!(mirrors.to_string().matches("\n\n").count(), 2);
+        assert!(mirrors.to_string().ends_with("pkgbuild.com/$repo/os/$arch\n"));
+        assert!(!mirrors.move_to_front("https://nowhere/"));
+    }
+}
@@ -0,0 +1,311 @@
+//! A parser for `pacman.log`, the log that libalpm and pacman write (see `Alpm::log_action`).
+//!
+//! Each entry starts with a timestamp and (since pacman 4.1) the source of the message:
+//!
+//! ```text
+//! [2017-10-03 21:10] [PACMAN] Running 'pacman -Syu'
+//! [2017-10-03 21:10] [ALPM] upgraded zlib (1:1.2.11-1 -> 1:1.2.11-2)
+//! [2019-10-08T12:00:03+0200] [ALPM] installed tzdata (2019c-1)
+//! ```
+//!
+//! ```ignore
+//! use libalpm_utils::pacman_log::{Log, LogFilter};
+//!
+//! let log = Log::from_file("/var/log/pacman.log").unwrap();
+//! let filter = LogFilter { package: Some("linux".into()), ..LogFilter::default() };
+//! for entry in log.filter(&filter) {
+//!     println!("{} {:?}", entry.timestamp, entry.action);
+//! }
+//! ```
+
+use std::fs;
+use std::io::prelude::*;
+
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+
+use ini::Error;
+
+/// Where a log message came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// libalpm itself.
+    Alpm,
+    /// The output of an install scriptlet.
+    AlpmScriptlet,
+    /// The pacman front end.
+    Pacman,
+    /// Another front end (e.g. an AUR helper).
+    Other(String),
+}
+
+impl Source {
+    fn parse(tag: &str) -> Source {
+        match tag {
+            "ALPM" => Source::Alpm,
+            "ALPM-SCRIPTLET" => Source::AlpmScriptlet,
+            "PACMAN" => Source::Pacman,
+            _ => Source::Other(tag.into()),
+        }
+    }
+}
+
+/// What happened, for the messages that are understood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Installed { name: String, version: String },
+    Reinstalled { name: String, version: String },
+    Removed { name: String, version: String },
+    Upgraded { name: String, old_version: String, new_version: String },
+    Downgraded { name: String, old_version: String, new_version: String },
+    TransactionStarted,
+    TransactionCompleted,
+    TransactionInterrupted,
+    TransactionFailed,
+    /// Any other message (e.g. a warning, or the command pacman was run with).
+    Other,
+}
+
+impl Action {
+    fn parse(message: &str) -> Action {
+        match message {
+            "transaction started" => return Action::TransactionStarted,
+            "transaction completed" => return Action::TransactionCompleted,
+            "transaction interrupted" => return Action::TransactionInterrupted,
+            "transaction failed" => return Action::TransactionFailed,
+            _ => (),
+        }
+        let mut words = message.splitn(3, ' ');
+        let (verb, name, versions) = match (words.next(), words.next(), words.next()) {
+            (Some(verb), Some(name), Some(versions))
+                if versions.starts_with('(') && versions.ends_with(')') =>
+            {
+                (verb, name.to_owned(), &versions[1..versions.len() - 1])
+            }
+            _ => return Action::Other,
+        };
+        let mut versions = versions.splitn(2, " -> ");
+        match (verb, versions.next(), versions.next()) {
+            ("installed", Some(version), None) => Action::Installed {
+                name: name,
+                version: version.into(),
+            },
+            ("reinstalled", Some(version), None) => Action::Reinstalled {
+                name: name,
+                version: version.into(),
+            },
+            ("removed", Some(version), None) => Action::Removed {
+                name: name,
+                version: version.into(),
+            },
+            ("upgraded", Some(old), Some(new)) => Action::Upgraded {
+                name: name,
+                old_version: old.into(),
+                new_version: new.into(),
+            },
+            ("downgraded", Some(old), Some(new)) => Action::Downgraded {
+                name: name,
+                old_version: old.into(),
+                new_version: new.into(),
+            },
+            _ => Action::Other,
+        }
+    }
+
+    /// The package the action is about, if any.
+    pub fn package(&self) -> Option<&str> {
+        match *self {
+            Action::Installed { ref name, .. } | Action::Reinstalled { ref name, .. }
+            | Action::Removed { ref name, .. } | Action::Upgraded { ref name, .. }
+            | Action::Downgraded { ref name, .. } => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// An entry in the log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// The local time the entry was written.
+    pub timestamp: NaiveDateTime,
+    /// The UTC offset of `timestamp`. Logs written before pacman 5.2 don't record it.
+    pub utc_offset: Option<FixedOffset>,
+    /// The source tag. Very old logs don't have one.
+    pub source: Option<Source>,
+    /// The message, without the trailing newline. Messages can span several lines.
+    pub message: String,
+    pub action: Action,
+}
+
+impl LogEntry {
+    /// The time the entry was written, taken to be at `offset` if the log doesn't say.
+    pub fn time_at(&self, offset: FixedOffset) -> DateTime<FixedOffset> {
+        self.utc_offset.unwrap_or(offset).from_local_datetime(&self.timestamp).unwrap()
+    }
+}
+
+/// Criteria for picking log entries. Unset criteria match everything.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LogFilter {
+    /// Only entries at or after this time. Entries without a UTC offset are taken to be at the
+    /// same offset as this.
+    pub since: Option<DateTime<FixedOffset>>,
+    /// Only entries before this time, with the same handling of entries without an offset.
+    pub until: Option<DateTime<FixedOffset>>,
+    /// Only actions on this package.
+    pub package: Option<String>,
+    /// Only entries from this source.
+    pub source: Option<Source>,
+}
+
+impl LogFilter {
+    /// Whether an entry matches all the criteria.
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.since.map_or(true, |since| entry.time_at(*since.offset()) >= since)
+            && self.until.map_or(true, |until| entry.time_at(*until.offset()) < until)
+            && self.package.as_ref().map_or(true, |name| entry.action.package() == Some(name))
+            && self.source.as_ref().map_or(true, |source| entry.source.as_ref() == Some(source))
+    }
+}
+
+/// A parsed `pacman.log`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Log {
+    entries: Vec<LogEntry>,
+}
+
+impl Log {
+    /// Parses the text of a log. `filename` is only used in error messages.
+    ///
+    /// Lines that don't start with a timestamp are treated as a continuation of the previous
+    /// entry (e.g. multi-line scriptlet output, which may itself start with `[`).
+    pub fn parse(filename: &str, text: &str) -> Result<Log, Error> {
+        let mut entries: Vec<LogEntry> = Vec::new();
+        for (line_no, line) in text.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let entry = if line.starts_with('[') { parse_entry(line) } else { None };
+            match (entry, entries.last_mut()) {
+                (Some(entry), _) => entries.push(entry),
+                (None, Some(entry)) => {
+                    entry.message.push('\n');
+                    entry.message.push_str(line);
+                }
+                (None, None) => {
+                    return Err(Error::Parse(filename.into(), line_no + 1, line.into()))
+                }
+            }
+        }
+        Ok(Log { entries: entries })
+    }
+
+    /// Reads and parses a log file, e.g. `/var/log/pacman.log`.
+    pub fn from_file(filename: &str) -> Result<Log, Error> {
+        let mut bytes = Vec::new();
+        fs::File::open(filename)?.read_to_end(&mut bytes)?;
+        // scriptlets can write anything, so don't fail on invalid utf-8
+        Log::parse(filename, &String::from_utf8_lossy(&bytes))
+    }
+
+    /// All the entries, oldest first.
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    /// The entries matching a filter, oldest first.
+    pub fn filter(&self, filter: &LogFilter) -> Vec<&LogEntry> {
+        self.entries.iter().filter(|entry| filter.matches(entry)).collect()
+    }
+}
+
+fn parse_entry(line: &str) -> Option<LogEntry> {
+    let end = line.find(']')?;
+    let time = &line[1..end];
+    let (timestamp, utc_offset) = match DateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S%z") {
+        Ok(time) => (time.naive_local(), Some(*time.offset())),
+        Err(_) => (NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").ok()?, None),
+    };
+    let mut rest = line[end + 1..].trim_start();
+    let mut source = None;
+    if rest.starts_with('[') {
+        let end = rest.find(']')?;
+        source = Some(Source::parse(&rest[1..end]));
+        rest = rest[end + 1..].trim_start();
+    }
+    Some(LogEntry {
+        timestamp: timestamp,
+        utc_offset: utc_offset,
+        source: source,
+        message: rest.into(),
+        action: Action::parse(rest),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &'static str = "\
+[2017-10-03 21:10] [PACMAN] Running 'pacman -Syu'
+[2017-10-03 21:10] [ALPM] transaction started
+[2017-10-03 21:10] [ALPM] upgraded zlib (1:1.2.11-1 -> 1:1.2.11-2)
+[2017-10-03 21:11] [ALPM-SCRIPTLET] >>> Updating module dependencies...
+==> Building image from preset
+[  OK  ] Image built
+[2017-10-03 21:11] [ALPM] transaction completed
+[2019-10-08T12:00:03+0200] [ALPM] installed tzdata (2019c-1)
+[2019-10-08T12:01:00+0200] [ALPM] downgraded zlib (1:1.2.11-2 -> 1:1.2.11-1)
+[2019-10-08T12:02:00+0200] [ALPM] removed tzdata (2019c-1)
+";
+
+    #[test]
+    fn parse() {
+        let log = Log::parse("pacman.log", LOG).unwrap();
+        let entries = log.entries();
+        assert_eq!(entries.len(), 8);
+        assert_eq!(entries[0].source, Some(Source::Pacman));
+        assert_eq!(entries[0].action, Action::Other);
+        assert_eq!(entries[1].action, Action::TransactionStarted);
+        assert_eq!(entries[2].action, Action::Upgraded {
+            name: "zlib".into(),
+            old_version: "1:1.2.11-1".into(),
+            new_version: "1:1.2.11-2".into(),
+        });
+        assert_eq!(entries[2].utc_offset, None);
+        assert_eq!(entries[3].source, Some(Source::AlpmScriptlet));
+        assert!(entries[3].message
+                    .ends_with("\n==> Building image from preset\n[  OK  ] Image built"));
+        assert_eq!(entries[5].utc_offset, Some(FixedOffset::east(7200)));
+        assert_eq!(entries[5].timestamp.to_string(), "2019-10-08 12:00:03");
+        assert_eq!(entries[7].action.package(), Some("tzdata"));
+
+        assert!(Log::parse("pacman.log", "[2017-10-03] installed foo (1.0)\n").is_err());
+        assert!(Log::parse("pacman.log", "installed foo (1.0)\n").is_err());
+    }
+
+    #[test]
+    fn filter() {
+        let log = Log::parse("pacman.log", LOG).unwrap();
+        let zlib = LogFilter {
+            package: Some("zlib".into()),
+            ..LogFilter::default()
+        };
+        assert_eq!(log.filter(&zlib).len(), 2);
+        let since_2018 = LogFilter {
+            since: Some(DateTime::parse_from_rfc3339("2018-01-01T00:00:00+00:00").unwrap()),
+            ..zlib.clone()
+        };
+        assert_eq!(log.filter(&since_2018).len(), 1);
+        // 12:00:30 at +0200, so the tzdata install at 12:00:03 is before it
+        let since = DateTime::parse_from_rfc3339("2019-10-08T10:00:30+00:00").unwrap();
+        let after_install = LogFilter { since: Some(since), ..LogFilter::default() };
+        assert_eq!(log.filter(&after_install).len(), 2);
+        let before_install = LogFilter { until: Some(since), ..LogFilter::default() };
+        assert_eq!(log.filter(&before_install).len(), 6);
+        let scriptlets = LogFilter {
+            source: Some(Source::AlpmScriptlet),
+            ..LogFilter::default()
+        };
+        assert_eq!(log.filter(&scriptlets).len(), 1);
+    }
+}
@@ -0,0 +1,326 @@
+//! The `.PKGINFO` and `.BUILDINFO` metadata inside package files (`*.pkg.tar.*`).
+//!
+//! Unlike `Alpm::load_package`, this doesn't need a libalpm handle, so it can be used to index
+//! packages that aren't in any repository yet.
+//!
+//! ```ignore
+//! use libalpm_utils::pkginfo::PackageFile;
+//!
+//! let pkg = PackageFile::open("foo-1.0-1-x86_64.pkg.tar.zst").unwrap();
+//! println!("{} {}", pkg.pkginfo.name, pkg.pkginfo.version);
+//! if let Some(buildinfo) = pkg.buildinfo {
+//!     println!("built with {:?}", buildinfo.options);
+//! }
+//! ```
+
+use std::io::prelude::*;
+use std::path::Path;
+
+use archive;
+use depend::Depend;
+use desc::PackageRecord;
+use ini::Error;
+
+/// The contents of a `.PKGINFO` file.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PkgInfo {
+    pub name: String,
+    pub base: Option<String>,
+    pub version: String,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    /// Seconds since the unix epoch.
+    pub build_date: Option<i64>,
+    pub packager: Option<String>,
+    /// The size of the package when it is installed.
+    pub size: Option<u64>,
+    pub arch: Option<String>,
+    pub licenses: Vec<String>,
+    pub groups: Vec<String>,
+    pub replaces: Vec<Depend>,
+    pub conflicts: Vec<Depend>,
+    pub provides: Vec<Depend>,
+    /// Files to back up, relative to the root, e.g. `etc/pacman.conf`.
+    pub backup: Vec<String>,
+    pub depends: Vec<Depend>,
+    pub optional_depends: Vec<Depend>,
+    pub make_depends: Vec<Depend>,
+    pub check_depends: Vec<Depend>,
+    /// Extra `key=value` data, e.g. `pkgtype=pkg`.
+    pub xdata: Vec<String>,
+}
+
+impl PkgInfo {
+    /// Parses the text of a `.PKGINFO` file. `filename` is only used in error messages.
+    ///
+    /// Fails if `pkgname` or `pkgver` is missing. Unknown keys are ignored.
+    pub fn parse(filename: &str, text: &str) -> Result<PkgInfo, Error> {
+        let mut info = PkgInfo::default();
+        for (line_no, key, value) in parse_pairs(filename, text)? {
+            let err = || Error::Parse(filename.into(), line_no, value.clone());
+            let number = || value.parse::<i64>().map_err(|_| err());
+            match key.as_str() {
+                "pkgname" => info.name = value,
+                "pkgbase" => info.base = Some(value),
+                "pkgver" => info.version = value,
+                "pkgdesc" => info.description = Some(value),
+                "url" => info.url = Some(value),
+                "builddate" => info.build_date = Some(number()?),
+                "packager" => info.packager = Some(value),
+                "size" => info.size = Some(number()? as u64),
+                "arch" => info.arch = Some(value),
+                "license" => info.licenses.push(value),
+                "group" => info.groups.push(value),
+                "replaces" => info.replaces.push(Depend::parse(&value)),
+                "conflict" => info.conflicts.push(Depend::parse(&value)),
+                "provides" => info.provides.push(Depend::parse(&value)),
+                "backup" => info.backup.push(value),
+                "depend" => info.depends.push(Depend::parse(&value)),
+                "optdepend" => info.optional_depends.push(Depend::parse(&value)),
+                "makedepend" => info.make_depends.push(Depend::parse(&value)),
+                "checkdepend" => info.check_depends.push(Depend::parse(&value)),
+                "xdata" => info.xdata.push(value),
+                _ => (),
+            }
+        }
+        if info.name.is_empty() || info.version.is_empty() {
+            return Err(Error::Parse(filename.into(), 0, "missing pkgname or pkgver".into()));
+        }
+        Ok(info)
+    }
+
+    /// Converts the metadata to a database record. The fields that only come from the package
+    /// file or the database (e.g. checksums) are left empty.
+    pub fn to_record(&self) -> PackageRecord {
+        PackageRecord {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            base: self.base.clone(),
+            description: self.description.clone(),
+            url: self.url.clone(),
+            arch: self.arch.clone(),
+            build_date: self.build_date,
+            packager: self.packager.clone(),
+            installed_size: self.size,
+            licenses: self.licenses.clone(),
+            groups: self.groups.clone(),
+            replaces: self.replaces.clone(),
+            depends: self.depends.clone(),
+            optional_depends: self.optional_depends.clone(),
+            make_depends: self.make_depends.clone(),
+            check_depends: self.check_depends.clone(),
+            conflicts: self.conflicts.clone(),
+            provides: self.provides.clone(),
+            xdata: self.xdata.clone(),
+            ..PackageRecord::default()
+        }
+    }
+}
+
+/// A package that was installed when a package was built, from `installed = ` in `.BUILDINFO`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledPackage {
+    pub name: String,
+    /// The full version, e.g. `1:2.0-1`.
+    pub version: String,
+    pub arch: String,
+}
+
+impl InstalledPackage {
+    /// Parses a `name-pkgver-pkgrel-arch` string.
+    pub fn parse(s: &str) -> Option<InstalledPackage> {
+        let mut parts = s.rsplitn(4, '-');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(arch), Some(rel), Some(ver), Some(name)) if !name.is_empty() => {
+                Some(InstalledPackage {
+                    name: name.into(),
+                    version: format!("{}-{}", ver, rel),
+                    arch: arch.into(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The contents of a `.BUILDINFO` file, describing the environment a package was built in.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BuildInfo {
+    /// The `format` of the file (missing in the first version of the format).
+    pub format: Option<u32>,
+    pub name: String,
+    pub base: Option<String>,
+    pub version: String,
+    pub arch: Option<String>,
+    /// The sha256 of the PKGBUILD the package was built from.
+    pub pkgbuild_sha256sum: Option<String>,
+    pub packager: Option<String>,
+    /// Seconds since the unix epoch.
+    pub build_date: Option<i64>,
+    pub build_dir: Option<String>,
+    pub start_dir: Option<String>,
+    /// The tool used to build the package, e.g. `makepkg` or `devtools`.
+    pub build_tool: Option<String>,
+    pub build_tool_version: Option<String>,
+    /// The `BUILDENV` from makepkg.conf, e.g. `!distcc` or `color`.
+    pub build_env: Vec<String>,
+    /// The `OPTIONS` from makepkg.conf and the PKGBUILD, e.g. `strip` or `!debug`.
+    pub options: Vec<String>,
+    /// The packages installed on the system the package was built on.
+    pub installed: Vec<InstalledPackage>,
+}
+
+impl BuildInfo {
+    /// Parses the text of a `.BUILDINFO` file. `filename` is only used in error messages.
+    pub fn parse(filename: &str, text: &str) -> Result<BuildInfo, Error> {
+        let mut info = BuildInfo::default();
+        for (line_no, key, value) in parse_pairs(filename, text)? {
+            let err = || Error::Parse(filename.into(), line_no, value.clone());
+            match key.as_str() {
+                "format" => info.format = Some(value.parse().map_err(|_| err())?),
+                "pkgname" => info.name = value,
+                "pkgbase" => info.base = Some(value),
+                "pkgver" => info.version = value,
+                "pkgarch" => info.arch = Some(value),
+                "pkgbuild_sha256sum" => info.pkgbuild_sha256sum = Some(value),
+                "packager" => info.packager = Some(value),
+                "builddate" => info.build_date = Some(value.parse().map_err(|_| err())?),
+                "builddir" => info.build_dir = Some(value),
+                "startdir" => info.start_dir = Some(value),
+                "buildtool" => info.build_tool = Some(value),
+                "buildtoolver" => info.build_tool_version = Some(value),
+                "buildenv" => info.build_env.push(value),
+                "options" => info.options.push(value),
+                "installed" => {
+                    info.installed.push(InstalledPackage::parse(&value).ok_or_else(err)?)
+                }
+                _ => (),
+            }
+        }
+        Ok(info)
+    }
+}
+
+/// The metadata read from a package file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageFile {
+    pub pkginfo: PkgInfo,
+    /// Packages built before pacman 5.1 don't have a `.BUILDINFO`.
+    pub buildinfo: Option<BuildInfo>,
+    /// The files and directories in the package, in archive order. Directories end in `/`.
+    pub files: Vec<String>,
+}
+
+impl PackageFile {
+    /// Reads the metadata and the file list from a package file.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<PackageFile, Error> {
+        let filename = path.as_ref().display().to_string();
+        let mut pkginfo = None;
+        let mut buildinfo = None;
+        let mut files = Vec::new();
+        for entry in archive::open(path)?.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            if name == ".PKGINFO" || name == ".BUILDINFO" {
+                let mut text = String::new();
+                entry.read_to_string(&mut text)?;
+                let member = format!("{}/{}", filename, name);
+                if name == ".PKGINFO" {
+                    pkginfo = Some(PkgInfo::parse(&member, &text)?);
+                } else {
+                    buildinfo = Some(BuildInfo::parse(&member, &text)?);
+                }
+            } else if !name.starts_with('.') {
+                if entry.header().entry_type().is_dir() && !name.ends_with('/') {
+                    files.push(format!("{}/", name));
+                } else {
+                    files.push(name);
+                }
+            }
+        }
+        Ok(PackageFile {
+            pkginfo: pkginfo.ok_or_else(|| Error::Parse(filename, 0, "no .PKGINFO".into()))?,
+            buildinfo: buildinfo,
+            files: files,
+        })
+    }
+}
+
+/// Splits a `key = value` file into its (1-based) line numbers, keys and values, skipping
+/// comments and blank lines.
+fn parse_pairs(filename: &str, text: &str) -> Result<Vec<(usize, String, String)>, Error> {
+    let mut pairs = Vec::new();
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let idx = line.find('=').ok_or_else(|| {
+            Error::Parse(filename.into(), line_no + 1, line.into())
+        })?;
+        pairs.push((line_no + 1, line[..idx].trim().into(), line[idx + 1..].trim().into()));
+    }
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pkginfo() {
+        let text = "# Generated by makepkg 5.0.2\n\
+                    pkgname = pacman\n\
+                    pkgbase = pacman\n\
+                    pkgver = 5.0.2-2\n\
+                    pkgdesc = A library-based package manager with dependency support\n\
+                    builddate = 1500000000\n\
+                    size = 4726784\n\
+                    arch = x86_64\n\
+                    license = GPL\n\
+                    backup = etc/pacman.conf\n\
+                    backup = etc/makepkg.conf\n\
+                    depend = bash\n\
+                    depend = libarchive>=3.1.2\n\
+                    optdepend = perl-locale-gettext: translation support in makepkg-template\n\
+                    makedepend = asciidoc\n";
+        let info = PkgInfo::parse(".PKGINFO", text).unwrap();
+        assert_eq!(info.name, "pacman");
+        assert_eq!(info.version, "5.0.2-2");
+        assert_eq!(info.size, Some(4726784));
+        assert_eq!(info.backup, vec!["etc/pacman.conf", "etc/makepkg.conf"]);
+        assert_eq!(info.depends[1], Depend::parse("libarchive>=3.1.2"));
+        assert_eq!(info.optional_depends[0].description,
+                   Some("translation support in makepkg-template".into()));
+        assert_eq!(info.make_depends[0].name, "asciidoc");
+        assert_eq!(info.to_record().installed_size, Some(4726784));
+        assert!(PkgInfo::parse(".PKGINFO", "pkgname = foo\n").is_err());
+        assert!(PkgInfo::parse(".PKGINFO", "pkgname\n").is_err());
+    }
+
+    #[test]
+    fn buildinfo() {
+        let text = "format = 1\n\
+                    pkgname = zlib\n\
+                    pkgver = 1:1.2.11-2\n\
+                    pkgarch = x86_64\n\
+                    builddate = 1500000000\n\
+                    buildenv = !distcc\n\
+                    buildenv = color\n\
+                    options = strip\n\
+                    options = !debug\n\
+                    installed = gcc-libs-multilib-7.2.0-3-x86_64\n\
+                    installed = zlib-1:1.2.11-2-x86_64\n";
+        let info = BuildInfo::parse(".BUILDINFO", text).unwrap();
+        assert_eq!(info.format, Some(1));
+        assert_eq!(info.build_env, vec!["!distcc", "color"]);
+        assert_eq!(info.options, vec!["strip", "!debug"]);
+        assert_eq!(info.installed[0], InstalledPackage {
+            name: "gcc-libs-multilib".into(),
+            version: "7.2.0-3".into(),
+            arch: "x86_64".into(),
+        });
+        assert_eq!(info.installed[1].version, "1:1.2.11-2");
+        assert!(BuildInfo::parse(".BUILDINFO", "installed = foo\n").is_err());
+    }
+}
@@ -0,0 +1,284 @@
+//! Building and updating the sync databases of a repository, like `repo-add` and `repo-remove`.
+//!
+//! A repository named `custom` is a directory of package files along with `custom.db.tar.*` and
+//! `custom.files.tar.*` archives, and `custom.db` and `custom.files` symlinks to them (libalpm
+//! downloads `$server/custom.db`).
+//!
+//! ```ignore
+//! use libalpm_utils::repo_db::RepoDb;
+//!
+//! let mut repo = RepoDb::open("/srv/repo/custom.db.tar.gz").unwrap();
+//! repo.add_package("/srv/repo/foo-1.0-1-x86_64.pkg.tar.zst").unwrap();
+//! repo.remove_stale();
+//! repo.write().unwrap();
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::io::prelude::*;
+use std::os::unix;
+use std::path::{Path, PathBuf};
+
+use base64;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use tar::{Builder, EntryType, Header};
+
+use archive::{self, Compression};
+use desc::PackageRecord;
+use document::write_atomic;
+use ini::Error;
+use pkginfo::PackageFile;
+use sync_db::SyncDb;
+
+/// The sync databases of a repository, held in memory while they are edited.
+#[derive(Debug, Clone)]
+pub struct RepoDb {
+    /// The directory holding the databases and the packages.
+    dir: PathBuf,
+    name: String,
+    /// The archive extension, e.g. `.tar.gz`.
+    extension: String,
+    compression: Compression,
+    signatures: bool,
+    /// Package name -> record.
+    packages: BTreeMap<String, PackageRecord>,
+}
+
+impl RepoDb {
+    /// Creates an empty repository database. `db_path` is the path of the `.db` archive, e.g.
+    /// `/srv/repo/custom.db.tar.gz`, and its extension decides the compression.
+    pub fn new<P: AsRef<Path>>(db_path: P) -> Result<RepoDb, Error> {
+        let db_path = db_path.as_ref();
+        let invalid = || {
+            Error::Parse(db_path.display().to_string(), 0,
+                         "the database must end in .db.tar, .db.tar.gz, .db.tar.xz or .db.tar.zst"
+                             .into())
+        };
+        let file_name = db_path.file_name().and_then(|n| n.to_str()).ok_or_else(&invalid)?;
+        let idx = file_name.find(".db.").ok_or_else(&invalid)?;
+        let extension = &file_name[idx + 3..];
+        let compression = Compression::from_extension(extension).ok_or_else(&invalid)?;
+        Ok(RepoDb {
+            dir: db_path.parent().unwrap_or(Path::new("")).to_owned(),
+            name: file_name[..idx].into(),
+            extension: extension.into(),
+            compression: compression,
+            signatures: false,
+            packages: BTreeMap::new(),
+        })
+    }
+
+    /// Opens an existing repository database, or creates an empty one if it doesn't exist yet.
+    ///
+    /// The entries are read from the `.files` database if there is one, so that the file lists
+    /// are kept.
+    pub fn open<P: AsRef<Path>>(db_path: P) -> Result<RepoDb, Error> {
+        let mut repo = RepoDb::new(db_path)?;
+        for path in &[repo.files_path(), repo.db_path()] {
+            match SyncDb::open(path) {
+                Ok(db) => {
+                    for pkg in db.into_packages() {
+                        repo.packages.insert(pkg.name.clone(), pkg);
+                    }
+                    break;
+                }
+                Err(Error::Io(ref e)) if e.kind() == ErrorKind::NotFound => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(repo)
+    }
+
+    /// Whether to include detached signatures (`<package>.sig`) in the database when adding
+    /// packages, like `repo-add --include-sigs`.
+    pub fn include_signatures(&mut self, include: bool) -> &mut RepoDb {
+        self.signatures = include;
+        self
+    }
+
+    /// The name of the repository, e.g. `custom`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The path of the `.db` archive.
+    pub fn db_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.db{}", self.name, self.extension))
+    }
+
+    /// The path of the `.files` archive.
+    pub fn files_path(&self) -> PathBuf {
+        self.dir.join(format!("{}.files{}", self.name, self.extension))
+    }
+
+    /// The entries in the database, sorted by name.
+    pub fn packages(&self) -> Vec<&PackageRecord> {
+        self.packages.values().collect()
+    }
+
+    /// Gets the entry for a package.
+    pub fn package(&self, name: &str) -> Option<&PackageRecord> {
+        self.packages.get(name)
+    }
+
+    /// Adds a package file to the database, replacing any entry with the same name.
+    ///
+    /// The metadata comes from the package's `.PKGINFO`, and the checksums and size are
+    /// computed from the file. The package should be in the same directory as the database,
+    /// since only its file name is recorded. Returns the replaced entry, if there was one.
+    pub fn add_package<P: AsRef<Path>>(&mut self, path: P)
+        -> Result<Option<PackageRecord>, Error>
+    {
+        let path = path.as_ref();
+        let package = PackageFile::open(path)?;
+        let mut pkg = package.pkginfo.to_record();
+        pkg.files = package.files;
+        pkg.filename = path.file_name().and_then(|n| n.to_str()).map(|n| n.to_owned());
+
+        let mut file = fs::File::open(path)?;
+        let mut md5 = Md5::new();
+        let mut sha256 = Sha256::new();
+        let mut size = 0;
+        let mut buf = [0; 64 * 1024];
+        loop {
+            let len = match file.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+            md5.update(&buf[..len]);
+            sha256.update(&buf[..len]);
+            size += len as u64;
+        }
+        pkg.download_size = Some(size);
+        pkg.md5sum = Some(format!("{:x}", md5.finalize()));
+        pkg.sha256sum = Some(format!("{:x}", sha256.finalize()));
+
+        if self.signatures {
+            let mut sig_path = path.as_os_str().to_owned();
+            sig_path.push(".sig");
+            match fs::File::open(&sig_path) {
+                Ok(mut file) => {
+                    let mut sig = Vec::new();
+                    file.read_to_end(&mut sig)?;
+                    pkg.pgp_signature = Some(base64::encode(&sig));
+                }
+                Err(ref e) if e.kind() == ErrorKind::NotFound => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(self.packages.insert(pkg.name.clone(), pkg))
+    }
+
+    /// Removes the entry for a package, like `repo-remove`.
+    pub fn remove_package(&mut self, name: &str) -> Option<PackageRecord> {
+        self.packages.remove(name)
+    }
+
+    /// Removes the entries whose package file is no longer in the repository directory, and
+    /// returns them.
+    pub fn remove_stale(&mut self) -> Vec<PackageRecord> {
+        let stale: Vec<String> = self.packages.values()
+            .filter(|pkg| match pkg.filename {
+                Some(ref filename) => !self.dir.join(filename).is_file(),
+                None => true,
+            })
+            .map(|pkg| pkg.name.clone())
+            .collect();
+        stale.iter().filter_map(|name| self.packages.remove(name)).collect()
+    }
+
+    /// Writes the `.db` and `.files` archives and their symlinks.
+    pub fn write(&self) -> Result<(), Error> {
+        self.write_archive(&self.db_path(), false)?;
+        self.write_archive(&self.files_path(), true)?;
+        for (link, target) in &[("db", self.db_path()), ("files", self.files_path())] {
+            let link = self.dir.join(format!("{}.{}", self.name, link));
+            match fs::symlink_metadata(&link) {
+                Ok(_) => fs::remove_file(&link)?,
+                Err(ref e) if e.kind() == ErrorKind::NotFound => (),
+                Err(e) => return Err(e.into()),
+            }
+            unix::fs::symlink(target.file_name().unwrap(), &link)?;
+        }
+        Ok(())
+    }
+
+    fn write_archive(&self, path: &Path, files: bool) -> Result<(), Error> {
+        let mut builder = Builder::new(Vec::new());
+        for pkg in self.packages.values() {
+            let dir = format!("{}-{}", pkg.name, pkg.version);
+            let mut header = Header::new_gnu();
+            header.set_entry_type(EntryType::Directory);
+            header.set_size(0);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder.append_data(&mut header, format!("{}/", dir), io::empty())?;
+            let mut entries = vec![("desc", pkg.write_desc())];
+            if files {
+                entries.push(("files", pkg.write_files()));
+            }
+            for (name, text) in entries {
+                let mut header = Header::new_gnu();
+                header.set_size(text.len() as u64);
+                header.set_mode(0o644);
+                header.set_cksum();
+                builder.append_data(&mut header, format!("{}/{}", dir, name), text.as_bytes())?;
+            }
+        }
+        let data = archive::compress(&builder.into_inner()?, self.compression)?;
+        write_atomic(&path.display().to_string(), data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use depend::Depend;
+    use testing::{TempDir, build_package};
+
+    use super::*;
+
+    #[test]
+    fn repo_db() {
+        let dir = TempDir::new("repo-db");
+        let foo = build_package(&dir, "foo", "1.0-1");
+        let bar = build_package(&dir, "bar", "2.0-1");
+        fs::File::create(dir.join("foo-1.0-1-x86_64.pkg.tar.gz.sig")).unwrap()
+            .write_all(b"sig").unwrap();
+
+        assert!(RepoDb::new(dir.join("test.tar.gz")).is_err());
+        let mut repo = RepoDb::open(dir.join("test.db.tar.gz")).unwrap();
+        assert!(repo.packages().is_empty());
+        repo.include_signatures(true);
+        assert!(repo.add_package(&foo).unwrap().is_none());
+        repo.add_package(&bar).unwrap();
+        repo.write().unwrap();
+
+        let db = SyncDb::open(dir.join("test.db")).unwrap();
+        assert_eq!(db.packages().len(), 2);
+        let pkg = db.package("foo").unwrap();
+        assert_eq!(pkg.filename, Some("foo-1.0-1-x86_64.pkg.tar.gz".into()));
+        assert_eq!(pkg.pgp_signature, Some("c2ln".into()));
+        assert_eq!(pkg.depends, vec![Depend::parse("glibc>=2.26")]);
+        assert_eq!(pkg.sha256sum.as_ref().map(|s| s.len()), Some(64));
+        assert!(pkg.files.is_empty());
+
+        // reopening keeps the file lists from the .files database
+        let mut repo = RepoDb::open(dir.join("test.db.tar.gz")).unwrap();
+        assert_eq!(repo.package("foo").unwrap().files, vec!["usr/bin/foo"]);
+        let foo2 = build_package(&dir, "foo", "1.1-1");
+        assert_eq!(repo.add_package(&foo2).unwrap().unwrap().version, "1.0-1");
+        fs::remove_file(&bar).unwrap();
+        let stale = repo.remove_stale();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].name, "bar");
+        repo.write().unwrap();
+
+        let db = SyncDb::open(dir.join("test.files")).unwrap();
+        assert_eq!(db.packages().len(), 1);
+        assert_eq!(db.package("foo").unwrap().version, "1.1-1");
+    }
+}
@@ -0,0 +1,329 @@
+//! A parser for `.SRCINFO` files, the machine readable summary of a PKGBUILD.
+//!
+//! A `.SRCINFO` has a `pkgbase` section followed by a `pkgname` section for each package built
+//! from the PKGBUILD. Package sections inherit everything from `pkgbase`, and any key they set
+//! (even to an empty value) replaces the inherited one. Most array keys can also be given per
+//! architecture, e.g. `depends_x86_64`.
+//!
+//! ```ignore
+//! use libalpm_utils::srcinfo::SrcInfo;
+//!
+//! let srcinfo = SrcInfo::from_file("pacman/.SRCINFO").unwrap();
+//! for pkg in srcinfo.packages() {
+//!     for dep in pkg.depends.get("x86_64") {
+//!         // `dep.to_string()` is a dependency string for satisfier lookups
+//!         println!("{} needs {}", pkg.name, dep);
+//!     }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::prelude::*;
+
+use depend::Depend;
+use ini::Error;
+
+/// Values that can be given for all architectures and for specific ones (e.g. `source` and
+/// `source_x86_64`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchSpecific<T> {
+    /// The values for every architecture.
+    pub any: Vec<T>,
+    /// The extra values for each architecture.
+    pub arch: BTreeMap<String, Vec<T>>,
+}
+
+impl<T> ArchSpecific<T> {
+    /// Gets the values that apply to an architecture, the common ones first.
+    pub fn get(&self, arch: &str) -> Vec<&T> {
+        let extra = self.arch.get(arch).into_iter().flat_map(|values| values.iter());
+        self.any.iter().chain(extra).collect()
+    }
+
+    /// Whether there are no values for any architecture.
+    pub fn is_empty(&self) -> bool {
+        self.any.is_empty() && self.arch.values().all(|values| values.is_empty())
+    }
+}
+
+impl<T> Default for ArchSpecific<T> {
+    fn default() -> ArchSpecific<T> {
+        ArchSpecific {
+            any: Vec::new(),
+            arch: BTreeMap::new(),
+        }
+    }
+}
+
+/// The `pkgbase` section: what is shared by all the packages built from a PKGBUILD.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PackageBase {
+    pub name: String,
+    pub pkgver: String,
+    pub pkgrel: String,
+    pub epoch: Option<String>,
+    pub make_depends: ArchSpecific<Depend>,
+    pub check_depends: ArchSpecific<Depend>,
+    pub sources: ArchSpecific<String>,
+    /// The checksums of the sources by kind, e.g. `sha256sums`.
+    pub checksums: BTreeMap<String, ArchSpecific<String>>,
+    pub valid_pgp_keys: Vec<String>,
+    pub no_extract: Vec<String>,
+}
+
+impl PackageBase {
+    /// The full version, `epoch:pkgver-pkgrel`.
+    pub fn version(&self) -> String {
+        match self.epoch {
+            Some(ref epoch) if epoch != "0" => format!("{}:{}-{}", epoch, self.pkgver, self.pkgrel),
+            _ => format!("{}-{}", self.pkgver, self.pkgrel),
+        }
+    }
+}
+
+/// A package built from the PKGBUILD, with the values inherited from `pkgbase` filled in.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Package {
+    pub name: String,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    /// The architectures the package can be built for, e.g. `x86_64` or `any`.
+    pub arch: Vec<String>,
+    pub licenses: Vec<String>,
+    pub groups: Vec<String>,
+    pub depends: ArchSpecific<Depend>,
+    pub optional_depends: ArchSpecific<Depend>,
+    pub provides: ArchSpecific<Depend>,
+    pub conflicts: ArchSpecific<Depend>,
+    pub replaces: ArchSpecific<Depend>,
+    pub backup: Vec<String>,
+    pub options: Vec<String>,
+    pub install: Option<String>,
+    pub changelog: Option<String>,
+}
+
+/// A parsed `.SRCINFO` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrcInfo {
+    base: PackageBase,
+    packages: Vec<Package>,
+}
+
+/// A key (without the architecture suffix), the architecture and the value.
+type Field = (String, Option<String>, String);
+
+impl SrcInfo {
+    /// Parses the text of a `.SRCINFO` file. `filename` is only used in error messages.
+    pub fn parse(filename: &str, text: &str) -> Result<SrcInfo, Error> {
+        let mut base: Option<(String, Vec<Field>)> = None;
+        let mut packages: Vec<(String, Vec<Field>)> = Vec::new();
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = || Error::Parse(filename.into(), line_no + 1, line.into());
+            let idx = line.find('=').ok_or_else(&err)?;
+            let (key, value) = (line[..idx].trim(), line[idx + 1..].trim().to_owned());
+            match key {
+                "pkgbase" if base.is_none() => base = Some((value, Vec::new())),
+                "pkgname" if base.is_some() => packages.push((value, Vec::new())),
+                "pkgbase" | "pkgname" => return Err(err()),
+                _ => {
+                    let (key, arch) = match key.find('_') {
+                        Some(idx) => (&key[..idx], Some(key[idx + 1..].to_owned())),
+                        None => (key, None),
+                    };
+                    let fields = match packages.last_mut() {
+                        Some(&mut (_, ref mut fields)) => fields,
+                        None => &mut base.as_mut().ok_or_else(&err)?.1,
+                    };
+                    fields.push((key.into(), arch, value));
+                }
+            }
+        }
+        let (base_name, base_fields) = base.ok_or_else(|| {
+            Error::Parse(filename.into(), 0, "missing pkgbase".into())
+        })?;
+        Ok(SrcInfo {
+            base: build_base(base_name, &base_fields),
+            packages: packages.into_iter()
+                .map(|(name, fields)| build_package(name, &inherit(&base_fields, fields)))
+                .collect(),
+        })
+    }
+
+    /// Reads and parses a `.SRCINFO` file.
+    pub fn from_file(filename: &str) -> Result<SrcInfo, Error> {
+        let mut text = String::new();
+        fs::File::open(filename)?.read_to_string(&mut text)?;
+        SrcInfo::parse(filename, &text)
+    }
+
+    /// The `pkgbase` section.
+    pub fn base(&self) -> &PackageBase {
+        &self.base
+    }
+
+    /// The packages built from the PKGBUILD. There is more than one for split packages.
+    pub fn packages(&self) -> &[Package] {
+        &self.packages
+    }
+
+    /// Gets a package by name.
+    pub fn package(&self, name: &str) -> Option<&Package> {
+        self.packages.iter().find(|pkg| pkg.name == name)
+    }
+}
+
+/// Merges a package section into the fields of `pkgbase`. A key set in the package section
+/// replaces all the values for that key and architecture.
+fn inherit(base: &[Field], package: Vec<Field>) -> Vec<Field> {
+    let mut fields: Vec<Field> = base.iter()
+        .filter(|&&(ref key, ref arch, _)| {
+            !package.iter().any(|&(ref k, ref a, _)| k == key && a == arch)
+        })
+        .cloned()
+        .collect();
+    fields.extend(package);
+    fields
+}
+
+/// Gets the (non-empty) values of a key that isn't architecture specific.
+fn values(fields: &[Field], key: &str) -> Vec<String> {
+    fields.iter()
+        .filter(|&&(ref k, ref arch, ref value)| k == key && arch.is_none() && !value.is_empty())
+        .map(|&(_, _, ref value)| value.clone())
+        .collect()
+}
+
+fn value(fields: &[Field], key: &str) -> Option<String> {
+    values(fields, key).pop()
+}
+
+fn arch_specific<T, F: Fn(&str) -> T>(fields: &[Field], key: &str, f: F) -> ArchSpecific<T> {
+    let mut out = ArchSpecific::default();
+    for &(ref k, ref arch, ref value) in fields {
+        if k != key || value.is_empty() {
+            continue;
+        }
+        match *arch {
+            Some(ref arch) => out.arch.entry(arch.clone()).or_insert_with(Vec::new).push(f(value)),
+            None => out.any.push(f(value)),
+        }
+    }
+    out
+}
+
+fn depends(fields: &[Field], key: &str) -> ArchSpecific<Depend> {
+    arch_specific(fields, key, Depend::parse)
+}
+
+fn build_base(name: String, fields: &[Field]) -> PackageBase {
+    let mut checksums = BTreeMap::new();
+    for kind in &["md5sums", "sha1sums", "sha224sums", "sha256sums", "sha384sums", "sha512sums",
+                  "b2sums"] {
+        let sums = arch_specific(fields, kind, |s| s.to_owned());
+        if !sums.is_empty() {
+            checksums.insert(kind.to_string(), sums);
+        }
+    }
+    PackageBase {
+        name: name,
+        pkgver: value(fields, "pkgver").unwrap_or_default(),
+        pkgrel: value(fields, "pkgrel").unwrap_or_default(),
+        epoch: value(fields, "epoch"),
+        make_depends: depends(fields, "makedepends"),
+        check_depends: depends(fields, "checkdepends"),
+        sources: arch_specific(fields, "source", |s| s.to_owned()),
+        checksums: checksums,
+        valid_pgp_keys: values(fields, "validpgpkeys"),
+        no_extract: values(fields, "noextract"),
+    }
+}
+
+fn build_package(name: String, fields: &[Field]) -> Package {
+    Package {
+        name: name,
+        description: value(fields, "pkgdesc"),
+        url: value(fields, "url"),
+        arch: values(fields, "arch"),
+        licenses: values(fields, "license"),
+        groups: values(fields, "groups"),
+        depends: depends(fields, "depends"),
+        optional_depends: depends(fields, "optdepends"),
+        provides: depends(fields, "provides"),
+        conflicts: depends(fields, "conflicts"),
+        replaces: depends(fields, "replaces"),
+        backup: values(fields, "backup"),
+        options: values(fields, "options"),
+        install: value(fields, "install"),
+        changelog: value(fields, "changelog"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRCINFO: &'static str = "\
+# Generated by mksrcinfo
+pkgbase = gcc
+\tpkgdesc = The GNU Compiler Collection
+\tpkgver = 7.2.0
+\tpkgrel = 3
+\turl = http://gcc.gnu.org
+\tarch = x86_64
+\tarch = i686
+\tlicense = GPL
+\tmakedepends = binutils>=2.28
+\tmakedepends_x86_64 = lib32-glibc>=2.26
+\tdepends = zlib
+\tsource = https://ftp.gnu.org/gnu/gcc/gcc-7.2.0/gcc-7.2.0.tar.xz
+\tsource_i686 = i686.patch
+\tsha256sums = SKIP
+\tsha256sums_i686 = 0123
+
+pkgname = gcc
+\tdepends = gcc-libs=7.2.0-3
+\tdepends = binutils>=2.28
+\toptdepends_x86_64 = lib32-gcc-libs: for 32-bit programs
+
+pkgname = gcc-libs
+\tpkgdesc = Runtime libraries shipped by GCC
+\tdepends =
+\toptions = !strip
+";
+
+    #[test]
+    fn parse() {
+        let srcinfo = SrcInfo::parse(".SRCINFO", SRCINFO).unwrap();
+        let base = srcinfo.base();
+        assert_eq!(base.name, "gcc");
+        assert_eq!(base.version(), "7.2.0-3");
+        assert_eq!(base.make_depends.get("x86_64").len(), 2);
+        assert_eq!(base.make_depends.get("i686"), vec![&Depend::parse("binutils>=2.28")]);
+        assert_eq!(base.sources.get("i686").len(), 2);
+        assert_eq!(base.checksums["sha256sums"].get("x86_64"), vec!["SKIP"]);
+
+        assert_eq!(srcinfo.packages().len(), 2);
+        let gcc = srcinfo.package("gcc").unwrap();
+        assert_eq!(gcc.description, Some("The GNU Compiler Collection".into()));
+        assert_eq!(gcc.arch, vec!["x86_64", "i686"]);
+        assert_eq!(gcc.depends.get("x86_64")[0].to_string(), "gcc-libs=7.2.0-3");
+        assert_eq!(gcc.depends.any.len(), 2);
+        assert_eq!(gcc.optional_depends.get("x86_64").len(), 1);
+        assert!(gcc.optional_depends.get("i686").is_empty());
+
+        let libs = srcinfo.package("gcc-libs").unwrap();
+        assert_eq!(libs.description, Some("Runtime libraries shipped by GCC".into()));
+        assert!(libs.depends.is_empty());
+        assert_eq!(libs.options, vec!["!strip"]);
+        assert_eq!(libs.licenses, vec!["GPL"]);
+
+        assert!(SrcInfo::parse(".SRCINFO", "pkgname = foo\n").is_err());
+        assert!(SrcInfo::parse(".SRCINFO", "pkgdesc = foo\n").is_err());
+        assert!(SrcInfo::parse(".SRCINFO", "pkgbase = foo\nbar\n").is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! A pure-rust reader for sync databases (e.g. `core.db` or `extra.files`).
+//!
+//! A sync database is a (usually compressed) tar archive with a directory per package named
+//! `name-version`, containing a `desc` entry and, in `.files` databases, a `files` entry. Old
+//! databases also split the dependency fields out into a `depends` entry.
+//!
+//! ```ignore
+//! use libalpm_utils::sync_db::SyncDb;
+//!
+//! let core = SyncDb::open("/var/lib/pacman/sync/core.db").unwrap();
+//! for pkg in core.packages() {
+//!     println!("{} {}", pkg.name, pkg.version);
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::prelude::*;
+use std::path::Path;
+
+use tar::Archive;
+
+use archive;
+use desc::PackageRecord;
+use ini::Error;
+
+/// The packages in a sync database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncDb {
+    name: String,
+    packages: Vec<PackageRecord>,
+}
+
+impl SyncDb {
+    /// Reads a sync database file. The database name is the file name up to the first `.`
+    /// (e.g. `core` for `core.db`).
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SyncDb, Error> {
+        let path = path.as_ref();
+        let name = path.file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.split('.').next())
+            .unwrap_or("")
+            .to_owned();
+        SyncDb::from_reader(&name, fs::File::open(path)?)
+    }
+
+    /// Reads a sync database from a reader (e.g. a download in progress). The compression is
+    /// detected automatically.
+    pub fn from_reader<R: Read>(name: &str, reader: R) -> Result<SyncDb, Error> {
+        let mut archive = Archive::new(archive::decompress(reader)?);
+        let mut packages: Vec<PackageRecord> = Vec::new();
+        // package directory -> index in `packages`
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_string_lossy().into_owned();
+            let mut parts = path.trim_end_matches('/').splitn(2, '/');
+            let dir = match (parts.next(), parts.next()) {
+                (Some(dir), Some(_)) => dir.to_owned(),
+                // the package directories themselves
+                _ => continue,
+            };
+            let mut text = String::new();
+            entry.read_to_string(&mut text)?;
+            let idx = *index.entry(dir).or_insert_with(|| {
+                packages.push(PackageRecord::default());
+                packages.len() - 1
+            });
+            packages[idx].add_metadata(&path, &text)?;
+        }
+        Ok(SyncDb {
+            name: name.into(),
+            packages: packages,
+        })
+    }
+
+    /// The name of the database (e.g. `core`).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// All the packages in the database, in archive order.
+    pub fn packages(&self) -> &[PackageRecord] {
+        &self.packages
+    }
+
+    /// Consumes the database, returning its packages.
+    pub fn into_packages(self) -> Vec<PackageRecord> {
+        self.packages
+    }
+
+    /// Gets a package by name.
+    pub fn package(&self, name: &str) -> Option<&PackageRecord> {
+        self.packages.iter().find(|pkg| pkg.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use tar::{Builder, Header};
+    use xz2::write::XzEncoder;
+    use zstd::stream::write::Encoder as ZstdEncoder;
+
+    use super::*;
+
+    fn add_file<W: Write>(builder: &mut Builder<W>, path: &str, contents: &str) {
+        let mut header = Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, contents.as_bytes()).unwrap();
+    }
+
+    fn build_db<W: Write>(writer: W) -> W {
+        let mut builder = Builder::new(writer);
+        add_file(&mut builder, "zlib-1:1.2.11-1/desc",
+                 "%FILENAME%\nzlib-1:1.2.11-1-x86_64.pkg.tar.xz\n\n%NAME%\nzlib\n\n\
+                  %VERSION%\n1:1.2.11-1\n\n%CSIZE%\n86380\n\n");
+        add_file(&mut builder, "zlib-1:1.2.11-1/depends", "%DEPENDS%\nglibc\n\n");
+        add_file(&mut builder, "glibc-2.26-1/desc", "%NAME%\nglibc\n\n%VERSION%\n2.26-1\n\n");
+        add_file(&mut builder, "zlib-1:1.2.11-1/files", "%FILES%\nusr/\nusr/lib/libz.so\n\n");
+        builder.into_inner().unwrap()
+    }
+
+    fn check(db: SyncDb) {
+        assert_eq!(db.name(), "core");
+        assert_eq!(db.packages().len(), 2);
+        let zlib = db.package("zlib").unwrap();
+        assert_eq!(zlib.version, "1:1.2.11-1");
+        assert_eq!(zlib.download_size, Some(86380));
+        assert_eq!(zlib.depends[0].name, "glibc");
+        assert_eq!(zlib.files.len(), 2);
+    }
+
+    #[test]
+    fn gzip() {
+        let data = build_db(GzEncoder::new(Vec::new(), Compression::default())).finish().unwrap();
+        check(SyncDb::from_reader("core", &data[..]).unwrap());
+    }
+
+    #[test]
+    fn xz() {
+        let data = build_db(XzEncoder::new(Vec::new(), 6)).finish().unwrap();
+        check(SyncDb::from_reader("core", &data[..]).unwrap());
+    }
+
+    #[test]
+    fn zstd() {
+        let data = build_db(ZstdEncoder::new(Vec::new(), 0).unwrap()).finish().unwrap();
+        check(SyncDb::from_reader("core", &data[..]).unwrap());
+    }
+}
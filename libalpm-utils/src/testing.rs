@@ -0,0 +1,67 @@
+//! Fixtures shared by the tests. The integration tests include this file with `#[path]`, so
+//! there is only one copy.
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use tar::{Builder, Header};
+
+/// An empty directory for a test, removed when dropped, so it's cleaned up even if the test
+/// fails.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates the directory, replacing any left over from an earlier run.
+    pub fn new(name: &str) -> TempDir {
+        let dir = env::temp_dir().join(format!("libalpm-utils-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Writes a minimal package file and returns its path.
+pub fn build_package(dir: &Path, name: &str, version: &str) -> PathBuf {
+    let pkginfo = format!("# Generated by makepkg\npkgname = {}\npkgver = {}\n\
+                           pkgdesc = A test package\nsize = 1024\narch = x86_64\n\
+                           depend = glibc>=2.26\nprovides = lib{}.so=1-64\n",
+                          name, version, name);
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for &(path, contents) in &[(".PKGINFO", pkginfo.as_str()), ("usr/bin/foo", "#!/bin/sh\n")] {
+        let mut header = Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, contents.as_bytes()).unwrap();
+    }
+    let data = builder.into_inner().unwrap().finish().unwrap();
+    let path = dir.join(format!("{}-{}-x86_64.pkg.tar.gz", name, version));
+    fs::File::create(&path).unwrap().write_all(&data).unwrap();
+    path
+}
@@ -0,0 +1,43 @@
+//! Checks that databases written by `RepoDb` can be synced by libalpm.
+
+extern crate flate2;
+extern crate libalpm;
+extern crate libalpm_utils;
+extern crate tar;
+
+#[path = "../src/testing.rs"]
+mod testing;
+
+use std::fs;
+
+use libalpm::{Alpm, SigLevel};
+use libalpm_utils::repo_db::RepoDb;
+
+use testing::{TempDir, build_package};
+
+#[test]
+fn round_trip() {
+    let base = TempDir::new("repo-round-trip");
+    let repo_dir = base.join("repo");
+    let db_path = base.join("var/lib/pacman");
+    fs::create_dir_all(&repo_dir).unwrap();
+    fs::create_dir_all(&db_path).unwrap();
+    let foo = build_package(&repo_dir, "foo", "1.0-1");
+
+    let mut repo = RepoDb::open(repo_dir.join("test.db.tar.gz")).unwrap();
+    repo.add_package(&foo).unwrap();
+    repo.write().unwrap();
+    let sha256 = repo.package("foo").unwrap().sha256sum.clone().unwrap();
+
+    let alpm = Alpm::new(base.to_str().unwrap(), db_path.to_str().unwrap()).unwrap();
+    let level = SigLevel { use_default: false, ..SigLevel::default() };
+    let db = alpm.register_sync_db("test", level).unwrap();
+    db.add_server(&format!("file://{}", repo_dir.display())).unwrap();
+    db.update(true).unwrap();
+    let pkg = db.pkg("foo").unwrap();
+    assert_eq!(pkg.version().as_ref(), "1.0-1");
+    assert_eq!(pkg.description(), "A test package");
+    assert_eq!(pkg.filename(), "foo-1.0-1-x86_64.pkg.tar.gz");
+    assert_eq!(pkg.sha256(), Some(sha256.as_str()));
+    assert_eq!(pkg.depends()[0].name(), "glibc");
+}
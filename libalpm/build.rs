@@ -0,0 +1,25 @@
+//! Passes the libalpm version that alpm-sys detected on as cfgs (`alpm_v12`, `alpm_v13` and
+//! `alpm_v14`, see alpm-sys's build script), so the wrapper can follow API changes.
+//!
+//! alpm-sys fails to build without a version, so it should always be set. If it isn't, this
+//! fails the same way rather than guessing an API that might not match the library.
+
+use std::env;
+
+fn main() {
+    let version = env::var("DEP_ALPM_VERSION").unwrap_or_else(|_| {
+        panic!("cannot find libalpm with pkg-config; install libalpm (pacman) or set \
+                ALPM_SYS_LIBALPM_VERSION to the version to bind (e.g. 13.0.1)")
+    });
+    let major: u32 = version
+        .split('.')
+        .next()
+        .and_then(|major| major.parse().ok())
+        .unwrap_or_else(|| panic!("cannot parse libalpm version {:?}", version));
+    for cfg in 12..15 {
+        println!("cargo:rustc-check-cfg=cfg(alpm_v{})", cfg);
+        if major >= cfg {
+            println!("cargo:rustc-cfg=alpm_v{}", cfg);
+        }
+    }
+}
@@ -6,30 +6,45 @@ use std::fs::File;
 use std::path::Path;
 use std::io::Write;
 
-use libalpm::{Alpm, DownloadResult};
+use libalpm::{Alpm, Downloader, DownloadStatus, DownloadError, DownloadProgress};
 use curl::easy::Easy;
 use url::Url;
 
-fn fetch(url: &str, loc: &str, force: bool) -> DownloadResult {
-    let url = url.parse::<Url>().unwrap();
-    let path = url.path_segments().unwrap().last().unwrap_or("".into());
-    let filename = match url.query() {
-        Some(q) => format!("{}?{}", path, q),
-        None => path.into()
-    };
-    let mut file = File::create(Path::new(&filename)).unwrap();
-    let mut easy = Easy::new();
-    easy.url(url.as_str()).unwrap();
-    easy.write_function(move |data| {
-        Ok(file.write(data).unwrap())
-    }).unwrap();
-    easy.perform().unwrap();
-    DownloadResult::Ok
+/// A minimal downloader (see `HttpDownloader` with the `http` feature for a complete one).
+struct Fetch;
+
+impl Downloader for Fetch {
+    fn download(&mut self, url: &Url, dest: &Path, _force: bool, progress: &mut DownloadProgress)
+        -> Result<DownloadStatus, DownloadError>
+    {
+        let path = url.path_segments().unwrap().last().unwrap_or("".into());
+        let filename = match url.query() {
+            Some(q) => format!("{}?{}", path, q),
+            None => path.into()
+        };
+        let mut file = File::create(dest.join(&filename))?;
+        let mut easy = Easy::new();
+        easy.url(url.as_str()).unwrap();
+        easy.progress(true).unwrap();
+        let mut transfer = easy.transfer();
+        transfer.write_function(|data| {
+            Ok(file.write(data).unwrap())
+        }).unwrap();
+        transfer.progress_function(|total, now, _, _| {
+            progress.update(now as u64, total as u64);
+            true
+        }).unwrap();
+        transfer.perform().map_err(|e| DownloadError::Transfer(e.to_string()))?;
+        Ok(DownloadStatus::Downloaded)
+    }
 }
 
 fn main() {
-    let mut alpm = Alpm::new("/", "/var/lib/pacman").unwrap();
-    unsafe { alpm.fetch_function(fetch); }
+    let alpm = Alpm::new("/", "/var/lib/pacman").unwrap();
+    alpm.set_downloader(Fetch);
     let dl_loc = alpm.fetch_pkgurl("http://archlinux.polymorf.fr/extra/os/x86_64/a2ps");
     println!("{:?}", dl_loc);
+    if let Some(e) = alpm.take_download_error() {
+        println!("{}", e);
+    }
 }
@@ -57,6 +57,7 @@ fn print_pkg_info(pkg: &PackageRef) {
     //println!("         make dependencies: {:?}", pkg.make_depends());
     println!("         conflicts: {:?}", pkg.conflicts());
     println!("         provides: {:?}", pkg.provides());
+    #[cfg(not(alpm_v12))]
     println!("         deltas: {:?}", pkg.deltas());
     println!("         replaces: {:?}", pkg.replaces());
     println!("         files: {:?}", pkg.files());
@@ -68,8 +69,16 @@ fn main() {
     println!("arch: {:?}", arch);
     let options = Config::default();
     let mut alpm = Alpm::new("./tmp", "./tmp/var/lib/pacman").unwrap();
-    alpm.set_arch(&arch).unwrap();
-    println!("arch: {:?}", alpm.arch());
+    #[cfg(not(alpm_v13))]
+    {
+        alpm.set_arch(&arch).unwrap();
+        println!("arch: {:?}", alpm.arch());
+    }
+    #[cfg(alpm_v13)]
+    {
+        alpm.add_architecture(&arch).unwrap();
+        println!("arch: {:?}", alpm.architectures());
+    }
     //panic!("bail");
     alpm.log_function(log);
     alpm.file_download_progress_function(download);
@@ -58,6 +58,7 @@ fn print_pkg_info(pkg: &PackageRef) {
     //println!("         make dependencies: {:?}", pkg.make_depends());
     println!("         conflicts: {:?}", pkg.conflicts());
     println!("         provides: {:?}", pkg.provides());
+    #[cfg(not(alpm_v12))]
     println!("         deltas: {:?}", pkg.deltas());
     println!("         replaces: {:?}", pkg.replaces());
     println!("         files: {:?}", pkg.files());
@@ -74,8 +75,16 @@ fn main() {
     println!("Creating Alpm instance with base \"{}\" and db \"{}\"", ALPM_BASE, ALPM_DB);
     let mut alpm = Alpm::new(ALPM_BASE, ALPM_DB).unwrap();
     alpm.log_function(log);
-    alpm.set_arch(&arch).unwrap();
-    println!("arch: {:?}", alpm.arch());
+    #[cfg(not(alpm_v13))]
+    {
+        alpm.set_arch(&arch).unwrap();
+        println!("arch: {:?}", alpm.arch());
+    }
+    #[cfg(alpm_v13)]
+    {
+        alpm.add_architecture(&arch).unwrap();
+        println!("arch: {:?}", alpm.architectures());
+    }
     //panic!("bail");
 
     for (name, repo) in options.repositories.iter() {
@@ -0,0 +1,446 @@
+//! Cleaning old packages out of the cache directories, like `paccache` and `pacman -Sc`.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use error::Error;
+use options::CleanMethod;
+
+/// A package file found in a cache directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedPackage {
+    pub path: PathBuf,
+    pub name: String,
+    /// The full version, `[epoch:]pkgver-pkgrel`.
+    pub version: String,
+    pub arch: String,
+    /// The detached signature next to the package, if there is one.
+    pub signature: Option<PathBuf>,
+    /// The size of the package and its signature, in bytes.
+    pub size: u64,
+}
+
+/// Which packages to keep when cleaning the cache.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeepPolicy {
+    /// Keep the given number of newest versions of each package (for each architecture), like
+    /// `paccache -rk`.
+    Newest(usize),
+    /// Keep the packages `CleanMethod` says to, like `pacman -Sc`.
+    Method(CleanMethod),
+}
+
+/// Options for `Alpm::clean_cache`.
+#[derive(Debug, Clone)]
+pub struct CleanOptions {
+    pub(crate) keep: KeepPolicy,
+    pub(crate) dry_run: bool,
+}
+
+impl Default for CleanOptions {
+    fn default() -> CleanOptions {
+        CleanOptions {
+            keep: KeepPolicy::Newest(3),
+            dry_run: false,
+        }
+    }
+}
+
+impl CleanOptions {
+    /// Creates the default options: keep the 3 newest versions (as `paccache` does), and remove
+    /// the rest.
+    pub fn new() -> CleanOptions {
+        CleanOptions::default()
+    }
+
+    /// Sets which packages to keep.
+    pub fn keep(mut self, keep: KeepPolicy) -> CleanOptions {
+        self.keep = keep;
+        self
+    }
+
+    /// Sets whether to only report what would be removed, without removing anything.
+    pub fn dry_run(mut self, dry_run: bool) -> CleanOptions {
+        self.dry_run = dry_run;
+        self
+    }
+}
+
+/// What `Alpm::clean_cache` removed (or would remove, for a dry run).
+#[derive(Debug, Default)]
+pub struct CleanReport {
+    /// The packages removed, with their signatures.
+    pub removed: Vec<CachedPackage>,
+    /// The number of packages kept.
+    pub kept: usize,
+    /// The bytes freed (or that would be freed).
+    pub freed: u64,
+    /// Files or directories that could not be read or removed.
+    pub failed: Vec<(PathBuf, io::Error)>,
+}
+
+/// Something wrong with a package file in the cache.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CacheProblem {
+    /// libalpm could not load the package (it may be truncated).
+    Unreadable(Error),
+    /// The package doesn't match the checksum in its sync database.
+    Checksum,
+    /// The detached signature is missing or invalid, for the siglevel of the sync database.
+    Signature(Error),
+}
+
+impl CacheProblem {
+    /// Gets the problem behind an error from `Alpm::load_package`.
+    pub(crate) fn from_load_error(e: Error) -> CacheProblem {
+        match e {
+            Error::PkgInvalidSig | Error::PkgMissingSig | Error::SigMissing | Error::SigInvalid => {
+                CacheProblem::Signature(e)
+            }
+            Error::PkgInvalidChecksum => CacheProblem::Checksum,
+            e => CacheProblem::Unreadable(e),
+        }
+    }
+}
+
+/// What `Alpm::audit_cache` found.
+#[derive(Debug, Default)]
+pub struct AuditReport {
+    /// Packages that load and match their sync database entry.
+    pub ok: Vec<PathBuf>,
+    /// Packages that are damaged, or whose signature doesn't verify.
+    pub corrupt: Vec<(PathBuf, CacheProblem)>,
+    /// Packages that load, but have no entry with the same file name in any sync database to be
+    /// checked against.
+    pub unknown: Vec<PathBuf>,
+    /// Files that aren't packages: signatures without their package, partial downloads and
+    /// anything else.
+    pub orphaned: Vec<PathBuf>,
+    /// Files or directories that could not be read.
+    pub failed: Vec<(PathBuf, io::Error)>,
+}
+
+impl AuditReport {
+    /// Whether nothing is wrong with the cache.
+    pub fn is_ok(&self) -> bool {
+        self.corrupt.is_empty() && self.orphaned.is_empty() && self.failed.is_empty()
+    }
+}
+
+/// Splits a package file name into its name, version and architecture, if it is one.
+///
+/// ```
+/// # use libalpm::parse_package_filename;
+/// assert_eq!(parse_package_filename("linux-4.11.3-1-x86_64.pkg.tar.xz"),
+///            Some(("linux", "4.11.3-1", "x86_64")));
+/// assert_eq!(parse_package_filename("linux-4.11.3-1-x86_64.pkg.tar.xz.sig"), None);
+/// ```
+pub fn parse_package_filename(filename: &str) -> Option<(&str, &str, &str)> {
+    // anything after .pkg.tar is the compression, if any
+    let end = filename.rfind(".pkg.tar")?;
+    let compression = &filename[end + ".pkg.tar".len()..];
+    let valid_compression = compression.is_empty()
+        || compression.starts_with('.') && !compression[1..].contains('.');
+    if !valid_compression {
+        return None;
+    }
+    let mut parts = filename[..end].rsplitn(4, '-');
+    let arch = parts.next()?;
+    let pkgrel = parts.next()?;
+    let pkgver = parts.next()?;
+    let name = parts.next()?;
+    if name.is_empty() || pkgver.is_empty() || pkgrel.is_empty() || arch.is_empty() {
+        return None;
+    }
+    let version_start = name.len() + 1;
+    Some((name, &filename[version_start..version_start + pkgver.len() + 1 + pkgrel.len()], arch))
+}
+
+/// Lists the package files in `dirs`, and the other files that aren't their signatures.
+/// Directories that don't exist are skipped, and those that can't be read are added to `failed`.
+pub(crate) fn scan<P: AsRef<Path>>(dirs: &[P], failed: &mut Vec<(PathBuf, io::Error)>)
+    -> (Vec<CachedPackage>, Vec<PathBuf>)
+{
+    let mut packages = Vec::new();
+    let mut others = Vec::new();
+    for dir in dirs {
+        let dir = dir.as_ref();
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                failed.push((dir.to_owned(), e));
+                continue;
+            }
+        };
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    failed.push((dir.to_owned(), e));
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let metadata = match fs::metadata(&path) {
+                Ok(ref metadata) if !metadata.is_file() => continue,
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    failed.push((path, e));
+                    continue;
+                }
+            };
+            let (name, version, arch) = match path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(parse_package_filename)
+            {
+                Some((name, version, arch)) => {
+                    (name.to_owned(), version.to_owned(), arch.to_owned())
+                }
+                None => {
+                    others.push(path);
+                    continue;
+                }
+            };
+            let mut signature = path.clone().into_os_string();
+            signature.push(".sig");
+            let signature = PathBuf::from(signature);
+            let signature_size = fs::metadata(&signature).ok()
+                .and_then(|metadata| if metadata.is_file() { Some(metadata.len()) } else { None });
+            packages.push(CachedPackage {
+                name: name,
+                version: version,
+                arch: arch,
+                signature: signature_size.map(|_| signature),
+                size: metadata.len() + signature_size.unwrap_or(0),
+                path: path,
+            });
+        }
+    }
+    packages.sort_by(|a, b| a.path.cmp(&b.path));
+    others.retain(|path| !packages.iter().any(|package| package.signature.as_ref() == Some(path)));
+    others.sort();
+    (packages, others)
+}
+
+/// Splits packages into those to keep and those to remove.
+///
+/// With `KeepPolicy::Newest`, versions are ordered with `vercmp`; with `KeepPolicy::Method`,
+/// `is_installed` and `is_current` say whether a package's version is in the local or a sync
+/// database.
+pub(crate) fn select<F, G>(packages: Vec<CachedPackage>,
+                           keep: KeepPolicy,
+                           vercmp: fn(&str, &str) -> Ordering,
+                           is_installed: F,
+                           is_current: G)
+                           -> (Vec<CachedPackage>, Vec<CachedPackage>)
+    where F: Fn(&CachedPackage) -> bool,
+          G: Fn(&CachedPackage) -> bool
+{
+    match keep {
+        KeepPolicy::Newest(n) => {
+            let mut groups: HashMap<(String, String), Vec<String>> = HashMap::new();
+            for package in &packages {
+                let versions = groups.entry((package.name.clone(), package.arch.clone()))
+                    .or_insert_with(Vec::new);
+                // the same version may be in more than one cache directory
+                if !versions.contains(&package.version) {
+                    versions.push(package.version.clone());
+                }
+            }
+            for versions in groups.values_mut() {
+                versions.sort_by(|a, b| vercmp(b, a));
+                versions.truncate(n);
+            }
+            packages.into_iter().partition(|package| {
+                groups[&(package.name.clone(), package.arch.clone())].contains(&package.version)
+            })
+        }
+        KeepPolicy::Method(method) => packages.into_iter().partition(|package| {
+            method.keep_installed && is_installed(package)
+                || method.keep_current && is_current(package)
+        }),
+    }
+}
+
+/// Removes packages (and their signatures), or only adds up their sizes for a dry run.
+pub(crate) fn remove(remove: Vec<CachedPackage>, kept: usize, dry_run: bool) -> CleanReport {
+    let mut report = CleanReport {
+        kept: kept,
+        ..CleanReport::default()
+    };
+    for package in remove {
+        if !dry_run {
+            if let Err(e) = fs::remove_file(&package.path) {
+                report.failed.push((package.path, e));
+                continue;
+            }
+            if let Some(ref signature) = package.signature {
+                if let Err(e) = fs::remove_file(signature) {
+                    report.failed.push((signature.clone(), e));
+                }
+            }
+        }
+        report.freed += package.size;
+        report.removed.push(package);
+    }
+    report
+}
+
+/// Sorts packages into those that are fine, corrupt or unknown.
+///
+/// `load` loads a package in full, verifying its signature. `checksum` then checks it against
+/// its sync database entry, returning `None` if it has none, or else whether it matches (an
+/// entry without a checksum matches anything).
+pub(crate) fn audit<F, G>(packages: Vec<CachedPackage>, load: F, checksum: G) -> AuditReport
+    where F: Fn(&CachedPackage) -> Result<(), Error>,
+          G: Fn(&CachedPackage) -> Option<bool>
+{
+    let mut report = AuditReport::default();
+    for package in packages {
+        match load(&package).map(|()| checksum(&package)) {
+            Err(e) => report.corrupt.push((package.path, CacheProblem::from_load_error(e))),
+            Ok(None) => report.unknown.push(package.path),
+            Ok(Some(false)) => report.corrupt.push((package.path, CacheProblem::Checksum)),
+            Ok(Some(true)) => report.ok.push(package.path),
+        }
+    }
+    report
+}
+
+#[test]
+fn test_parse_package_filename() {
+    assert_eq!(parse_package_filename("a-1.0-1-any.pkg.tar"), Some(("a", "1.0-1", "any")));
+    assert_eq!(parse_package_filename("lib32-gcc-libs-1:7.1.1-2-x86_64.pkg.tar.zst"),
+               Some(("lib32-gcc-libs", "1:7.1.1-2", "x86_64")));
+    assert_eq!(parse_package_filename("a-1.0-1-any.pkg.tar.xz.part"), None);
+    assert_eq!(parse_package_filename("1.0-1-any.pkg.tar.xz"), None);
+    assert_eq!(parse_package_filename("core.db"), None);
+}
+
+#[test]
+fn test_cache_problem() {
+    assert_eq!(CacheProblem::from_load_error(Error::PkgInvalidSig),
+               CacheProblem::Signature(Error::PkgInvalidSig));
+    assert_eq!(CacheProblem::from_load_error(Error::SigMissing),
+               CacheProblem::Signature(Error::SigMissing));
+    assert_eq!(CacheProblem::from_load_error(Error::PkgInvalidChecksum), CacheProblem::Checksum);
+    assert_eq!(CacheProblem::from_load_error(Error::PkgOpen),
+               CacheProblem::Unreadable(Error::PkgOpen));
+}
+
+#[test]
+fn test_audit() {
+    let package = |name: &str| CachedPackage {
+        path: PathBuf::from(format!("/cache/{}-1-1-any.pkg.tar.zst", name)),
+        name: name.to_owned(),
+        version: "1-1".to_owned(),
+        arch: "any".to_owned(),
+        signature: None,
+        size: 0,
+    };
+    let packages = ["ok", "truncated", "unsigned", "unknown", "modified"].iter()
+        .map(|name| package(name))
+        .collect();
+    let load = |package: &CachedPackage| match package.name.as_str() {
+        "truncated" => Err(Error::PkgOpen),
+        "unsigned" => Err(Error::PkgMissingSig),
+        _ => Ok(()),
+    };
+    let checksum = |package: &CachedPackage| match package.name.as_str() {
+        "unknown" => None,
+        "modified" => Some(false),
+        "truncated" | "unsigned" => panic!("checksum of a package that didn't load"),
+        _ => Some(true),
+    };
+    let report = audit(packages, load, checksum);
+    assert_eq!(report.ok, vec![package("ok").path]);
+    assert_eq!(report.corrupt, vec![
+        (package("truncated").path, CacheProblem::Unreadable(Error::PkgOpen)),
+        (package("unsigned").path, CacheProblem::Signature(Error::PkgMissingSig)),
+        (package("modified").path, CacheProblem::Checksum),
+    ]);
+    assert_eq!(report.unknown, vec![package("unknown").path]);
+    assert!(!report.is_ok());
+}
+
+#[test]
+fn test_clean() {
+    use std::fs::File;
+    use std::io::Write;
+    use download::temp_dir;
+
+    // stands in for vercmp, for versions like "1-1"
+    fn vercmp(a: &str, b: &str) -> Ordering {
+        let number = |v: &str| v.split('-').next().unwrap().parse::<u32>().unwrap();
+        number(a).cmp(&number(b))
+    }
+
+    let dirs = vec![temp_dir("cache-a"), temp_dir("cache-b")];
+    let files = [(0, "foo-1-1-x86_64.pkg.tar.xz"),
+                 (0, "foo-1-1-x86_64.pkg.tar.xz.sig"),
+                 (0, "foo-2-1-x86_64.pkg.tar.xz"),
+                 (1, "foo-2-1-x86_64.pkg.tar.xz"),
+                 (1, "foo-10-1-x86_64.pkg.tar.xz"),
+                 (0, "foo-1-1-i686.pkg.tar.xz"),
+                 (0, "bar-1-1-any.pkg.tar.zst"),
+                 (0, "notes.txt"),
+                 (1, "foo-11-1-x86_64.pkg.tar.xz.part"),
+                 (1, "gone-1-1-any.pkg.tar.xz.sig")];
+    for &(dir, file) in files.iter() {
+        File::create(dirs[dir].join(file)).unwrap().write_all(b"1234").unwrap();
+    }
+
+    let mut failed = Vec::new();
+    let (packages, others) = scan(&dirs, &mut failed);
+    assert!(failed.is_empty());
+    assert_eq!(packages.len(), 6);
+    // signatures without a package and partial downloads aren't packages either
+    assert_eq!(others, vec![dirs[0].join("notes.txt"),
+                            dirs[1].join("foo-11-1-x86_64.pkg.tar.xz.part"),
+                            dirs[1].join("gone-1-1-any.pkg.tar.xz.sig")]);
+    let name = |package: &CachedPackage| package.path.file_name().unwrap().to_str().unwrap()
+        .to_owned();
+
+    // the newest version of each name and architecture, wherever it is
+    let (kept, removed) = select(packages.clone(), KeepPolicy::Newest(2), vercmp,
+                                 |_| false, |_| false);
+    assert_eq!(kept.len(), 5);
+    assert_eq!(removed.iter().map(name).collect::<Vec<_>>(), vec!["foo-1-1-x86_64.pkg.tar.xz"]);
+    let (kept, removed) = select(packages.clone(), KeepPolicy::Newest(1), vercmp,
+                                 |_| false, |_| false);
+    assert_eq!(kept.iter().map(name).collect::<Vec<_>>(),
+               vec!["bar-1-1-any.pkg.tar.zst", "foo-1-1-i686.pkg.tar.xz",
+                    "foo-10-1-x86_64.pkg.tar.xz"]);
+    assert_eq!(removed.len(), 3);
+
+    // a dry run only reports the space, including the signature
+    let report = remove(removed.clone(), kept.len(), true);
+    assert_eq!(report.removed.len(), 3);
+    assert_eq!(report.freed, 16);
+    assert!(dirs[0].join("foo-1-1-x86_64.pkg.tar.xz.sig").exists());
+
+    // installed is foo 2, current is bar 1
+    let method = KeepPolicy::Method(CleanMethod::keep_installed());
+    let installed = |package: &CachedPackage| package.name == "foo" && package.version == "2-1";
+    let current = |package: &CachedPackage| package.name == "bar";
+    let (kept, _) = select(packages.clone(), method, vercmp, installed, current);
+    assert_eq!(kept.len(), 2);
+    let method = KeepPolicy::Method(CleanMethod { keep_installed: true, keep_current: true });
+    let (kept, removed) = select(packages, method, vercmp, installed, current);
+    assert_eq!(kept.len(), 3);
+
+    let report = remove(removed, kept.len(), false);
+    assert!(report.failed.is_empty());
+    assert_eq!(report.freed, 16);
+    assert!(!dirs[0].join("foo-1-1-x86_64.pkg.tar.xz").exists());
+    assert!(!dirs[0].join("foo-1-1-x86_64.pkg.tar.xz.sig").exists());
+    assert!(dirs[0].join("foo-2-1-x86_64.pkg.tar.xz").exists());
+    assert!(dirs[0].join("notes.txt").exists());
+    for dir in dirs.iter() {
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,93 +1,236 @@
 use std::panic;
 use std::ffi::CStr;
 
-use printf::printf;
+use printf::{printf, decode_args};
 use alpm_sys::*;
-use libc::{c_int, c_char, c_void, off_t};
+use libc::{c_int, c_char, c_void};
+#[cfg(not(alpm_v13))]
+use libc::off_t;
 
-use {LOG_CB, DOWNLOAD_CB, DLTOTAL_CB, FETCH_CB, EVENT_CB, DownloadResult};
+use {LOG_CB, LOG_RECORD_CB, DOWNLOAD_CB, DLTOTAL_CB, EVENT_CB};
+use download;
 use event::Event;
+use log::LogRecord;
+use subscription::{self, Record};
+use util::lock;
+
+/// Gets the handle a callback is called for. It is passed as the context since libalpm 13;
+/// before that there is no way to tell.
+#[cfg(alpm_v13)]
+macro_rules! handle {
+    ($ctx:ident) => { Some($ctx as usize) }
+}
+#[cfg(not(alpm_v13))]
+macro_rules! handle {
+    ($ctx:ident) => { None }
+}
 
 /// Function with C calling convention and required type signature to wrap our callback
-pub unsafe extern "C" fn alpm_cb_log(level: alpm_loglevel_t,
-                                 fmt: *const c_char,
-                                 args: *const Struct_va_list) {
+pub unsafe extern "C" fn alpm_cb_log(#[cfg(alpm_v13)] ctx: *mut c_void,
+                                     level: alpm_loglevel_t,
+                                     fmt: *const c_char,
+                                     args: *const Struct_va_list) {
     let out = printf(fmt, args as *mut c_void);
+    // a panic publishing mustn't stop the callback, so each gets its own catch_unwind
     panic::catch_unwind(|| {
-        let mut cb = LOG_CB.lock().unwrap();
+        subscription::publish(handle!(ctx), || Record::Log(LogRecord {
+            level: level.into(),
+            format: CStr::from_ptr(fmt).to_string_lossy().into_owned(),
+            args: decode_args(fmt, args as *mut c_void),
+        }));
+    }).unwrap_or(());
+    panic::catch_unwind(|| {
+        let mut cb = lock(&LOG_CB);
         if let Some(ref mut cb) = *cb {
             cb(level.into(), out);
         }
     }).unwrap_or(()) // ignore all errors since we are about to cross ffi boundary
 }
 
+/// Like `alpm_cb_log`, but passes the format string and arguments on without formatting them
+pub unsafe extern "C" fn alpm_cb_log_record(#[cfg(alpm_v13)] ctx: *mut c_void,
+                                            level: alpm_loglevel_t,
+                                            fmt: *const c_char,
+                                            args: *const Struct_va_list) {
+    let record = panic::catch_unwind(|| LogRecord {
+        level: level.into(),
+        format: CStr::from_ptr(fmt).to_string_lossy().into_owned(),
+        args: decode_args(fmt, args as *mut c_void),
+    });
+    let record = match record {
+        Ok(record) => record,
+        Err(_) => return, // nothing to pass on, and we are about to cross ffi boundary
+    };
+    panic::catch_unwind(|| {
+        subscription::publish(handle!(ctx), || Record::Log(record.clone()));
+    }).unwrap_or(());
+    panic::catch_unwind(|| {
+        let mut cb = lock(&LOG_RECORD_CB);
+        if let Some(ref mut cb) = *cb {
+            cb(record);
+        }
+    }).unwrap_or(()) // ignore all errors since we are about to cross ffi boundary
+}
+
 /** Type of download progress callbacks.
  * @param filename the name of the file being downloaded
  * @param xfered the number of transferred bytes
  * @param total the total number of bytes to transfer
  */
+#[cfg(not(alpm_v13))]
 pub unsafe extern "C" fn alpm_cb_download(filename: *const c_char, xfered: off_t, total: off_t) {
     let filename = CStr::from_ptr(filename).to_string_lossy();
-    let xfered = xfered as u64;
-    let total = total as u64;
+    report_download(None, filename.as_ref(), xfered as u64, total as u64);
+}
+
+/** Download event callback (libalpm 13).
+ * Progress events are passed on as with the older callback, and completion only to subscribers.
+ * @param filename the name of the file being downloaded
+ * @param event the kind of download event
+ * @param data the event-specific data
+ */
+#[cfg(alpm_v13)]
+pub unsafe extern "C" fn alpm_cb_download(ctx: *mut c_void,
+                                          filename: *const c_char,
+                                          event: alpm_download_event_type_t,
+                                          data: *mut c_void) {
+    if data.is_null() {
+        return;
+    }
+    let filename = CStr::from_ptr(filename).to_string_lossy();
+    if event == ALPM_DOWNLOAD_PROGRESS {
+        let progress = &*(data as *const alpm_download_event_progress_t);
+        report_download(handle!(ctx),
+                        filename.as_ref(),
+                        progress.downloaded as u64,
+                        progress.total as u64);
+    } else if event == ALPM_DOWNLOAD_COMPLETED {
+        // the result is 1 if the file was up to date, and -1 on failure
+        let completed = &*(data as *const alpm_download_event_completed_t);
+        report_download_done(handle!(ctx), filename.as_ref(), completed.result >= 0);
+    }
+}
+
+/// Passes download progress for `handle` (if known) to subscribers and the download progress
+/// callback. This is also used for downloads done by a `Downloader`.
+pub(crate) fn report_download(handle: Option<usize>, filename: &str, xfered: u64, total: u64) {
     panic::catch_unwind(|| {
-        let mut cb = DOWNLOAD_CB.lock().unwrap();
+        subscription::publish(handle, || Record::Download {
+            filename: filename.to_owned(),
+            downloaded: xfered,
+            total: total,
+        });
+    }).unwrap_or(());
+    panic::catch_unwind(|| {
+        let mut cb = lock(&DOWNLOAD_CB);
         if let Some(ref mut cb) = *cb {
-            cb(filename.as_ref(), xfered, total);
+            cb(filename, xfered, total);
         }
     }).unwrap_or(()) // ignore all errors since we are about to cross ffi boundary
 }
 
+/// Tells subscribers to `handle` (if known) that a file has been downloaded, or could not be.
+/// This is also used for downloads done by a `Downloader`.
+pub(crate) fn report_download_done(handle: Option<usize>, filename: &str, success: bool) {
+    panic::catch_unwind(|| {
+        subscription::publish(handle, || Record::DownloadDone {
+            filename: filename.to_owned(),
+            success: success,
+        });
+    }).unwrap_or(()) // ignore all errors since we are about to cross ffi boundary
+}
+
 /** Type of download progress callbacks.
  * @param filename the name of the file being downloaded
  * @param xfered the number of transferred bytes
  * @param total the total number of bytes to transfer
  */
+#[cfg(not(alpm_v13))]
 pub unsafe extern "C" fn alpm_cb_totaldl(total: off_t) {
-    let total = total as u64;
+    report_total_download(None, total as u64);
+}
+
+/// Passes the total download size for `handle` (if known) to subscribers and the total download
+/// callback.
+fn report_total_download(handle: Option<usize>, total: u64) {
     panic::catch_unwind(|| {
-        let mut cb = DLTOTAL_CB.lock().unwrap();
+        subscription::publish(handle, || Record::TotalDownload(total));
+    }).unwrap_or(());
+    panic::catch_unwind(|| {
+        let mut cb = lock(&DLTOTAL_CB);
         if let Some(ref mut cb) = *cb {
             cb(total);
         }
     }).unwrap_or(()) // ignore all errors since we are about to cross ffi boundary
 }
 
-/** A callback for downloading files
+/** A callback for downloading files, which hands them to the handle's `Downloader`
  * @param url the URL of the file to be downloaded
  * @param localpath the directory to which the file should be downloaded
  * @param force whether to force an update, even if the file is the same
  * @return 0 on success, 1 if the file exists and is identical, -1 on
  * error.
  */
-pub unsafe extern "C" fn alpm_cb_fetch(url: *const c_char,
+pub unsafe extern "C" fn alpm_cb_fetch(#[cfg(alpm_v13)] ctx: *mut c_void,
+                                       url: *const c_char,
                                        localpath: *const c_char,
                                        force: c_int) -> c_int
 {
     let url = CStr::from_ptr(url).to_string_lossy();
     let localpath = CStr::from_ptr(localpath).to_string_lossy();
-    let force = ! force == 0;
+    let force = force != 0;
     panic::catch_unwind(|| {
-        let mut cb = FETCH_CB.lock().unwrap();
-        if let Some(ref mut cb) = *cb {
-            match cb(url.as_ref(), localpath.as_ref(), force) {
-                DownloadResult::Ok => 0,
-                DownloadResult::NotNeeded => 1,
-                DownloadResult::Err => -1,
-            }
-        } else {
-            -1
-        }
+        download::fetch(handle!(ctx), url.as_ref(), localpath.as_ref(), force)
     }).unwrap_or(-1) // set error code if we have panicked
 }
 
 /** Event callback */
-pub unsafe extern "C" fn alpm_cb_event(evt: *const alpm_event_t) {
+pub unsafe extern "C" fn alpm_cb_event(#[cfg(alpm_v13)] ctx: *mut c_void,
+                                       evt: *const alpm_event_t) {
+    // since libalpm 13 the total download size is announced by an event
+    #[cfg(alpm_v13)]
+    {
+        if (*evt).type_ == ALPM_EVENT_PKG_RETRIEVE_START {
+            report_total_download(handle!(ctx), (*evt).pkg_retrieve.total_size as u64);
+        }
+    }
     let evt = Event::new(evt);
     panic::catch_unwind(|| {
-        let mut cb = EVENT_CB.lock().unwrap();
+        subscription::publish(handle!(ctx), || Record::Event((&evt).into()));
+    }).unwrap_or(());
+    panic::catch_unwind(|| {
+        let mut cb = lock(&EVENT_CB);
         if let Some(ref mut cb) = *cb {
             cb(evt);
         }
     }).unwrap_or(())
 }
+
+/** Progress callback, only used for subscriptions.
+ * @param progress the kind of step
+ * @param pkgname the package being processed
+ * @param percent the progress through this package
+ * @param howmany the number of packages the step will process
+ * @param current the position of this package among them
+ */
+pub unsafe extern "C" fn alpm_cb_progress(#[cfg(alpm_v13)] ctx: *mut c_void,
+                                          progress: alpm_progress_t,
+                                          pkgname: *const c_char,
+                                          percent: c_int,
+                                          howmany: usize,
+                                          current: usize) {
+    let package = if pkgname.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(pkgname).to_string_lossy().into_owned()
+    };
+    panic::catch_unwind(|| {
+        subscription::publish(handle!(ctx), || Record::Progress {
+            kind: progress.into(),
+            package: package,
+            percent: percent as u8,
+            howmany: howmany,
+            current: current,
+        });
+    }).unwrap_or(())
+}
@@ -1,10 +1,14 @@
 use std::ffi::{CStr, CString};
 use std::str::Utf8Error;
 use std::mem;
+#[cfg(alpm_v13)]
+use std::ptr;
 
 use alpm_sys::*;
 use pgp::SigLevel;
 use libc::{self, c_char};
+#[cfg(alpm_v13)]
+use libc::c_void;
 
 use {Alpm, AlpmResult, Error, PackageRef, Group};
 use util::{self, alpm_list_to_vec, vec_to_alpm_list, str_to_unowned_char_array,
@@ -125,7 +129,17 @@ impl<'a> Db<'a> {
     /// successfully without doing anything.
     pub fn update(&self, force: bool) -> AlpmResult<()> {
         let force = if force { 1 } else { 0 };
-        if unsafe { alpm_db_update(force, self.inner) } == 0 {
+        #[cfg(not(alpm_v13))]
+        let res = unsafe { alpm_db_update(force, self.inner) };
+        // since libalpm 13 databases are updated in batches, so pass a batch of one
+        #[cfg(alpm_v13)]
+        let res = unsafe {
+            let dbs = alpm_list_add(ptr::null(), self.inner as *const c_void);
+            let res = alpm_db_update(self.handle.handle, dbs, force);
+            alpm_list_free(dbs);
+            res
+        };
+        if res == 0 {
             Ok(())
         } else {
             if let Some(err) = self.handle.error() {
@@ -0,0 +1,926 @@
+//! Fetching files for libalpm with a pluggable `Downloader`, in place of its built-in one.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use libc::c_int;
+use url::Url;
+
+use callbacks::{report_download, report_download_done};
+use util;
+use mirror::{Mirrors, mirror_of};
+
+lazy_static! {
+    static ref DOWNLOADERS: Mutex<Vec<Registration>> = Default::default();
+}
+
+/// A downloader set on a handle, with the last error it reported.
+struct Registration {
+    handle: usize,
+    downloader: Arc<Mutex<Box<Downloader>>>,
+    error: Option<DownloadError>,
+}
+
+/// Fetches files for libalpm.
+///
+/// Set with `Alpm::set_downloader`. Two implementations are included: `LocalDownloader` for
+/// `file://` urls and local mirrors, and `HttpDownloader` (with the `http` feature).
+pub trait Downloader: Send {
+    /// Downloads `url` into the directory `dest`, keeping the file name from the url.
+    ///
+    /// Unless `force` is set, a file already in `dest` that is the same as the source should not
+    /// be downloaded again, and `DownloadStatus::UpToDate` returned instead.
+    fn download(&mut self,
+                url: &Url,
+                dest: &Path,
+                force: bool,
+                progress: &mut DownloadProgress)
+                -> Result<DownloadStatus, DownloadError>;
+
+    /// Sets when downloads must be finished by, or `None` for no limit. `RetryingDownloader`
+    /// uses this to keep to its policy's timeout. The default ignores it, for downloaders that
+    /// can't stop a download early.
+    fn set_deadline(&mut self, _deadline: Option<Instant>) {}
+}
+
+/// The outcome of a successful download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadStatus {
+    /// The file was downloaded.
+    Downloaded,
+    /// The file was already there, so was not downloaded again.
+    UpToDate,
+}
+
+/// Why a download failed.
+#[derive(Debug)]
+pub enum DownloadError {
+    /// The url could not be parsed, or does not name a file.
+    InvalidUrl(String),
+    /// The downloader cannot fetch urls with this scheme.
+    UnsupportedScheme(String),
+    /// The file does not exist at the source.
+    NotFound(String),
+    /// The server responded with this (error) status code.
+    Http(u32),
+    /// The transfer failed, e.g. the connection was lost.
+    Transfer(String),
+    /// The downloaded file does not have the expected checksum.
+    Checksum(String),
+    /// Reading or writing a local file failed.
+    Io(io::Error),
+}
+
+impl StdError for DownloadError {
+    fn description(&self) -> &str {
+        match *self {
+            DownloadError::InvalidUrl(_) => "invalid url",
+            DownloadError::UnsupportedScheme(_) => "unsupported url scheme",
+            DownloadError::NotFound(_) => "file not found",
+            DownloadError::Http(_) => "server error",
+            DownloadError::Transfer(_) => "transfer failed",
+            DownloadError::Checksum(_) => "checksum mismatch",
+            DownloadError::Io(ref e) => e.description(),
+        }
+    }
+
+    fn cause(&self) -> Option<&StdError> {
+        match *self {
+            DownloadError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DownloadError::InvalidUrl(ref url) => write!(f, "invalid url {}", url),
+            DownloadError::UnsupportedScheme(ref scheme) => {
+                write!(f, "cannot download {} urls", scheme)
+            }
+            DownloadError::NotFound(ref name) => write!(f, "{} not found", name),
+            DownloadError::Http(code) => write!(f, "server responded with status {}", code),
+            DownloadError::Transfer(ref msg) => write!(f, "transfer failed: {}", msg),
+            DownloadError::Checksum(ref name) => write!(f, "checksum mismatch for {}", name),
+            DownloadError::Io(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<io::Error> for DownloadError {
+    fn from(e: io::Error) -> DownloadError {
+        DownloadError::Io(e)
+    }
+}
+
+/// Passes on how a download is going, in the same way as libalpm's own downloads (to the file
+/// download progress callback and subscribers).
+pub struct DownloadProgress<'a> {
+    handle: Option<usize>,
+    filename: &'a str,
+}
+
+impl<'a> DownloadProgress<'a> {
+    pub(crate) fn new(handle: Option<usize>, filename: &'a str) -> DownloadProgress<'a> {
+        DownloadProgress { handle: handle, filename: filename }
+    }
+
+    /// The name of the file being downloaded.
+    pub fn filename(&self) -> &str {
+        self.filename
+    }
+
+    /// Reports that `downloaded` bytes out of `total` have been downloaded (`total` is 0 if not
+    /// known).
+    pub fn update(&mut self, downloaded: u64, total: u64) {
+        report_download(self.handle, self.filename, downloaded, total);
+    }
+}
+
+/// Gets the name of the file a url points to.
+fn file_name(url: &Url) -> Result<&str, DownloadError> {
+    url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|name| if name.is_empty() { None } else { Some(name) })
+        .ok_or_else(|| DownloadError::InvalidUrl(url.as_str().to_owned()))
+}
+
+/// Gets the path a file is downloaded to until it is complete.
+pub(crate) fn part_path(target: &Path) -> PathBuf {
+    let mut part = target.as_os_str().to_owned();
+    part.push(".part");
+    PathBuf::from(part)
+}
+
+/// Fetches `file://` urls, or serves every url from a local directory by file name.
+///
+/// The second is useful for hosts without network access, where the packages are on a mounted
+/// share with the same layout as a mirror.
+///
+/// Files are copied to `<name>.part` and renamed once complete. A url pointing at the target
+/// itself counts as up to date.
+#[derive(Debug, Clone, Default)]
+pub struct LocalDownloader {
+    dir: Option<PathBuf>,
+}
+
+impl LocalDownloader {
+    /// Creates a downloader for `file://` urls.
+    pub fn new() -> LocalDownloader {
+        LocalDownloader { dir: None }
+    }
+
+    /// Creates a downloader that takes files from `dir`, whatever the url.
+    pub fn with_dir<P: Into<PathBuf>>(dir: P) -> LocalDownloader {
+        LocalDownloader { dir: Some(dir.into()) }
+    }
+
+    /// Gets the path of the file to copy.
+    fn source(&self, url: &Url) -> Result<PathBuf, DownloadError> {
+        match self.dir {
+            Some(ref dir) => Ok(dir.join(file_name(url)?)),
+            None if url.scheme() == "file" => {
+                url.to_file_path().map_err(|_| DownloadError::InvalidUrl(url.as_str().to_owned()))
+            }
+            None => Err(DownloadError::UnsupportedScheme(url.scheme().to_owned())),
+        }
+    }
+}
+
+impl Downloader for LocalDownloader {
+    fn download(&mut self,
+                url: &Url,
+                dest: &Path,
+                force: bool,
+                progress: &mut DownloadProgress)
+                -> Result<DownloadStatus, DownloadError>
+    {
+        let source = self.source(url)?;
+        let target = dest.join(file_name(url)?);
+        let metadata = fs::metadata(&source).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => DownloadError::NotFound(source.display().to_string()),
+            _ => DownloadError::Io(e),
+        })?;
+        // copying a file onto itself would empty it, and it's already there anyway
+        if fs::canonicalize(&target).ok() == Some(fs::canonicalize(&source)?) {
+            return Ok(DownloadStatus::UpToDate);
+        }
+        // a copy made earlier is newer than the source
+        if !force {
+            if let Ok(existing) = fs::metadata(&target) {
+                if existing.len() == metadata.len()
+                    && existing.modified()? >= metadata.modified()? {
+                    return Ok(DownloadStatus::UpToDate);
+                }
+            }
+        }
+
+        // copy next to the target and rename, so a failed copy doesn't leave a broken file
+        let total = metadata.len();
+        let part = part_path(&target);
+        let mut copy = || -> io::Result<()> {
+            let mut from = File::open(&source)?;
+            let mut to = File::create(&part)?;
+            let mut buf = [0; 64 * 1024];
+            let mut copied = 0;
+            progress.update(0, total);
+            loop {
+                let n = from.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                to.write_all(&buf[..n])?;
+                copied += n as u64;
+                progress.update(copied, total);
+            }
+            fs::rename(&part, &target)
+        };
+        copy().map_err(|e| {
+            let _ = fs::remove_file(&part);
+            DownloadError::Io(e)
+        })?;
+        Ok(DownloadStatus::Downloaded)
+    }
+}
+
+/// Fetches `http://`, `https://` and `ftp://` urls using curl.
+///
+/// Files are written to `<name>.part` and renamed once complete. If a transfer is interrupted,
+/// the `.part` file is kept, and the next download of the same file resumes it with a range
+/// request (starting again if the server doesn't support them).
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+pub struct HttpDownloader {
+    user_agent: Option<String>,
+    connect_timeout: ::std::time::Duration,
+    timeout: Option<::std::time::Duration>,
+    deadline: Option<Instant>,
+}
+
+#[cfg(feature = "http")]
+impl Default for HttpDownloader {
+    fn default() -> HttpDownloader {
+        HttpDownloader {
+            user_agent: None,
+            connect_timeout: ::std::time::Duration::from_secs(10),
+            timeout: None,
+            deadline: None,
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+impl HttpDownloader {
+    /// Creates a downloader with curl's user agent and a 10 second connect timeout.
+    pub fn new() -> HttpDownloader {
+        HttpDownloader::default()
+    }
+
+    /// Sets the user agent sent to servers.
+    pub fn user_agent<S: Into<String>>(mut self, user_agent: S) -> HttpDownloader {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Sets how long to wait for a server to accept the connection.
+    pub fn connect_timeout(mut self, timeout: ::std::time::Duration) -> HttpDownloader {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Sets how long a whole download may take before it is abandoned.
+    pub fn timeout(mut self, timeout: ::std::time::Duration) -> HttpDownloader {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+#[cfg(feature = "http")]
+impl From<::curl::Error> for DownloadError {
+    fn from(e: ::curl::Error) -> DownloadError {
+        DownloadError::Transfer(e.to_string())
+    }
+}
+
+#[cfg(feature = "http")]
+impl Downloader for HttpDownloader {
+    fn download(&mut self,
+                url: &Url,
+                dest: &Path,
+                force: bool,
+                progress: &mut DownloadProgress)
+                -> Result<DownloadStatus, DownloadError>
+    {
+        use std::cell::Cell;
+        use std::fs::OpenOptions;
+        use std::time::UNIX_EPOCH;
+        use curl::easy::{Easy, TimeCondition};
+
+        match url.scheme() {
+            "http" | "https" | "ftp" => (),
+            scheme => return Err(DownloadError::UnsupportedScheme(scheme.to_owned())),
+        }
+        let target = dest.join(file_name(url)?);
+        let part = part_path(&target);
+        let resume_from = fs::metadata(&part).map(|metadata| metadata.len()).unwrap_or(0);
+
+        let mut easy = Easy::new();
+        easy.url(url.as_str())?;
+        easy.follow_location(true)?;
+        easy.progress(true)?;
+        easy.connect_timeout(self.connect_timeout)?;
+        // a deadline can only shorten the timeout
+        let timeout = match self.deadline {
+            Some(deadline) => {
+                let now = Instant::now();
+                if deadline <= now {
+                    return Err(DownloadError::Transfer("timed out".into()));
+                }
+                Some(self.timeout.map_or(deadline - now, |timeout| {
+                    ::std::cmp::min(timeout, deadline - now)
+                }))
+            }
+            None => self.timeout,
+        };
+        if let Some(timeout) = timeout {
+            easy.timeout(timeout)?;
+        }
+        if let Some(ref user_agent) = self.user_agent {
+            easy.useragent(user_agent)?;
+        }
+        if resume_from > 0 {
+            easy.resume_from(resume_from)?;
+        } else if !force {
+            // only download again if the server has a newer file
+            let modified = fs::metadata(&target).and_then(|metadata| metadata.modified()).ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok());
+            if let Some(modified) = modified {
+                easy.time_condition(TimeCondition::IfModifiedSince)?;
+                easy.time_value(modified.as_secs() as i64)?;
+            }
+        }
+
+        // whether the server is sending the rest of the file, rather than all of it
+        let partial = Cell::new(false);
+        let mut file = None;
+        let mut write_error = None;
+        let result = {
+            let mut transfer = easy.transfer();
+            transfer.header_function(|header| {
+                // there is a status line for every response when following redirects
+                if header.starts_with(b"HTTP/") {
+                    partial.set(header.split(|&b| b == b' ').nth(1) == Some(&b"206"[..]));
+                }
+                true
+            })?;
+            transfer.write_function(|data| {
+                // only open the file once there is something to write
+                if file.is_none() {
+                    let opened = if partial.get() {
+                        OpenOptions::new().append(true).open(&part)
+                    } else {
+                        File::create(&part)
+                    };
+                    match opened {
+                        Ok(f) => file = Some(f),
+                        Err(e) => {
+                            write_error = Some(e);
+                            return Ok(0);
+                        }
+                    }
+                }
+                match file.as_mut().unwrap().write_all(data) {
+                    Ok(()) => Ok(data.len()),
+                    Err(e) => {
+                        write_error = Some(e);
+                        Ok(0)
+                    }
+                }
+            })?;
+            transfer.progress_function(|total, now, _, _| {
+                let offset = if partial.get() { resume_from } else { 0 };
+                let total = if total > 0.0 { total as u64 + offset } else { 0 };
+                progress.update(now as u64 + offset, total);
+                true
+            })?;
+            transfer.perform()
+        };
+        let code = easy.response_code()?;
+        let unresumable = resume_from > 0
+            && (code == 416 || result.as_ref().err().map_or(false, |e| e.is_range_error()));
+        if unresumable {
+            // the partial file can't be resumed, so start again
+            fs::remove_file(&part)?;
+            return self.download(url, dest, force, progress);
+        }
+        let wrote = file.is_some();
+        drop(file);
+        let status = match (write_error, result) {
+            (Some(e), _) => Err(DownloadError::Io(e)),
+            (None, Err(e)) => Err(DownloadError::from(e)),
+            (None, Ok(())) if code == 304 || easy.time_condition_unmet()? => {
+                Ok(DownloadStatus::UpToDate)
+            }
+            (None, Ok(())) if code == 404 => Err(DownloadError::NotFound(url.as_str().to_owned())),
+            (None, Ok(())) if code >= 400 => Err(DownloadError::Http(code)),
+            (None, Ok(())) => {
+                fs::rename(&part, &target)?;
+                Ok(DownloadStatus::Downloaded)
+            }
+        };
+        // keep what was received of an interrupted transfer so it can be resumed, but not an
+        // error page
+        match status {
+            Err(DownloadError::Transfer(_)) => (),
+            Err(_) if wrote => {
+                let _ = fs::remove_file(&part);
+            }
+            _ => (),
+        }
+        status
+    }
+
+    fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+}
+
+/// Options for downloading the packages of a transaction with
+/// `Transaction::download_packages`.
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    parallel: usize,
+    mirrors: Option<Mirrors>,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> DownloadOptions {
+        DownloadOptions {
+            parallel: 5,
+            mirrors: None,
+        }
+    }
+}
+
+impl DownloadOptions {
+    /// Creates the default options: 5 downloads at once.
+    pub fn new() -> DownloadOptions {
+        DownloadOptions::default()
+    }
+
+    /// Sets the number of packages downloaded at once (at least 1).
+    pub fn parallel(mut self, parallel: usize) -> DownloadOptions {
+        self.parallel = ::std::cmp::max(parallel, 1);
+        self
+    }
+
+    /// Tries servers in the order of their statistics in `mirrors`, so that demoted mirrors are
+    /// tried last, and counts packages that fail verification against the mirror they came from.
+    /// Use the same `Mirrors` with a `RetryingDownloader` to also retry failed downloads.
+    pub fn mirrors(mut self, mirrors: Mirrors) -> DownloadOptions {
+        self.mirrors = Some(mirrors);
+        self
+    }
+}
+
+/// What happened to each package in `Transaction::download_packages`, by file name.
+#[derive(Debug, Default)]
+pub struct DownloadReport {
+    /// Packages that were downloaded and verified.
+    pub downloaded: Vec<String>,
+    /// Packages that were already in a cache directory.
+    pub cached: Vec<String>,
+    /// Packages that could not be downloaded or verified from any server, with the last error.
+    pub failed: Vec<(String, DownloadError)>,
+}
+
+impl DownloadReport {
+    /// Whether every package is now in the cache.
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// A package to download, with the urls it can be downloaded from in order of preference.
+pub(crate) struct Job {
+    pub(crate) filename: String,
+    pub(crate) urls: Vec<String>,
+    pub(crate) checksum: Option<Checksum>,
+}
+
+/// The checksum a package is verified with, from its sync database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Checksum {
+    Sha256(String),
+    Md5(String),
+}
+
+impl Checksum {
+    /// Gets the strongest checksum available, if any.
+    pub(crate) fn new(sha256: Option<&str>, md5: Option<&str>) -> Option<Checksum> {
+        sha256.map(|sha256| Checksum::Sha256(sha256.to_owned()))
+            .or_else(|| md5.map(|md5| Checksum::Md5(md5.to_owned())))
+    }
+
+    /// Whether the file at `path` has this checksum.
+    pub(crate) fn matches(&self, path: &Path) -> bool {
+        let (actual, expected) = match *self {
+            Checksum::Sha256(ref expected) => (util::compute_sha256(path), expected),
+            Checksum::Md5(ref expected) => (util::compute_md5(path), expected),
+        };
+        actual.map_or(false, |actual| actual == *expected)
+    }
+}
+
+/// Downloads `jobs` into `dir` using clones of `downloader`, several at once. Each file is
+/// checked against its checksum using `verify`, and removed if it doesn't match. Progress is
+/// reported to the subscribers of `handle`, or all of them if it's `None`.
+pub(crate) fn download_parallel<D>(handle: Option<usize>,
+                                   jobs: Vec<Job>,
+                                   dir: &Path,
+                                   downloader: &D,
+                                   options: &DownloadOptions,
+                                   verify: fn(&Checksum, &Path) -> bool)
+                                   -> DownloadReport
+    where D: Downloader + Clone + 'static
+{
+    let workers = ::std::cmp::min(options.parallel, jobs.len());
+    let queue = Arc::new(Mutex::new(jobs.into_iter()));
+    let report = Arc::new(Mutex::new(DownloadReport::default()));
+    let threads: Vec<_> = (0..workers).map(|_| {
+        let queue = queue.clone();
+        let report = report.clone();
+        let dir = dir.to_owned();
+        let mut downloader = downloader.clone();
+        let mirrors = options.mirrors.clone();
+        thread::spawn(move || loop {
+            let job = match queue.lock().unwrap().next() {
+                Some(job) => job,
+                None => break,
+            };
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                download_job(handle, &mut downloader, &job, &dir, mirrors.as_ref(), verify)
+            })).unwrap_or_else(|_| Err(DownloadError::Transfer("downloader panicked".into())));
+            report_download_done(handle, &job.filename, result.is_ok());
+            let mut report = report.lock().unwrap();
+            match result {
+                Ok(()) => report.downloaded.push(job.filename),
+                Err(e) => report.failed.push((job.filename, e)),
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    let mut report = match Arc::try_unwrap(report) {
+        Ok(report) => report.into_inner().unwrap(),
+        Err(_) => unreachable!("all workers have finished"),
+    };
+    report.downloaded.sort();
+    report.failed.sort_by(|a, b| a.0.cmp(&b.0));
+    report
+}
+
+/// Downloads one package, trying each url in turn until the file is downloaded and verified.
+fn download_job<D>(handle: Option<usize>,
+                   downloader: &mut D,
+                   job: &Job,
+                   dir: &Path,
+                   mirrors: Option<&Mirrors>,
+                   verify: fn(&Checksum, &Path) -> bool)
+                   -> Result<(), DownloadError>
+    where D: Downloader
+{
+    let urls = match mirrors {
+        Some(mirrors) => mirrors.order(&job.urls),
+        None => job.urls.clone(),
+    };
+    let mut error = DownloadError::NotFound(job.filename.clone());
+    for url in &urls {
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(_) => {
+                error = DownloadError::InvalidUrl(url.clone());
+                continue;
+            }
+        };
+        let mut progress = DownloadProgress::new(handle, &job.filename);
+        // anything already there didn't match, so always download
+        if let Err(e) = downloader.download(&url, dir, true, &mut progress) {
+            error = e;
+            continue;
+        }
+        let path = dir.join(&job.filename);
+        match job.checksum {
+            Some(ref expected) if !verify(expected, &path) => {
+                let _ = fs::remove_file(&path);
+                let _ = fs::remove_file(part_path(&path));
+                if let Some(mirrors) = mirrors {
+                    mirrors.reject(mirror_of(url.as_str()));
+                }
+                error = DownloadError::Checksum(job.filename.clone());
+            }
+            _ => return Ok(()),
+        }
+    }
+    Err(error)
+}
+
+/// Sets the downloader for a handle, replacing any it had.
+pub(crate) fn register(handle: usize, downloader: Box<Downloader>) {
+    let mut downloaders = DOWNLOADERS.lock().unwrap();
+    downloaders.retain(|registration| registration.handle != handle);
+    downloaders.push(Registration {
+        handle: handle,
+        downloader: Arc::new(Mutex::new(downloader)),
+        error: None,
+    });
+}
+
+/// Removes the downloader for a handle, if it has one.
+pub(crate) fn unregister(handle: usize) {
+    DOWNLOADERS.lock().unwrap().retain(|registration| registration.handle != handle);
+}
+
+/// Takes the last error reported by the downloader for a handle.
+pub(crate) fn take_error(handle: usize) -> Option<DownloadError> {
+    DOWNLOADERS.lock().unwrap()
+        .iter_mut()
+        .find(|registration| registration.handle == handle)
+        .and_then(|registration| registration.error.take())
+}
+
+/// Fetches a file for libalpm with the downloader of `handle`, or the most recently set
+/// downloader if the handle isn't known. Returns 0 on success, 1 if the file was up to date and
+/// -1 on error.
+pub(crate) fn fetch(handle: Option<usize>, url: &str, dest: &str, force: bool) -> c_int {
+    let (handle, downloader) = {
+        let downloaders = DOWNLOADERS.lock().unwrap();
+        let registration = match handle {
+            Some(handle) => downloaders.iter().find(|r| r.handle == handle),
+            None => downloaders.last(),
+        };
+        match registration {
+            Some(registration) => (registration.handle, registration.downloader.clone()),
+            None => return -1,
+        }
+    };
+    let result = Url::parse(url)
+        .map_err(|_| DownloadError::InvalidUrl(url.to_owned()))
+        .and_then(|parsed| {
+            let name = file_name(&parsed)?.to_owned();
+            let mut progress = DownloadProgress::new(Some(handle), &name);
+            let result = downloader.lock().unwrap()
+                .download(&parsed, Path::new(dest), force, &mut progress);
+            // a failure here may only be one server; libalpm goes on to the next
+            report_download_done(Some(handle), &name, result.is_ok());
+            result
+        });
+    match result {
+        Ok(DownloadStatus::Downloaded) => 0,
+        Ok(DownloadStatus::UpToDate) => 1,
+        Err(e) => {
+            let mut downloaders = DOWNLOADERS.lock().unwrap();
+            if let Some(registration) = downloaders.iter_mut().find(|r| r.handle == handle) {
+                registration.error = Some(e);
+            }
+            -1
+        }
+    }
+}
+
+/// Creates an empty directory for a test, which the test removes when it's done.
+#[cfg(test)]
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    use std::env;
+    use std::process;
+
+    let dir = env::temp_dir().join(format!("libalpm-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_local_downloader() {
+    let mirror = temp_dir("mirror");
+    let dest = temp_dir("dest");
+    File::create(mirror.join("a-1-1-any.pkg.tar.xz")).unwrap().write_all(b"package").unwrap();
+
+    let url = Url::from_file_path(mirror.join("a-1-1-any.pkg.tar.xz")).unwrap();
+    let mut progress = DownloadProgress::new(None, "a-1-1-any.pkg.tar.xz");
+    let mut local = LocalDownloader::new();
+    assert_eq!(local.download(&url, &dest, false, &mut progress).unwrap(),
+               DownloadStatus::Downloaded);
+    let mut copied = String::new();
+    File::open(dest.join("a-1-1-any.pkg.tar.xz")).unwrap().read_to_string(&mut copied).unwrap();
+    assert_eq!(copied, "package");
+    assert_eq!(local.download(&url, &dest, false, &mut progress).unwrap(),
+               DownloadStatus::UpToDate);
+    assert_eq!(local.download(&url, &dest, true, &mut progress).unwrap(),
+               DownloadStatus::Downloaded);
+    assert!(!part_path(&dest.join("a-1-1-any.pkg.tar.xz")).exists());
+
+    // copying onto the source would empty it
+    assert_eq!(local.download(&url, &mirror, true, &mut progress).unwrap(),
+               DownloadStatus::UpToDate);
+    let mut source = String::new();
+    File::open(mirror.join("a-1-1-any.pkg.tar.xz")).unwrap().read_to_string(&mut source).unwrap();
+    assert_eq!(source, "package");
+
+    // any url is served from the directory
+    let url = Url::parse("https://mirror.example/core/os/x86_64/a-1-1-any.pkg.tar.xz").unwrap();
+    let mut mirrored = LocalDownloader::with_dir(&mirror);
+    assert_eq!(mirrored.download(&url, &dest, true, &mut progress).unwrap(),
+               DownloadStatus::Downloaded);
+    match local.download(&url, &dest, false, &mut progress) {
+        Err(DownloadError::UnsupportedScheme(ref scheme)) if scheme == "https" => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    let url = Url::parse("https://mirror.example/core/os/x86_64/b-1-1-any.pkg.tar.xz").unwrap();
+    match mirrored.download(&url, &dest, false, &mut progress) {
+        Err(DownloadError::NotFound(_)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    fs::remove_dir_all(&mirror).unwrap();
+    fs::remove_dir_all(&dest).unwrap();
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_http_downloader() {
+    use std::net::TcpListener;
+    use std::thread;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = thread::spawn(move || {
+        let responses = ["HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\npackage",
+                         "HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\nnot found"];
+        for response in responses.iter() {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request).unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    let dest = temp_dir("http");
+    let mut http = HttpDownloader::new();
+    let mut progress = DownloadProgress::new(None, "a-1-1-any.pkg.tar.xz");
+    let url = Url::parse(&format!("http://127.0.0.1:{}/a-1-1-any.pkg.tar.xz", port)).unwrap();
+    assert_eq!(http.download(&url, &dest, true, &mut progress).unwrap(),
+               DownloadStatus::Downloaded);
+    let mut downloaded = String::new();
+    File::open(dest.join("a-1-1-any.pkg.tar.xz")).unwrap().read_to_string(&mut downloaded)
+        .unwrap();
+    assert_eq!(downloaded, "package");
+    let url = Url::parse(&format!("http://127.0.0.1:{}/b-1-1-any.pkg.tar.xz", port)).unwrap();
+    match http.download(&url, &dest, true, &mut progress) {
+        Err(DownloadError::NotFound(_)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(!dest.join("b-1-1-any.pkg.tar.xz").exists());
+    server.join().unwrap();
+    fs::remove_dir_all(&dest).unwrap();
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_http_resume() {
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration;
+    use mirror::{Mirrors, RetryPolicy, RetryingDownloader};
+
+    // sends half of the file and drops the connection, unless asked for the rest; "/norange/"
+    // always sends the whole file
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = [0; 4096];
+            let n = stream.read(&mut request).unwrap();
+            let request = String::from_utf8_lossy(&request[..n]).into_owned();
+            let response = if request.starts_with("GET /norange/") {
+                "HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n0123456789"
+            } else if request.contains("Range: bytes=5-") {
+                "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 5-9/10\r\n\
+                 Content-Length: 5\r\n\r\n56789"
+            } else {
+                "HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n01234"
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    let read = |path: &Path| {
+        let mut contents = String::new();
+        File::open(path).unwrap().read_to_string(&mut contents).unwrap();
+        contents
+    };
+
+    let dest = temp_dir("resume");
+    let mut http = HttpDownloader::new();
+    let mut progress = DownloadProgress::new(None, "a-1-1-any.pkg.tar.xz");
+    let url = Url::parse(&format!("http://127.0.0.1:{}/a-1-1-any.pkg.tar.xz", port)).unwrap();
+    match http.download(&url, &dest, true, &mut progress) {
+        Err(DownloadError::Transfer(_)) => (),
+        other => panic!("unexpected result {:?}", other),
+    }
+    assert!(!dest.join("a-1-1-any.pkg.tar.xz").exists());
+    assert_eq!(read(&dest.join("a-1-1-any.pkg.tar.xz.part")), "01234");
+    assert_eq!(http.download(&url, &dest, true, &mut progress).unwrap(),
+               DownloadStatus::Downloaded);
+    assert_eq!(read(&dest.join("a-1-1-any.pkg.tar.xz")), "0123456789");
+    assert!(!dest.join("a-1-1-any.pkg.tar.xz.part").exists());
+
+    // a server that ignores the range replaces the partial file
+    File::create(dest.join("b-1-1-any.pkg.tar.xz.part")).unwrap().write_all(b"xxxxx").unwrap();
+    let url = Url::parse(&format!("http://127.0.0.1:{}/norange/b-1-1-any.pkg.tar.xz", port))
+        .unwrap();
+    assert_eq!(http.download(&url, &dest, true, &mut progress).unwrap(),
+               DownloadStatus::Downloaded);
+    assert_eq!(read(&dest.join("b-1-1-any.pkg.tar.xz")), "0123456789");
+
+    // retrying resumes, and a file with the wrong checksum is discarded
+    fn contents(checksum: &Checksum, path: &Path) -> bool {
+        let mut contents = String::new();
+        let _ = File::open(path).and_then(|mut file| file.read_to_string(&mut contents));
+        *checksum == Checksum::Sha256(contents)
+    }
+    let jobs = ["c", "d"].iter().map(|name| {
+        let filename = format!("{}-1-1-any.pkg.tar.xz", name);
+        Job {
+            urls: vec![format!("http://127.0.0.1:{}/{}", port, filename)],
+            checksum: Some(Checksum::Sha256(if *name == "c" { "0123456789" } else { "wrong" }
+                                                .to_owned())),
+            filename: filename,
+        }
+    }).collect();
+    let policy = RetryPolicy::new().backoff(Duration::from_millis(1));
+    let downloader = RetryingDownloader::new(HttpDownloader::new(), Mirrors::new(policy));
+    let options = DownloadOptions::new();
+    let report = download_parallel(None, jobs, &dest, &downloader, &options, contents);
+    assert_eq!(report.downloaded, vec!["c-1-1-any.pkg.tar.xz".to_owned()]);
+    assert_eq!(read(&dest.join("c-1-1-any.pkg.tar.xz")), "0123456789");
+    match report.failed[..] {
+        [(ref name, DownloadError::Checksum(_))] if name == "d-1-1-any.pkg.tar.xz" => (),
+        ref other => panic!("unexpected failures {:?}", other),
+    }
+    assert!(!dest.join("d-1-1-any.pkg.tar.xz").exists());
+    assert!(!dest.join("d-1-1-any.pkg.tar.xz.part").exists());
+    fs::remove_dir_all(&dest).unwrap();
+}
+
+#[test]
+fn test_download_parallel() {
+    // stands in for a real checksum
+    fn contents(checksum: &Checksum, path: &Path) -> bool {
+        let mut contents = String::new();
+        let _ = File::open(path).and_then(|mut file| file.read_to_string(&mut contents));
+        *checksum == Checksum::Sha256(contents)
+    }
+
+    let mirror_dir = temp_dir("parallel-mirror");
+    let dest = temp_dir("parallel-dest");
+    let missing = Url::from_directory_path(mirror_dir.join("missing")).unwrap();
+    let mirror = Url::from_directory_path(&mirror_dir).unwrap();
+    let mut jobs = Vec::new();
+    for n in 0..10 {
+        let filename = format!("p{}-1-1-any.pkg.tar.xz", n);
+        let path = mirror.join(&filename).unwrap().to_file_path().unwrap();
+        File::create(path).unwrap().write_all(filename.as_bytes()).unwrap();
+        let sha256 = if n == 3 { "corrupt".into() } else { filename.clone() };
+        // the first server doesn't have anything
+        jobs.push(Job {
+            urls: vec![missing.join(&filename).unwrap().into_string(),
+                       mirror.join(&filename).unwrap().into_string()],
+            checksum: Some(Checksum::Sha256(sha256)),
+            filename: filename,
+        });
+    }
+
+    let options = DownloadOptions::new().parallel(3);
+    let report = download_parallel(None, jobs, &dest, &LocalDownloader::new(), &options, contents);
+    assert_eq!(report.downloaded.len(), 9);
+    assert!(report.cached.is_empty());
+    assert_eq!(report.failed.len(), 1);
+    match report.failed[0] {
+        (ref name, DownloadError::Checksum(_)) if name == "p3-1-1-any.pkg.tar.xz" => (),
+        ref other => panic!("unexpected failure {:?}", other),
+    }
+    assert!(!report.is_ok());
+    assert!(dest.join("p0-1-1-any.pkg.tar.xz").exists());
+    assert!(!dest.join("p3-1-1-any.pkg.tar.xz").exists());
+    fs::remove_dir_all(&mirror_dir).unwrap();
+    fs::remove_dir_all(&dest).unwrap();
+}
@@ -258,7 +258,9 @@ impl From<u32> for Error {
             ALPM_ERR_SIG_MISSING => Error::SigMissing,
             ALPM_ERR_SIG_INVALID => Error::SigInvalid,
             /* Deltas */
+            #[cfg(not(alpm_v12))]
             ALPM_ERR_DLT_INVALID => Error::DltInvalid,
+            #[cfg(not(alpm_v12))]
             ALPM_ERR_DLT_PATCHFAILED => Error::DltPatchFailed,
             /* Dependencies */
             ALPM_ERR_UNSATISFIED_DEPS => Error::UnsatisfiedDeps,
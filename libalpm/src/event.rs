@@ -2,7 +2,7 @@
 
 use alpm_sys::*;
 
-use package::{Package, PackageOperation};
+use package::{Package, PackageOperation, PackageOperationRecord};
 
 /// An event emitted from libalpm
 pub enum Event<'a> {
@@ -38,7 +38,7 @@ pub enum Event<'a> {
     LoadStart,
     /// Target package is finished loading.
     LoadDone,
-    /// Target delta's integrity will be checked.
+    /// Target delta's integrity will be checked (deltas were removed in libalpm 12).
     DeltaIntegrityStart,
     /// Target delta's integrity was checked.
     DeltaIntegrityDone,
@@ -54,17 +54,21 @@ pub enum Event<'a> {
     DeltaPatchFailed,
     /// Scriptlet has printed information; See alpm_event_scriptlet_info_t for arguments.
     ScriptletInfo,
-    /// Files will be downloaded from a repository.
+    /// Files will be downloaded from a repository (databases only since libalpm 13).
     RetrieveStart,
-    /// Files were downloaded from a repository.
+    /// Files were downloaded from a repository (databases only since libalpm 13).
     RetrieveDone,
-    /// Not all files were successfully downloaded from a repository.
+    /// Not all files were successfully downloaded from a repository (databases only since
+    /// libalpm 13).
     RetrieveFailed,
-    /// A file will be downloaded from a repository; See alpm_event_pkgdownload_t for arguments
+    /// A file will be downloaded from a repository; See alpm_event_pkgdownload_t for arguments.
+    /// Since libalpm 13 this is sent once before all the packages are downloaded.
     PkgDownloadStart,
-    /// A file was downloaded from a repository; See alpm_event_pkgdownload_t for arguments
+    /// A file was downloaded from a repository; See alpm_event_pkgdownload_t for arguments.
+    /// Since libalpm 13 this is sent once after all the packages are downloaded.
     PkgDownloadDone,
-    /// A file failed to be downloaded from a repository; See alpm_event_pkgdownload_t for arguments
+    /// A file failed to be downloaded from a repository; See alpm_event_pkgdownload_t for
+    /// arguments. Since libalpm 13 this is sent once if any package failed to download.
     PkgDownloadFailed,
     /// Disk space usage will be computed for a package.
     DiskspaceStart,
@@ -118,20 +122,45 @@ impl<'a> Event<'a> {
             ALPM_EVENT_INTEGRITY_DONE => Event::IntegrityDone,
             ALPM_EVENT_LOAD_START => Event::LoadStart,
             ALPM_EVENT_LOAD_DONE => Event::LoadDone,
+            #[cfg(not(alpm_v12))]
             ALPM_EVENT_DELTA_INTEGRITY_START => Event::DeltaIntegrityStart,
+            #[cfg(not(alpm_v12))]
             ALPM_EVENT_DELTA_INTEGRITY_DONE => Event::DeltaIntegrityDone,
+            #[cfg(not(alpm_v12))]
             ALPM_EVENT_DELTA_PATCHES_START => Event::DeltaPatchesStart,
+            #[cfg(not(alpm_v12))]
             ALPM_EVENT_DELTA_PATCHES_DONE => Event::DeltaPatchesDone,
+            #[cfg(not(alpm_v12))]
             ALPM_EVENT_DELTA_PATCH_START => Event::DeltaPatchStart,
+            #[cfg(not(alpm_v12))]
             ALPM_EVENT_DELTA_PATCH_DONE => Event::DeltaPatchDone,
+            #[cfg(not(alpm_v12))]
             ALPM_EVENT_DELTA_PATCH_FAILED => Event::DeltaPatchFailed,
             ALPM_EVENT_SCRIPTLET_INFO => Event::ScriptletInfo,
+            #[cfg(not(alpm_v13))]
             ALPM_EVENT_RETRIEVE_START => Event::RetrieveStart,
+            #[cfg(not(alpm_v13))]
             ALPM_EVENT_RETRIEVE_DONE => Event::RetrieveDone,
+            #[cfg(not(alpm_v13))]
             ALPM_EVENT_RETRIEVE_FAILED => Event::RetrieveFailed,
+            #[cfg(not(alpm_v13))]
             ALPM_EVENT_PKGDOWNLOAD_START => Event::PkgDownloadStart,
+            #[cfg(not(alpm_v13))]
             ALPM_EVENT_PKGDOWNLOAD_DONE => Event::PkgDownloadDone,
+            #[cfg(not(alpm_v13))]
             ALPM_EVENT_PKGDOWNLOAD_FAILED => Event::PkgDownloadFailed,
+            #[cfg(alpm_v13)]
+            ALPM_EVENT_DB_RETRIEVE_START => Event::RetrieveStart,
+            #[cfg(alpm_v13)]
+            ALPM_EVENT_DB_RETRIEVE_DONE => Event::RetrieveDone,
+            #[cfg(alpm_v13)]
+            ALPM_EVENT_DB_RETRIEVE_FAILED => Event::RetrieveFailed,
+            #[cfg(alpm_v13)]
+            ALPM_EVENT_PKG_RETRIEVE_START => Event::PkgDownloadStart,
+            #[cfg(alpm_v13)]
+            ALPM_EVENT_PKG_RETRIEVE_DONE => Event::PkgDownloadDone,
+            #[cfg(alpm_v13)]
+            ALPM_EVENT_PKG_RETRIEVE_FAILED => Event::PkgDownloadFailed,
             ALPM_EVENT_DISKSPACE_START => Event::DiskspaceStart,
             ALPM_EVENT_DISKSPACE_DONE => Event::DiskspaceDone,
             ALPM_EVENT_OPTDEP_REMOVAL => Event::OptDepRemoval,
@@ -150,3 +179,158 @@ impl<'a> Event<'a> {
         }
     }
 }
+
+/// An owned copy of an `Event`, which can be kept after the callback returns.
+///
+/// Packages are identified by name and version, as the packages themselves may be freed once
+/// the event has been handled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventRecord {
+    /// Dependencies will be computed for a package.
+    CheckDepsStart,
+    /// Dependencies were computed for a package.
+    CheckDepsDone,
+    /// File conflicts will be computed for a package.
+    FileConflictsStart,
+    /// File conflicts were computed for a package.
+    FileConflictsDone,
+    /// Dependencies will be resolved for target package.
+    ResolveDepsStart,
+    /// Dependencies were resolved for target package.
+    ResolveDepsDone,
+    /// Inter-conflicts will be checked for target package.
+    InterConflictsStart,
+    /// Inter-conflicts were checked for target package.
+    InterConflictsDone,
+    /// Processing the package transaction is starting.
+    TransactionStart,
+    /// Processing the package transaction is finished.
+    TransactionDone,
+    /// Package will be installed/upgraded/downgraded/re-installed/removed.
+    PackageOperationStart(PackageOperationRecord),
+    /// Package was installed/upgraded/downgraded/re-installed/removed.
+    PackageOperationDone(PackageOperationRecord),
+    /// Target package's integrity will be checked.
+    IntegrityStart,
+    /// Target package's integrity was checked.
+    IntegrityDone,
+    /// Target package will be loaded.
+    LoadStart,
+    /// Target package is finished loading.
+    LoadDone,
+    /// Target delta's integrity will be checked (deltas were removed in libalpm 12).
+    DeltaIntegrityStart,
+    /// Target delta's integrity was checked.
+    DeltaIntegrityDone,
+    /// Deltas will be applied to packages.
+    DeltaPatchesStart,
+    /// Deltas were applied to packages.
+    DeltaPatchesDone,
+    /// Delta patch will be applied to target package; See alpm_event_delta_patch_t for arguments..
+    DeltaPatchStart,
+    /// Delta patch was applied to target package.
+    DeltaPatchDone,
+    /// Delta patch failed to apply to target package.
+    DeltaPatchFailed,
+    /// Scriptlet has printed information; See alpm_event_scriptlet_info_t for arguments.
+    ScriptletInfo,
+    /// Files will be downloaded from a repository (databases only since libalpm 13).
+    RetrieveStart,
+    /// Files were downloaded from a repository (databases only since libalpm 13).
+    RetrieveDone,
+    /// Not all files were successfully downloaded from a repository (databases only since
+    /// libalpm 13).
+    RetrieveFailed,
+    /// A file will be downloaded from a repository; See alpm_event_pkgdownload_t for arguments.
+    /// Since libalpm 13 this is sent once before all the packages are downloaded.
+    PkgDownloadStart,
+    /// A file was downloaded from a repository; See alpm_event_pkgdownload_t for arguments.
+    /// Since libalpm 13 this is sent once after all the packages are downloaded.
+    PkgDownloadDone,
+    /// A file failed to be downloaded from a repository; See alpm_event_pkgdownload_t for
+    /// arguments. Since libalpm 13 this is sent once if any package failed to download.
+    PkgDownloadFailed,
+    /// Disk space usage will be computed for a package.
+    DiskspaceStart,
+    /// Disk space usage was computed for a package.
+    DiskspaceDone,
+    /// An optdepend for another package is being removed; See alpm_event_optdep_removal_t for
+    /// arguments.
+    OptDepRemoval,
+    /// A configured repository database is missing; See alpm_event_database_missing_t for
+    /// arguments.
+    DatabaseMissing,
+    /// Checking keys used to create signatures are in keyring.
+    KeyringStart,
+    /// Keyring checking is finished.
+    KeyringDone,
+    /// Downloading missing keys into keyring.
+    KeyDownloadStart,
+    /// Key downloading is finished.
+    KeyDownloadDone,
+    /// A .pacnew file was created; See alpm_event_pacnew_created_t for arguments.
+    PacnewCreated,
+    /// A .pacsave file was created; See alpm_event_pacsave_created_t for arguments
+    PacsaveCreated,
+    /// Processing hooks will be started.
+    HookStart,
+    /// Processing hooks is finished.
+    HookDone,
+    /// A hook is starting
+    HookRunStart,
+    /// A hook has finished running
+    HookRunDone,
+    __Unknown
+}
+
+impl<'a, 'b> From<&'b Event<'a>> for EventRecord {
+    fn from(event: &'b Event<'a>) -> EventRecord {
+        match *event {
+            Event::CheckDepsStart => EventRecord::CheckDepsStart,
+            Event::CheckDepsDone => EventRecord::CheckDepsDone,
+            Event::FileConflictsStart => EventRecord::FileConflictsStart,
+            Event::FileConflictsDone => EventRecord::FileConflictsDone,
+            Event::ResolveDepsStart => EventRecord::ResolveDepsStart,
+            Event::ResolveDepsDone => EventRecord::ResolveDepsDone,
+            Event::InterConflictsStart => EventRecord::InterConflictsStart,
+            Event::InterConflictsDone => EventRecord::InterConflictsDone,
+            Event::TransactionStart => EventRecord::TransactionStart,
+            Event::TransactionDone => EventRecord::TransactionDone,
+            Event::PackageOperationStart(ref op) => EventRecord::PackageOperationStart(op.into()),
+            Event::PackageOperationDone(ref op) => EventRecord::PackageOperationDone(op.into()),
+            Event::IntegrityStart => EventRecord::IntegrityStart,
+            Event::IntegrityDone => EventRecord::IntegrityDone,
+            Event::LoadStart => EventRecord::LoadStart,
+            Event::LoadDone => EventRecord::LoadDone,
+            Event::DeltaIntegrityStart => EventRecord::DeltaIntegrityStart,
+            Event::DeltaIntegrityDone => EventRecord::DeltaIntegrityDone,
+            Event::DeltaPatchesStart => EventRecord::DeltaPatchesStart,
+            Event::DeltaPatchesDone => EventRecord::DeltaPatchesDone,
+            Event::DeltaPatchStart => EventRecord::DeltaPatchStart,
+            Event::DeltaPatchDone => EventRecord::DeltaPatchDone,
+            Event::DeltaPatchFailed => EventRecord::DeltaPatchFailed,
+            Event::ScriptletInfo => EventRecord::ScriptletInfo,
+            Event::RetrieveStart => EventRecord::RetrieveStart,
+            Event::RetrieveDone => EventRecord::RetrieveDone,
+            Event::RetrieveFailed => EventRecord::RetrieveFailed,
+            Event::PkgDownloadStart => EventRecord::PkgDownloadStart,
+            Event::PkgDownloadDone => EventRecord::PkgDownloadDone,
+            Event::PkgDownloadFailed => EventRecord::PkgDownloadFailed,
+            Event::DiskspaceStart => EventRecord::DiskspaceStart,
+            Event::DiskspaceDone => EventRecord::DiskspaceDone,
+            Event::OptDepRemoval => EventRecord::OptDepRemoval,
+            Event::DatabaseMissing => EventRecord::DatabaseMissing,
+            Event::KeyringStart => EventRecord::KeyringStart,
+            Event::KeyringDone => EventRecord::KeyringDone,
+            Event::KeyDownloadStart => EventRecord::KeyDownloadStart,
+            Event::KeyDownloadDone => EventRecord::KeyDownloadDone,
+            Event::PacnewCreated => EventRecord::PacnewCreated,
+            Event::PacsaveCreated => EventRecord::PacsaveCreated,
+            Event::HookStart => EventRecord::HookStart,
+            Event::HookDone => EventRecord::HookDone,
+            Event::HookRunStart => EventRecord::HookRunStart,
+            Event::HookRunDone => EventRecord::HookRunDone,
+            Event::__Unknown => EventRecord::__Unknown,
+        }
+    }
+}
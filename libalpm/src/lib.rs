@@ -15,15 +15,16 @@
 //! alpm
 //! ```
 
-#![feature(untagged_unions)]
-#![feature(pub_restricted)]
-
 extern crate alpm_sys;
 extern crate url;
 extern crate libc;
 extern crate printf;
 extern crate chrono;
 #[macro_use] extern crate lazy_static;
+#[cfg(feature = "log")]
+#[macro_use] extern crate log as log_facade;
+#[cfg(feature = "http")]
+extern crate curl;
 
 mod error;
 mod event;
@@ -35,6 +36,12 @@ mod callbacks;
 mod options;
 mod types;
 mod trans;
+mod subscription;
+mod download;
+mod mirror;
+mod tracker;
+mod cache;
+mod orphans;
 pub mod util;
 
 use std::ffi::{CString, CStr};
@@ -45,33 +52,60 @@ use std::borrow::Borrow;
 use std::mem;
 use std::ptr;
 use std::marker::PhantomData;
+use std::sync::mpsc::Receiver;
 
 use alpm_sys::*;
 use libc::{c_char, c_void};
 
-pub use options::{Config, RepoConfig};
+pub use options::{Config, RepoConfig, CleanMethod};
 pub use error::{Error, AlpmResult};
-pub use log::{LogLevel, LogLevels};
-pub use event::Event;
+pub use log::{LogLevel, LogLevels, LogRecord, LogArg};
+pub use event::{Event, EventRecord};
 pub use package::{Package, PackageRef, Group, PackageVersion, PackageFrom, Reason, Validation,
-    ValidationMethod, Dependency, FileList, File, Backup, VersionConstraintType};
+    ValidationMethod, Dependency, FileList, File, Backup, VersionConstraintType, PackageRecord,
+    PackageOperationRecord};
 pub use db::Db;
 pub use pgp::SigLevel;
-pub use types::{Caps, DownloadResult};
-pub use trans::{TransactionError, Transaction, Initialized, Prepared, TransactionFlags};
-use callbacks::{alpm_cb_log, alpm_cb_download, alpm_cb_totaldl, alpm_cb_fetch, alpm_cb_event};
+pub use types::Caps;
+pub use download::{Downloader, DownloadStatus, DownloadError, DownloadProgress, LocalDownloader,
+                   DownloadOptions, DownloadReport};
+#[cfg(feature = "http")]
+pub use download::HttpDownloader;
+pub use mirror::{RetryPolicy, Mirrors, MirrorStats, RetryingDownloader};
+pub use trans::{TransactionError, Transaction, Initialized, Prepared, TransactionFlags,
+                TransactionFlagsBuilder, InvalidFlags};
+pub use subscription::{Record, ProgressKind};
+pub use tracker::{DownloadTracker, DownloadState, FileProgress, FileStatus};
+pub use cache::{CachedPackage, KeepPolicy, CleanOptions, CleanReport, CacheProblem, AuditReport,
+                parse_package_filename};
+use callbacks::{alpm_cb_log, alpm_cb_log_record, alpm_cb_download, alpm_cb_fetch, alpm_cb_event,
+                alpm_cb_progress};
+#[cfg(not(alpm_v13))]
+use callbacks::alpm_cb_totaldl;
+use download::Checksum;
 
 // callbacks
 lazy_static! {
     static ref LOG_CB: Mutex<Option<Box<FnMut(LogLevels, String) + Send>>> = Default::default();
+    static ref LOG_RECORD_CB: Mutex<Option<Box<FnMut(LogRecord) + Send>>> = Default::default();
     static ref DOWNLOAD_CB: Mutex<Option<Box<FnMut(&str, u64, u64) + Send>>> = Default::default();
-    static ref FETCH_CB: Mutex<Option<Box<FnMut(&str, &str, bool) -> DownloadResult + Send>>> = Default::default();
     static ref DLTOTAL_CB: Mutex<Option<Box<FnMut(u64) + Send>>> = Default::default();
     static ref EVENT_CB: Mutex<Option<Box<FnMut(Event) + Send>>> = Default::default();
     //static ref QUESTION_CB: Mutex<Option<Box<FnMut(LogLevels, String) + Send>>> = Default::default();
     //static ref PROGRESS_CB: Mutex<Option<Box<FnMut(LogLevels, String) + Send>>> = Default::default();
 }
 
+/// Registers a C callback. Since libalpm 13 each callback is registered with a context pointer,
+/// which is the handle so that subscribers only get records from their own handle.
+#[cfg(not(alpm_v13))]
+macro_rules! set_callback {
+    ($set:ident, $handle:expr, $cb:expr) => { $set($handle, $cb) }
+}
+#[cfg(alpm_v13)]
+macro_rules! set_callback {
+    ($set:ident, $handle:expr, $cb:expr) => { $set($handle, $cb, $handle as *mut c_void) }
+}
+
 /// A handle on an alpm instance
 ///
 /// Note that I have NOT checked whether the interface is threadsafe, so it's best to use only one
@@ -108,22 +142,33 @@ impl Alpm {
     /// TODO will only be implemented after the rest of the library is finished.
     pub fn with_config(config: &Config) -> AlpmResult<Alpm> {
         let sys_arch = util::uname().machine().to_owned();
-        let arch = if config.arch == "auto" {
-            &sys_arch
-        } else {
-            &config.arch
-        };
+        let mut arches: Vec<&str> = config.arch.iter()
+            .map(|arch| if arch == "auto" { &sys_arch } else { arch.as_str() })
+            .collect();
+        if arches.is_empty() {
+            arches.push(&sys_arch);
+        }
+        // servers are for the first architecture, like in pacman
+        let arch = arches[0];
         let alpm = Alpm::new(&config.root_dir, &config.db_path)?;
+        #[cfg(not(alpm_v13))]
+        alpm.set_arch(arch)?;
+        #[cfg(alpm_v13)]
+        for arch in arches.iter() {
+            alpm.add_architecture(arch)?;
+        }
 
         for (name, repo) in config.repositories.iter() {
             let db = alpm.register_sync_db(name, SigLevel::default()).unwrap();
             let mut fixed_servers = repo.servers.iter().map(
-                |el| el.replace("$arch", &arch).replace("$repo", name)
+                |el| el.replace("$arch", arch).replace("$repo", name)
             );
             for server in fixed_servers {
                 db.add_server(&server).unwrap();
             }
         }
+        #[cfg(alpm_v13)]
+        alpm.set_parallel_downloads(config.parallel_downloads)?;
 
         Ok(alpm)
     }
@@ -154,6 +199,7 @@ impl Alpm {
     }
 
     /// Fetch a remote pkg from the given URL and return its path.
+    #[cfg(not(alpm_v13))]
     pub fn fetch_pkgurl(&self, url: &str) -> AlpmResult<PathBuf> {
         unsafe {
             let url = CString::new(url)?;
@@ -169,20 +215,81 @@ impl Alpm {
         }
     }
 
+    /// Fetch a remote pkg from the given URL and return its path.
+    #[cfg(alpm_v13)]
+    pub fn fetch_pkgurl(&self, url: &str) -> AlpmResult<PathBuf> {
+        unsafe {
+            let url = CString::new(url)?;
+            let urls = alpm_list_add(ptr::null(), url.as_ptr() as *const c_void);
+            let mut fetched: *mut alpm_list_t = ptr::null_mut();
+            let res = alpm_fetch_pkgurl(self.handle, urls, &mut fetched);
+            alpm_list_free(urls);
+            if res != 0 || fetched.is_null() {
+                return Err(self.error().unwrap_or(Error::__Unknown));
+            }
+            // copy path into rust alloc'd data struct
+            let path = CStr::from_ptr((*fetched).data as *const c_char).to_str()
+                .map(PathBuf::from);
+            alpm_list_free_inner(fetched, Some(libc::free));
+            alpm_list_free(fetched);
+            Ok(path?)
+        }
+    }
+
     /// Set the callback called when a log message is received.
+    ///
+    /// This replaces any callback set with `log_record_function`.
     pub fn log_function<F>(&self, func: F)
         where F: FnMut(LogLevels, String) + Send + 'static
     {
+        (*LOG_RECORD_CB.lock().unwrap()) = None;
         let mut cb = LOG_CB.lock().unwrap();
         (*cb) = Some(Box::new(func));
-        unsafe { alpm_option_set_logcb(self.handle, Some(alpm_cb_log)); }
+        unsafe { set_callback!(alpm_option_set_logcb, self.handle, Some(alpm_cb_log)); }
+    }
+
+    /// Sends log messages to the `log` crate under the given target (e.g. `"libalpm"`), instead
+    /// of a log callback.
+    ///
+    /// Errors and warnings are logged at `Error` and `Warn`, debug messages at `Debug` and
+    /// function traces at `Trace`. The trailing newline libalpm adds is removed.
+    ///
+    /// Only available with the `log` feature.
+    #[cfg(feature = "log")]
+    pub fn log_to_facade<T: Into<String>>(&self, target: T) {
+        let target = target.into();
+        self.log_function(move |level, mut msg| {
+            if msg.ends_with('\n') {
+                msg.pop();
+            }
+            let level: LogLevel = level.into();
+            log!(target: &target, level.into(), "{}", msg);
+        });
+    }
+
+    /// Set the callback called when a log message is received, with the message unformatted.
+    ///
+    /// This replaces any callback set with `log_function` (and vice versa).
+    pub fn log_record_function<F>(&self, func: F)
+        where F: FnMut(LogRecord) + Send + 'static
+    {
+        (*LOG_CB.lock().unwrap()) = None;
+        let mut cb = LOG_RECORD_CB.lock().unwrap();
+        (*cb) = Some(Box::new(func));
+        unsafe { set_callback!(alpm_option_set_logcb, self.handle, Some(alpm_cb_log_record)); }
     }
 
     /// Clears the log callback.
     pub fn clear_log_function(&self) {
         let mut cb = LOG_CB.lock().unwrap();
         (*cb) = None;
-        unsafe { alpm_option_set_logcb(self.handle, None); }
+        let mut cb = LOG_RECORD_CB.lock().unwrap();
+        (*cb) = None;
+        // subscribers still need the C callback
+        if subscription::is_active(self.handle as usize) {
+            return;
+        }
+        unsafe { set_callback!(alpm_option_set_logcb, self.handle, None); }
     }
 
     /// Set the callback called to report progress on downloading a file.
@@ -191,59 +298,83 @@ impl Alpm {
     {
         let mut cb = DOWNLOAD_CB.lock().unwrap();
         (*cb) = Some(Box::new(func));
-        unsafe { alpm_option_set_dlcb(self.handle, Some(alpm_cb_download)); }
+        unsafe { set_callback!(alpm_option_set_dlcb, self.handle, Some(alpm_cb_download)); }
     }
 
     /// Clears the file download progress callback.
     pub fn clear_file_download_progress_function(&self) {
         let mut cb = DOWNLOAD_CB.lock().unwrap();
         (*cb) = None;
-        unsafe { alpm_option_set_dlcb(self.handle, None); }
+        // subscribers still need the C callback
+        if subscription::is_active(self.handle as usize) {
+            return;
+        }
+        unsafe { set_callback!(alpm_option_set_dlcb, self.handle, None); }
     }
 
     /// Set the callback called to report progress on total download
+    ///
+    /// Since libalpm 13 the total comes from the event that starts the package downloads, so
+    /// this shares the C event callback with `event_function`.
     pub fn total_download_progress_function<F>(&self, func: F)
         where F: FnMut(u64) + Send + 'static
     {
         let mut cb = DLTOTAL_CB.lock().unwrap();
         (*cb) = Some(Box::new(func));
+        #[cfg(not(alpm_v13))]
         unsafe { alpm_option_set_totaldlcb(self.handle, Some(alpm_cb_totaldl)); }
+        #[cfg(alpm_v13)]
+        unsafe { set_callback!(alpm_option_set_eventcb, self.handle, Some(alpm_cb_event)); }
     }
 
     /// Clears the total download progress callback.
     pub fn clear_total_download_progress_function(&self) {
         let mut cb = DLTOTAL_CB.lock().unwrap();
         (*cb) = None;
+        // subscribers still need the C callback
+        if subscription::is_active(self.handle as usize) {
+            return;
+        }
+        #[cfg(not(alpm_v13))]
         unsafe { alpm_option_set_totaldlcb(self.handle, None); }
+        #[cfg(alpm_v13)]
+        {
+            if EVENT_CB.lock().unwrap().is_none() {
+                unsafe { set_callback!(alpm_option_set_eventcb, self.handle, None); }
+            }
+        }
     }
 
-    /// Set the callback called to download a file.
-    ///
-    /// Providing this function is optional and it is recommended that you don't set it (and use
-    /// the built-in fetch fn). This could be useful e.g. if you are behind a complicated proxy or
-    /// want to use something other than http to fetch.
-    ///
-    /// # Safety
-    /// Note that if you supply this function, you promise that if you return DownloadResult::Ok,
-    /// the requested file is correctly located in the given location.
+    /// Sets the downloader used to fetch files, replacing libalpm's built-in one.
     ///
-    /// A panic in the function will cause DownloadResult::Err to be sent to the underlying
-    /// libalpm (i.e. not undefined behaviour).
+    /// Progress reported by the downloader goes to the file download progress callback and
+    /// subscribers, like libalpm's own downloads. When a download fails libalpm only learns that
+    /// it failed; the error itself can be retrieved with `take_download_error`.
     ///
-    /// TODO investigate whether safe to relax 'static bound
-    pub unsafe fn fetch_function<F>(&self, func: F)
-        where F: FnMut(&str, &str, bool) -> DownloadResult + Send + 'static
+    /// Since libalpm 13 each handle has its own downloader. Older versions don't tell the
+    /// callback which handle it's for, so the most recently set downloader is used by all
+    /// handles that have one.
+    pub fn set_downloader<D>(&self, downloader: D)
+        where D: Downloader + 'static
     {
-        let mut cb = FETCH_CB.lock().unwrap();
-        (*cb) = Some(Box::new(func));
-        alpm_option_set_fetchcb(self.handle, Some(alpm_cb_fetch));
+        download::register(self.handle as usize, Box::new(downloader));
+        #[cfg(not(alpm_v13))]
+        unsafe { alpm_option_set_fetchcb(self.handle, Some(alpm_cb_fetch)); }
+        #[cfg(alpm_v13)]
+        unsafe {
+            alpm_option_set_fetchcb(self.handle, Some(alpm_cb_fetch), self.handle as *mut c_void);
+        }
     }
 
-    /// Clears the file download callback, falling back to built-in fetch functionality.
-    pub fn clear_fetch_function(&self) {
-        let mut cb = DLTOTAL_CB.lock().unwrap();
-        (*cb) = None;
-        unsafe { alpm_option_set_fetchcb(self.handle, None); }
+    /// Clears the downloader, falling back to built-in fetch functionality.
+    pub fn clear_downloader(&self) {
+        download::unregister(self.handle as usize);
+        unsafe { set_callback!(alpm_option_set_fetchcb, self.handle, None); }
+    }
+
+    /// Takes the error from the last failed download by this handle's downloader.
+    pub fn take_download_error(&self) -> Option<DownloadError> {
+        download::take_error(self.handle as usize)
     }
 
     /// Sets the function called when an event occurs
@@ -252,14 +383,78 @@ impl Alpm {
     {
         let mut cb = EVENT_CB.lock().unwrap();
         (*cb) = Some(Box::new(func));
-        unsafe { alpm_option_set_eventcb(self.handle, Some(alpm_cb_event)); }
+        unsafe { set_callback!(alpm_option_set_eventcb, self.handle, Some(alpm_cb_event)); }
     }
 
-    /// Clears the file download callback, falling back to built-in fetch functionality.
+    /// Clears the function called when an event occurs
     pub fn clear_event_function(&self) {
-        let mut cb = DLTOTAL_CB.lock().unwrap();
+        let mut cb = EVENT_CB.lock().unwrap();
         (*cb) = None;
-        unsafe { alpm_option_set_eventcb(self.handle, None); }
+        // subscribers still need the C callback
+        if subscription::is_active(self.handle as usize) {
+            return;
+        }
+        // the total download callback relies on events since libalpm 13
+        #[cfg(alpm_v13)]
+        {
+            if DLTOTAL_CB.lock().unwrap().is_some() {
+                return;
+            }
+        }
+        unsafe { set_callback!(alpm_option_set_eventcb, self.handle, None); }
+    }
+
+    /// Subscribes to everything libalpm reports (events, log messages, download progress and
+    /// transaction progress) as owned records, buffering up to 256 records.
+    ///
+    /// The receiver can be read on another thread while this handle runs a transaction. Records
+    /// arrive in the order they were reported, and none are dropped: when the buffer is full,
+    /// libalpm waits for the receiver to catch up, so don't read it on the thread running
+    /// libalpm. Dropping the receiver ends the subscription.
+    ///
+    /// Any callbacks already set keep working. Only records from this handle are received, except
+    /// before libalpm 13, where libalpm doesn't say which handle its callbacks are for, so
+    /// records from every handle are received.
+    pub fn subscribe(&self) -> Receiver<Record> {
+        self.subscribe_with_capacity(subscription::DEFAULT_CAPACITY)
+    }
+
+    /// Like `subscribe`, but buffers up to `capacity` records.
+    pub fn subscribe_with_capacity(&self, capacity: usize) -> Receiver<Record> {
+        let receiver = subscription::subscribe(self.handle as usize, capacity);
+        self.set_subscription_callbacks();
+        receiver
+    }
+
+    /// Tracks the progress of all downloads together: each file's progress, the overall bytes
+    /// downloaded, the transfer rate and time remaining, and how many files have completed or
+    /// failed. This includes downloads done by a `Downloader` and by
+    /// `Transaction::download_packages`.
+    ///
+    /// The tracker can be polled, or waited on, from another thread. Like a subscription, it
+    /// only tracks downloads for this handle (for every handle before libalpm 13). Tracking ends
+    /// when the tracker (and all its clones) are dropped.
+    pub fn track_downloads(&self) -> DownloadTracker {
+        let tracker = subscription::track(self.handle as usize);
+        self.set_subscription_callbacks();
+        tracker
+    }
+
+    /// Registers the C callbacks subscriptions need.
+    fn set_subscription_callbacks(&self) {
+        unsafe {
+            // the C callbacks also call any rust callbacks, so only register those that aren't
+            if alpm_option_get_logcb(self.handle).is_none() {
+                set_callback!(alpm_option_set_logcb, self.handle, Some(alpm_cb_log));
+            }
+            if alpm_option_get_dlcb(self.handle).is_none() {
+                set_callback!(alpm_option_set_dlcb, self.handle, Some(alpm_cb_download));
+            }
+            #[cfg(not(alpm_v13))]
+            alpm_option_set_totaldlcb(self.handle, Some(alpm_cb_totaldl));
+            set_callback!(alpm_option_set_eventcb, self.handle, Some(alpm_cb_event));
+            set_callback!(alpm_option_set_progresscb, self.handle, Some(alpm_cb_progress));
+        }
     }
 
     /// Sets the function called when a question needs answering (todo i think)
@@ -463,6 +658,9 @@ impl Alpm {
     }
 
     /// Gets the targeted architecture.
+    ///
+    /// Replaced by `architectures` in libalpm 13.
+    #[cfg(not(alpm_v13))]
     pub fn arch(&self) -> Option<&str> {
         unsafe {
             let arch = alpm_option_get_arch(self.handle);
@@ -476,6 +674,9 @@ impl Alpm {
     }
 
     /// Sets the targeted architecture.
+    ///
+    /// Replaced by `add_architecture` in libalpm 13.
+    #[cfg(not(alpm_v13))]
     pub fn set_arch(&self, arch: &str) -> AlpmResult<()> {
         let arch = CString::new(arch)?;
         let res = unsafe { alpm_option_set_arch(self.handle, arch.as_ptr()) };
@@ -486,12 +687,42 @@ impl Alpm {
         }
     }
 
+    /// Gets the targeted architectures.
+    #[cfg(alpm_v13)]
+    pub fn architectures(&self) -> Vec<&str> {
+        unsafe {
+            let arches = alpm_option_get_architectures(self.handle);
+            util::alpm_list_to_vec(arches, |char_ptr| {
+                CStr::from_ptr(char_ptr as *const c_char).to_str()
+                    .expect("targeted arch is not utf8")
+            })
+        }
+    }
+
+    /// Adds a targeted architecture.
+    #[cfg(alpm_v13)]
+    pub fn add_architecture(&self, arch: &str) -> AlpmResult<()> {
+        let arch = CString::new(arch)?;
+        let res = unsafe { alpm_option_add_architecture(self.handle, arch.as_ptr()) };
+        if res == 0 {
+            Ok(())
+        } else {
+            Err(self.error().unwrap_or(Error::__Unknown))
+        }
+    }
+
     /// Gets the delta ratio
+    ///
+    /// Deltas were removed in libalpm 12.
+    #[cfg(not(alpm_v12))]
     pub fn delta_ratio(&self) -> f64 {
         unsafe { alpm_option_get_deltaratio(self.handle) }
     }
 
-    /// Sets the targeted architecture
+    /// Sets the delta ratio
+    ///
+    /// Deltas were removed in libalpm 12.
+    #[cfg(not(alpm_v12))]
     pub fn set_delta_ratio(&self, r: f64) -> AlpmResult<()> {
         let res = unsafe { alpm_option_set_deltaratio(self.handle, r) };
         if res == 0 {
@@ -501,6 +732,23 @@ impl Alpm {
         }
     }
 
+    /// Gets the number of files libalpm downloads at once
+    #[cfg(alpm_v13)]
+    pub fn parallel_downloads(&self) -> u32 {
+        unsafe { alpm_option_get_parallel_downloads(self.handle) as u32 }
+    }
+
+    /// Sets the number of files libalpm downloads at once (at least 1)
+    #[cfg(alpm_v13)]
+    pub fn set_parallel_downloads(&self, n: u32) -> AlpmResult<()> {
+        let res = unsafe { alpm_option_set_parallel_downloads(self.handle, n) };
+        if res == 0 {
+            Ok(())
+        } else {
+            Err(self.error().unwrap_or(Error::__Unknown))
+        }
+    }
+
     /// Gets whether alpm will check disk space before operations
     pub fn check_space(&self) -> bool {
         unsafe { alpm_option_get_checkspace(self.handle) != 0 }
@@ -667,11 +915,105 @@ impl Alpm {
         }
     }
 
+    /// Removes old packages from the cache directories, keeping those `options` says to.
+    ///
+    /// Packages are grouped by name and architecture across all cache directories, and
+    /// signatures are removed along with their package. Files that aren't packages are left
+    /// alone. With `CleanOptions::dry_run`, nothing is removed, but the report still lists what
+    /// would be, and how much space it would free.
+    pub fn clean_cache(&self, options: &CleanOptions) -> CleanReport {
+        let mut failed = Vec::new();
+        let (packages, _) = cache::scan(&self.cache_dirs(), &mut failed);
+        let local_db = self.local_db();
+        let sync_dbs = self.sync_dbs();
+        let (keep, remove) = cache::select(packages, options.keep, util::vercmp, |package| {
+            local_db.pkg(&package.name)
+                .map_or(false, |pkg| pkg.version().as_ref() == package.version)
+        }, |package| {
+            sync_dbs.iter().any(|db| {
+                db.pkg(&package.name)
+                    .map_or(false, |pkg| pkg.version().as_ref() == package.version)
+            })
+        });
+        let mut report = cache::remove(remove, keep.len(), options.dry_run);
+        failed.extend(report.failed);
+        report.failed = failed;
+        report
+    }
+
+    /// Checks every package file in the cache directories.
+    ///
+    /// Each package is loaded in full with `load_package`, which also verifies its detached
+    /// signature according to the siglevel of the sync database with an entry of the same file
+    /// name (or the remote file siglevel, if there is none). The package is then checked against
+    /// that entry's checksum. Files that aren't packages are reported as orphaned.
+    pub fn audit_cache(&self) -> AuditReport {
+        let mut failed = Vec::new();
+        let (packages, orphaned) = cache::scan(&self.cache_dirs(), &mut failed);
+        let sync_dbs = self.sync_dbs();
+        let entry = |package: &CachedPackage| {
+            let filename = package.path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+            sync_dbs.iter()
+                .filter_map(|db| db.pkg(&package.name).ok().map(|pkg| (db, pkg)))
+                .find(|&(_, pkg)| pkg.filename() == filename)
+        };
+        let load = |package: &CachedPackage| {
+            let level = match entry(package) {
+                Some((db, _)) => db.siglevel(),
+                None => self.remote_file_sign_level(),
+            };
+            // cache paths come from libalpm, so are valid utf-8
+            self.load_package(package.path.to_str().unwrap_or(""), true, level).map(|_| ())
+        };
+        let checksum = |package: &CachedPackage| entry(package).map(|(_, pkg)| {
+            Checksum::new(pkg.sha256(), pkg.md5())
+                .map_or(true, |checksum| checksum.matches(&package.path))
+        });
+        let mut report = cache::audit(packages, load, checksum);
+        report.orphaned = orphaned;
+        report.failed = failed;
+        report
+    }
+
+    /// Finds the packages installed as dependencies that no installed package requires or
+    /// optionally requires, like `pacman -Qdt`, along with those only needed by them, and so on,
+    /// so that they can all be removed together.
+    ///
+    /// With `ignore_optional`, packages only optionally required are included too (like
+    /// `pacman -Qdtt`).
+    pub fn orphans<'a>(&'a self, ignore_optional: bool) -> Vec<&'a PackageRef> {
+        let local_db = self.local_db();
+        let pkgs = local_db.pkg_cache();
+        let installed: Vec<_> = pkgs.iter().map(|pkg| orphans::Installed {
+            name: pkg.name(),
+            depend: pkg.reason() == Reason::Depend,
+            required_by: pkg.compute_required_by(),
+            optional_for: if ignore_optional { Vec::new() } else { pkg.compute_optional_for() },
+        }).collect();
+        orphans::find(&installed, ignore_optional).iter()
+            .filter_map(|name| local_db.pkg(name).ok())
+            .collect()
+    }
+
+    /// Starts a transaction removing the packages found by `orphans`, ready to be prepared and
+    /// committed.
+    pub fn remove_orphans<'a>(&'a self, ignore_optional: bool, flags: TransactionFlags)
+        -> AlpmResult<Transaction<'a, Initialized>>
+    {
+        let orphans = self.orphans(ignore_optional);
+        let trans = self.init_transaction(flags)?;
+        for pkg in orphans {
+            trans.remove_package(pkg)?;
+        }
+        Ok(trans)
+    }
+
 }
 
 impl Drop for Alpm {
     // deletes the lockfile, amongst other things.
     fn drop(&mut self) {
+        download::unregister(self.handle as usize);
         unsafe { alpm_release(self.handle); }
     }
 }
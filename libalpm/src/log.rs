@@ -5,6 +5,9 @@ use std::fmt;
 use std::cmp::{self, Ordering};
 
 use alpm_sys::*;
+use printf;
+
+pub use printf::Arg as LogArg;
 
 /// The highest log level marked true
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -155,3 +158,60 @@ impl Into<LogLevel> for LogLevels {
     }
 }
 
+/// A log message as libalpm sent it: the printf format string and its arguments, before
+/// formatting.
+///
+/// Messages from the same place in libalpm share a format (e.g. `"could not open file %s: %s\n"`),
+/// so they can be grouped by it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogRecord {
+    pub level: LogLevels,
+    /// The format string (including the trailing newline libalpm adds to most messages).
+    pub format: String,
+    /// The arguments, in the order the format uses them (`*` widths included).
+    pub args: Vec<LogArg>,
+}
+
+impl LogRecord {
+    /// The formatted message, as a log callback would receive it.
+    pub fn message(&self) -> String {
+        printf::format(self.format.as_bytes(), &self.args)
+    }
+}
+
+#[cfg(feature = "log")]
+impl Into<::log_facade::Level> for LogLevel {
+    fn into(self) -> ::log_facade::Level {
+        use log_facade::Level;
+
+        match self {
+            LogLevel::Error => Level::Error,
+            LogLevel::Warning => Level::Warn,
+            LogLevel::Debug => Level::Debug,
+            LogLevel::Function | LogLevel::None => Level::Trace,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "log"))]
+#[test]
+fn test_log_facade_level() {
+    use log_facade::Level;
+
+    let level: LogLevel = LogLevels::warning().into();
+    assert_eq!(Into::<Level>::into(level), Level::Warn);
+    let level: LogLevel = LogLevels::function().into();
+    assert_eq!(Into::<Level>::into(level), Level::Trace);
+}
+
+#[test]
+fn test_log_record_message() {
+    let record = LogRecord {
+        level: LogLevels::error(),
+        format: "could not open file %s: %s\n".into(),
+        args: vec![LogArg::Str(Some(b"/etc/pacman.conf".to_vec())),
+                   LogArg::Str(Some(b"No such file or directory".to_vec()))],
+    };
+    assert_eq!(record.message(),
+               "could not open file /etc/pacman.conf: No such file or directory\n");
+}
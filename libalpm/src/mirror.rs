@@ -0,0 +1,365 @@
+//! Retrying downloads and keeping track of which mirrors work.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+use download::{Downloader, DownloadError, DownloadProgress, DownloadStatus};
+
+/// How often, and how patiently, to retry a mirror.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    retries: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+    timeout: Option<Duration>,
+    demote_after: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            retries: 2,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            timeout: None,
+            demote_after: 3,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Creates the default policy: 2 retries per mirror, waiting 1s then 2s, and demoting a
+    /// mirror after 3 failures in a row.
+    pub fn new() -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// Sets how many times to retry a mirror after a failure that might be temporary.
+    pub fn retries(mut self, retries: u32) -> RetryPolicy {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets how long to wait before the first retry. The wait doubles for each further retry,
+    /// up to `max_backoff`.
+    pub fn backoff(mut self, backoff: Duration) -> RetryPolicy {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sets the longest wait between retries.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> RetryPolicy {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Sets how long to keep trying one mirror for one file before giving up on it. This includes
+    /// the download in progress if the downloader supports deadlines (`HttpDownloader` does).
+    pub fn timeout(mut self, timeout: Duration) -> RetryPolicy {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how many failures in a row demote a mirror. Demoted mirrors are not retried, and are
+    /// tried after the others, until they next succeed.
+    pub fn demote_after(mut self, failures: u32) -> RetryPolicy {
+        self.demote_after = cmp::max(failures, 1);
+        self
+    }
+
+    /// The wait before retry number `retry` (starting at 0).
+    fn wait(&self, retry: u32) -> Duration {
+        let factor = 1u32.checked_shl(retry).unwrap_or(u32::max_value());
+        self.backoff.checked_mul(factor).map_or(self.max_backoff, |wait| {
+            cmp::min(wait, self.max_backoff)
+        })
+    }
+}
+
+/// What happened to the downloads from one mirror.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MirrorStats {
+    /// Download attempts, including retries.
+    pub attempts: u32,
+    /// Files downloaded successfully (or found to be up to date).
+    pub successes: u32,
+    /// Attempts that failed.
+    pub failures: u32,
+    /// Failures since the last success.
+    pub consecutive_failures: u32,
+    /// Whether the mirror is currently demoted.
+    pub demoted: bool,
+    /// The time spent on attempts.
+    pub time: Duration,
+}
+
+/// The mirrors used for downloads, with their statistics, shared by every clone.
+///
+/// Mirrors are identified by the url of the directory the files are downloaded from.
+#[derive(Debug, Clone, Default)]
+pub struct Mirrors {
+    policy: RetryPolicy,
+    stats: Arc<Mutex<HashMap<String, MirrorStats>>>,
+}
+
+impl Mirrors {
+    /// Creates an empty set of mirrors which will be retried according to `policy`.
+    pub fn new(policy: RetryPolicy) -> Mirrors {
+        Mirrors {
+            policy: policy,
+            stats: Default::default(),
+        }
+    }
+
+    /// The policy mirrors are retried with.
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Gets the statistics for every mirror used so far, sorted by url.
+    pub fn stats(&self) -> Vec<(String, MirrorStats)> {
+        let mut stats: Vec<_> = self.stats.lock().unwrap()
+            .iter()
+            .map(|(mirror, stats)| (mirror.clone(), stats.clone()))
+            .collect();
+        stats.sort_by(|a, b| a.0.cmp(&b.0));
+        stats
+    }
+
+    /// Whether a mirror is demoted.
+    pub fn is_demoted(&self, mirror: &str) -> bool {
+        self.stats.lock().unwrap().get(mirror).map_or(false, |stats| stats.demoted)
+    }
+
+    /// Orders urls so that those from demoted mirrors come last, otherwise keeping their order.
+    pub fn order<S: AsRef<str>>(&self, urls: &[S]) -> Vec<String> {
+        let mut urls: Vec<String> = urls.iter().map(|url| url.as_ref().to_owned()).collect();
+        urls.sort_by_key(|url| self.is_demoted(mirror_of(url)));
+        urls
+    }
+
+    /// Records an attempt to download from a mirror.
+    pub(crate) fn record(&self, mirror: &str, success: bool, time: Duration) {
+        let mut all = self.stats.lock().unwrap();
+        let stats = all.entry(mirror.to_owned()).or_insert_with(MirrorStats::default);
+        stats.attempts += 1;
+        stats.time += time;
+        if success {
+            stats.successes += 1;
+            stats.consecutive_failures = 0;
+            stats.demoted = false;
+        } else {
+            stats.failures += 1;
+            stats.consecutive_failures += 1;
+            if stats.consecutive_failures >= self.policy.demote_after {
+                stats.demoted = true;
+            }
+        }
+    }
+
+    /// Turns the last success of a mirror into a failure, for a file that turned out to be bad.
+    pub(crate) fn reject(&self, mirror: &str) {
+        let mut all = self.stats.lock().unwrap();
+        let stats = all.entry(mirror.to_owned()).or_insert_with(MirrorStats::default);
+        stats.successes = stats.successes.saturating_sub(1);
+        stats.failures += 1;
+        stats.consecutive_failures += 1;
+        if stats.consecutive_failures >= self.policy.demote_after {
+            stats.demoted = true;
+        }
+    }
+}
+
+/// Gets the mirror (the directory) a url downloads from.
+pub(crate) fn mirror_of(url: &str) -> &str {
+    match url.rfind('/') {
+        Some(slash) => &url[..slash],
+        None => url,
+    }
+}
+
+/// Whether an error might go away if the download is tried again.
+fn is_temporary(e: &DownloadError) -> bool {
+    match *e {
+        DownloadError::Transfer(_) => true,
+        DownloadError::Http(code) => code >= 500 || code == 408 || code == 429,
+        _ => false,
+    }
+}
+
+/// Wraps a downloader to retry temporary failures with exponential backoff, and records how
+/// each mirror does in `Mirrors`.
+///
+/// Clones share the same `Mirrors`, so this can be used with `Transaction::download_packages`,
+/// as well as set on a handle (libalpm then tries each server in turn until one succeeds).
+///
+/// Only `Transaction::download_packages` tries demoted mirrors last. When set on a handle,
+/// libalpm tries the servers in database order, so a demoted mirror is still tried in its turn,
+/// just without retries.
+#[derive(Debug, Clone)]
+pub struct RetryingDownloader<D> {
+    inner: D,
+    mirrors: Mirrors,
+}
+
+impl<D: Downloader> RetryingDownloader<D> {
+    /// Wraps `inner`, retrying according to the policy of `mirrors`.
+    pub fn new(inner: D, mirrors: Mirrors) -> RetryingDownloader<D> {
+        RetryingDownloader {
+            inner: inner,
+            mirrors: mirrors,
+        }
+    }
+
+    /// The mirrors this downloader records to.
+    pub fn mirrors(&self) -> &Mirrors {
+        &self.mirrors
+    }
+}
+
+impl<D: Downloader> Downloader for RetryingDownloader<D> {
+    fn download(&mut self,
+                url: &Url,
+                dest: &Path,
+                force: bool,
+                progress: &mut DownloadProgress)
+                -> Result<DownloadStatus, DownloadError>
+    {
+        let mirror = mirror_of(url.as_str());
+        // demoted mirrors get one chance, so the next mirror is tried quickly
+        let retries = if self.mirrors.is_demoted(mirror) { 0 } else { self.mirrors.policy.retries };
+        let deadline = self.mirrors.policy.timeout.map(|timeout| Instant::now() + timeout);
+        self.inner.set_deadline(deadline);
+        let mut retry = 0;
+        loop {
+            let attempt = Instant::now();
+            let result = self.inner.download(url, dest, force, progress);
+            self.mirrors.record(mirror, result.is_ok(), attempt.elapsed());
+            match result {
+                Err(ref e) if is_temporary(e) && retry < retries => (),
+                result => return result,
+            }
+            let wait = self.mirrors.policy.wait(retry);
+            if let Some(deadline) = deadline {
+                if Instant::now() + wait >= deadline {
+                    return result;
+                }
+            }
+            thread::sleep(wait);
+            retry += 1;
+        }
+    }
+}
+
+#[test]
+fn test_retry_policy() {
+    let policy = RetryPolicy::new().backoff(Duration::from_millis(100))
+        .max_backoff(Duration::from_millis(300));
+    assert_eq!(policy.wait(0), Duration::from_millis(100));
+    assert_eq!(policy.wait(1), Duration::from_millis(200));
+    assert_eq!(policy.wait(2), Duration::from_millis(300));
+    assert_eq!(policy.wait(40), Duration::from_millis(300));
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_mirror_failover() {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::fs;
+    use download::{HttpDownloader, DownloadOptions, Job, download_parallel, temp_dir};
+
+    // "down" always fails, "flaky" fails every other request, "up" always works
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let flaky = AtomicUsize::new(0);
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = [0; 4096];
+            let n = stream.read(&mut request).unwrap();
+            let request = String::from_utf8_lossy(&request[..n]).into_owned();
+            let path = request.split_whitespace().nth(1).unwrap_or("").to_owned();
+            let fail = path.starts_with("/down/")
+                || path.starts_with("/flaky/") && flaky.fetch_add(1, Ordering::SeqCst) % 2 == 0;
+            let response = if fail {
+                "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n".to_owned()
+            } else {
+                format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", path.len(), path)
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    let dest = temp_dir("mirrors");
+    let base = format!("http://127.0.0.1:{}", port);
+    let jobs = (0..6).map(|n| {
+        let filename = format!("p{}-1-1-any.pkg.tar.xz", n);
+        Job {
+            urls: vec![format!("{}/down/{}", base, filename),
+                       format!("{}/flaky/{}", base, filename),
+                       format!("{}/up/{}", base, filename)],
+            checksum: None,
+            filename: filename,
+        }
+    }).collect();
+
+    let policy = RetryPolicy::new().retries(1).backoff(Duration::from_millis(1)).demote_after(2);
+    let mirrors = Mirrors::new(policy);
+    let downloader = RetryingDownloader::new(HttpDownloader::new(), mirrors.clone());
+    // one at a time, so the order of requests to the flaky mirror is known
+    let options = DownloadOptions::new().parallel(1).mirrors(mirrors.clone());
+    let report = download_parallel(None, jobs, &dest, &downloader, &options, |_, _| true);
+    assert!(report.is_ok());
+    assert_eq!(report.downloaded.len(), 6);
+
+    let stats = mirrors.stats();
+    assert_eq!(stats.len(), 2);
+    let (ref down, ref down_stats) = stats[0];
+    assert_eq!(down, &format!("{}/down", base));
+    // two attempts for the first file demote it, then it is only tried after the others
+    assert_eq!(down_stats.attempts, 2);
+    assert_eq!(down_stats.successes, 0);
+    assert!(down_stats.demoted);
+    let (ref flaky, ref flaky_stats) = stats[1];
+    assert_eq!(flaky, &format!("{}/flaky", base));
+    // every file fails once, then succeeds on the retry
+    assert_eq!(flaky_stats.successes, 6);
+    assert_eq!(flaky_stats.failures, 6);
+    assert!(!flaky_stats.demoted);
+    fs::remove_dir_all(&dest).unwrap();
+}
+
+#[cfg(feature = "http")]
+#[test]
+fn test_retry_timeout() {
+    use std::net::TcpListener;
+    use download::{HttpDownloader, temp_dir};
+
+    // accepts connections but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        let streams: Vec<_> = listener.incoming().take(10).collect();
+        thread::sleep(Duration::from_secs(30));
+        drop(streams);
+    });
+
+    let dest = temp_dir("retry-timeout");
+    let url = Url::parse(&format!("http://127.0.0.1:{}/a-1-1-any.pkg.tar.xz", port)).unwrap();
+    let policy = RetryPolicy::new().backoff(Duration::from_millis(1))
+        .timeout(Duration::from_millis(500));
+    let mut downloader = RetryingDownloader::new(HttpDownloader::new(), Mirrors::new(policy));
+    let mut progress = DownloadProgress::new(None, "a-1-1-any.pkg.tar.xz");
+    let start = Instant::now();
+    assert!(downloader.download(&url, &dest, true, &mut progress).is_err());
+    assert!(start.elapsed() < Duration::from_secs(5));
+    ::std::fs::remove_dir_all(&dest).unwrap();
+}
@@ -17,9 +17,14 @@ pub struct Config {
     pub hook_dirs: Vec<String>,
     pub hold_pkg: Vec<String>,
     pub transfer_command: Option<String>,
-    //pub clean_method: TODO,
+    /// Which packages to keep when cleaning the cache.
+    pub clean_method: CleanMethod,
     pub use_delta: f32,
-    pub arch: String,
+    /// The architectures packages can be installed for. Empty means `auto` (the machine
+    /// architecture).
+    ///
+    /// Like the C library, only the first `Architecture` line in the file is used.
+    pub arch: Vec<String>,
     pub ignore_pkg: Vec<String>,
     pub ignore_group: Vec<String>,
     pub no_upgrade: Vec<String>,
@@ -27,8 +32,18 @@ pub struct Config {
     pub use_syslog: bool,
     pub color: bool,
     pub total_download: bool,
+    /// Whether to check for enough disk space before installing. On by default, but off when
+    /// parsing a pacman.conf unless `CheckSpace` is set.
     pub check_space: bool,
     pub verbose_pkg_lists: bool,
+    /// The number of packages to download at once.
+    pub parallel_downloads: u32,
+    /// Disable the low speed limit and timeout on downloads.
+    pub disable_download_timeout: bool,
+    /// Hide progress bars.
+    pub no_progress_bar: bool,
+    /// The user to drop privileges to when downloading.
+    pub download_user: Option<String>,
     //pub sig_level: TODO,
     //pub local_files_sig_level: TODO,
     //pub remote_files_sig_level: TODO,
@@ -46,9 +61,9 @@ impl Default for Config {
             hook_dirs: vec!["/etc/pacman.d/hooks/".into()],
             hold_pkg: vec![],
             transfer_command: None,
-            //clean_method
+            clean_method: CleanMethod::default(),
             use_delta: 0.7,
-            arch: "auto".into(),
+            arch: vec![],
             ignore_pkg: vec![],
             ignore_group: vec![],
             no_upgrade: vec![],
@@ -58,6 +73,10 @@ impl Default for Config {
             total_download: false,
             check_space: true,
             verbose_pkg_lists: false,
+            parallel_downloads: 1,
+            disable_download_timeout: false,
+            no_progress_bar: false,
+            download_user: None,
             //sig_level: TODO,
             //local_files_sig_level: TODO,
             //remote_files_sig_level: TODO,
@@ -67,6 +86,34 @@ impl Default for Config {
 
 }
 
+/// The packages kept when cleaning the package cache (`CleanMethod` in pacman.conf).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CleanMethod {
+    /// Keep packages that are still installed (in the local database).
+    pub keep_installed: bool,
+    /// Keep packages that are still available in a sync database.
+    pub keep_current: bool,
+}
+
+impl CleanMethod {
+    /// Keep installed packages
+    pub fn keep_installed() -> CleanMethod {
+        CleanMethod { keep_installed: true, keep_current: false }
+    }
+
+    /// Keep packages in the sync databases
+    pub fn keep_current() -> CleanMethod {
+        CleanMethod { keep_installed: false, keep_current: true }
+    }
+}
+
+impl Default for CleanMethod {
+    /// The pacman default is `KeepInstalled`
+    fn default() -> CleanMethod {
+        CleanMethod::keep_installed()
+    }
+}
+
 /// Config for a repository.
 #[derive(Debug, Default)]
 pub struct RepoConfig {
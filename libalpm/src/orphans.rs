@@ -0,0 +1,77 @@
+//! Finding packages installed as dependencies that nothing needs any more.
+
+use std::collections::HashSet;
+
+/// What is needed to decide whether an installed package is an orphan.
+pub(crate) struct Installed<'a> {
+    pub(crate) name: &'a str,
+    /// Whether the package was installed as a dependency.
+    pub(crate) depend: bool,
+    pub(crate) required_by: Vec<String>,
+    pub(crate) optional_for: Vec<String>,
+}
+
+/// Gets the names of the packages that could be removed together, in the order given: those
+/// installed as dependencies, and only required (or optionally required, unless
+/// `ignore_optional`) by each other.
+///
+/// Every dependency starts as a candidate, and candidates needed by a package that isn't one are
+/// dropped until none are, so dependency cycles that nothing else needs are found too.
+pub(crate) fn find(installed: &[Installed], ignore_optional: bool) -> Vec<String> {
+    let mut candidates: HashSet<&str> = installed.iter()
+        .filter(|pkg| pkg.depend)
+        .map(|pkg| pkg.name)
+        .collect();
+    loop {
+        let needed: Vec<&str> = installed.iter()
+            .filter(|pkg| candidates.contains(pkg.name))
+            .filter(|pkg| {
+                let optional = if ignore_optional { &[][..] } else { &pkg.optional_for[..] };
+                pkg.required_by.iter()
+                    .chain(optional)
+                    .any(|needer| !candidates.contains(needer.as_str()))
+            })
+            .map(|pkg| pkg.name)
+            .collect();
+        if needed.is_empty() {
+            break;
+        }
+        for name in needed {
+            candidates.remove(name);
+        }
+    }
+    installed.iter()
+        .filter(|pkg| candidates.contains(pkg.name))
+        .map(|pkg| pkg.name.to_owned())
+        .collect()
+}
+
+#[test]
+fn test_find_orphans() {
+    let pkg = |name, depend, required_by: &[&str], optional_for: &[&str]| Installed {
+        name: name,
+        depend: depend,
+        required_by: required_by.iter().map(|s| s.to_string()).collect(),
+        optional_for: optional_for.iter().map(|s| s.to_string()).collect(),
+    };
+    let installed = vec![
+        // an explicit package with a dependency, which is needed
+        pkg("app", false, &[], &[]),
+        pkg("lib", true, &["app"], &[]),
+        // a chain of dependencies left behind when their user was removed
+        pkg("left", true, &[], &[]),
+        pkg("left-lib", true, &["left"], &[]),
+        pkg("left-lib-lib", true, &["left-lib", "left"], &[]),
+        // a cycle only needed by itself
+        pkg("cycle-a", true, &["cycle-b"], &[]),
+        pkg("cycle-b", true, &["cycle-a"], &[]),
+        // an optional dependency of an explicit package
+        pkg("extra", true, &[], &["app"]),
+        // an explicit package nothing needs is not an orphan
+        pkg("tool", false, &[], &[]),
+    ];
+    assert_eq!(find(&installed, false),
+               vec!["left", "left-lib", "left-lib-lib", "cycle-a", "cycle-b"]);
+    assert_eq!(find(&installed, true),
+               vec!["left", "left-lib", "left-lib-lib", "cycle-a", "cycle-b", "extra"]);
+}
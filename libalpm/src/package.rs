@@ -262,6 +262,20 @@ impl PackageRef {
         }
     }
 
+    /// Gets the extended data (`xdata` entries in .PKGINFO) for this package as name/value
+    /// pairs.
+    #[cfg(alpm_v14)]
+    pub fn xdata(&self) -> Vec<(&str, &str)> {
+        unsafe {
+            let xdata = alpm_pkg_get_xdata(self as *const _ as _);
+            util::alpm_list_to_vec(xdata, |data| {
+                let data = &*(data as *const alpm_pkg_xdata_t);
+                (CStr::from_ptr(data.name).to_str().unwrap(),
+                 CStr::from_ptr(data.value).to_str().unwrap())
+            })
+        }
+    }
+
     /// Gets the groups this package belongs to.
     pub fn groups(&self) -> Vec<&str> {
         unsafe {
@@ -335,6 +349,9 @@ impl PackageRef {
     }
 
     /// Gets the available deltas for this package.
+    ///
+    /// Deltas were removed in libalpm 12.
+    #[cfg(not(alpm_v12))]
     pub fn deltas(&self) -> Vec<&str> {
         unsafe {
             let deltas = alpm_pkg_get_deltas(self as *const _ as _);
@@ -421,6 +438,7 @@ impl PackageRef {
     ///
     /// TODO I'm guessing the return type as it's not in the alpm.h docs. I'm setting it to match
     /// `deltas`. check for segfaults.
+    #[cfg(not(alpm_v12))]
     pub fn unused_deltas(&self) -> Vec<&str> {
         unsafe {
             let deltas = alpm_pkg_get_licenses(self as *const _ as _);
@@ -507,6 +525,75 @@ impl<'a> PackageOperation<'a> {
     }
 }
 
+/// The name and version of a package, owned so it can outlive the package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageRecord {
+    pub name: String,
+    pub version: String,
+}
+
+impl<'a> From<&'a PackageRef> for PackageRecord {
+    fn from(pkg: &'a PackageRef) -> PackageRecord {
+        PackageRecord {
+            name: pkg.name().to_owned(),
+            version: pkg.version().as_ref().to_owned(),
+        }
+    }
+}
+
+/// An owned copy of a `PackageOperation`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PackageOperationRecord {
+    /// Package (to be) installed. (No oldpkg)
+    Install {
+        new_pkg: PackageRecord,
+    },
+    /// Package (to be) upgraded
+    Upgrade {
+        old_pkg: PackageRecord,
+        new_pkg: PackageRecord,
+    },
+    /// Package (to be) re-installed.
+    Reinstall {
+        old_pkg: PackageRecord,
+        new_pkg: PackageRecord,
+    },
+    /// Package (to be) downgraded.
+    Downgrade {
+        old_pkg: PackageRecord,
+        new_pkg: PackageRecord,
+    },
+    /// Package (to be) removed. (No newpkg)
+    Remove {
+        old_pkg: PackageRecord,
+    },
+}
+
+impl<'a, 'b> From<&'b PackageOperation<'a>> for PackageOperationRecord {
+    fn from(op: &'b PackageOperation<'a>) -> PackageOperationRecord {
+        match *op {
+            PackageOperation::Install { new_pkg } => PackageOperationRecord::Install {
+                new_pkg: new_pkg.into(),
+            },
+            PackageOperation::Upgrade { old_pkg, new_pkg } => PackageOperationRecord::Upgrade {
+                old_pkg: old_pkg.into(),
+                new_pkg: new_pkg.into(),
+            },
+            PackageOperation::Reinstall { old_pkg, new_pkg } => PackageOperationRecord::Reinstall {
+                old_pkg: old_pkg.into(),
+                new_pkg: new_pkg.into(),
+            },
+            PackageOperation::Downgrade { old_pkg, new_pkg } => PackageOperationRecord::Downgrade {
+                old_pkg: old_pkg.into(),
+                new_pkg: new_pkg.into(),
+            },
+            PackageOperation::Remove { old_pkg } => PackageOperationRecord::Remove {
+                old_pkg: old_pkg.into(),
+            },
+        }
+    }
+}
+
 /// A group of related pacakges in a database
 pub struct Group<'a> {
     pub name: &'a str,
@@ -0,0 +1,256 @@
+//! Receiving what libalpm reports over a channel, rather than through callbacks.
+//!
+//! Every subscriber gets its own bounded channel. Records are sent in the order libalpm reports
+//! them, and none are dropped: when a subscriber's buffer is full, the thread publishing waits
+//! until the subscriber catches up (subscribing and unsubscribing still work in the meantime).
+//!
+//! Download trackers are subscribers too, but are updated directly instead of through a channel.
+//!
+//! Subscribers only get records from the handle they subscribed on. Before libalpm 13 the C
+//! callbacks can't tell which handle they are called for, so what they report goes to every
+//! subscriber.
+
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+
+use alpm_sys::*;
+
+use event::EventRecord;
+use log::LogRecord;
+use tracker::{self, DownloadTracker};
+use util::lock;
+
+/// The number of records buffered for each subscriber by `Alpm::subscribe`.
+pub(crate) const DEFAULT_CAPACITY: usize = 256;
+
+lazy_static! {
+    static ref SUBSCRIBERS: Mutex<Vec<Arc<Subscription>>> = Default::default();
+    /// Held while publishing, so that all subscribers see records in the same order.
+    static ref PUBLISHING: Mutex<()> = Default::default();
+}
+
+/// A subscriber, and the handle it gets records from.
+struct Subscription {
+    handle: usize,
+    subscriber: Subscriber,
+}
+
+/// Where records are sent.
+enum Subscriber {
+    Channel(SyncSender<Record>),
+    /// Trackers are updated directly, until they are dropped.
+    Tracker(Weak<tracker::Shared>),
+}
+
+impl Subscriber {
+    /// Passes on a record, returning false if the subscriber has gone.
+    fn send(&self, record: &Record) -> bool {
+        match *self {
+            Subscriber::Channel(ref sender) => sender.send(record.clone()).is_ok(),
+            Subscriber::Tracker(ref tracker) => match tracker.upgrade() {
+                Some(tracker) => {
+                    tracker.update(record);
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+}
+
+/// Something reported by libalpm, as received from `Alpm::subscribe`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Record {
+    /// An event (see `Event`).
+    Event(EventRecord),
+    /// A log message.
+    Log(LogRecord),
+    /// Progress downloading a file.
+    Download {
+        filename: String,
+        downloaded: u64,
+        total: u64,
+    },
+    /// A file has been downloaded, or could not be.
+    DownloadDone {
+        filename: String,
+        success: bool,
+    },
+    /// The total number of bytes that will be downloaded.
+    TotalDownload(u64),
+    /// Progress of a transaction step, for one package.
+    Progress {
+        kind: ProgressKind,
+        /// The package being processed (empty for steps covering all packages).
+        package: String,
+        /// How far through this package the step is, out of 100.
+        percent: u8,
+        /// The number of packages the step will process.
+        howmany: usize,
+        /// The position of this package among them, starting at 1.
+        current: usize,
+    },
+}
+
+/// The transaction step a `Record::Progress` is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressKind {
+    AddStart,
+    UpgradeStart,
+    DowngradeStart,
+    ReinstallStart,
+    RemoveStart,
+    ConflictsStart,
+    DiskspaceStart,
+    IntegrityStart,
+    LoadStart,
+    KeyringStart,
+    __Unknown,
+}
+
+impl From<alpm_progress_t> for ProgressKind {
+    fn from(from: alpm_progress_t) -> ProgressKind {
+        match from {
+            ALPM_PROGRESS_ADD_START => ProgressKind::AddStart,
+            ALPM_PROGRESS_UPGRADE_START => ProgressKind::UpgradeStart,
+            ALPM_PROGRESS_DOWNGRADE_START => ProgressKind::DowngradeStart,
+            ALPM_PROGRESS_REINSTALL_START => ProgressKind::ReinstallStart,
+            ALPM_PROGRESS_REMOVE_START => ProgressKind::RemoveStart,
+            ALPM_PROGRESS_CONFLICTS_START => ProgressKind::ConflictsStart,
+            ALPM_PROGRESS_DISKSPACE_START => ProgressKind::DiskspaceStart,
+            ALPM_PROGRESS_INTEGRITY_START => ProgressKind::IntegrityStart,
+            ALPM_PROGRESS_LOAD_START => ProgressKind::LoadStart,
+            ALPM_PROGRESS_KEYRING_START => ProgressKind::KeyringStart,
+            _ => ProgressKind::__Unknown,
+        }
+    }
+}
+
+/// Adds a subscriber to `handle` that buffers up to `capacity` records.
+pub(crate) fn subscribe(handle: usize, capacity: usize) -> Receiver<Record> {
+    let (sender, receiver) = mpsc::sync_channel(capacity);
+    add(handle, Subscriber::Channel(sender));
+    receiver
+}
+
+/// Adds a subscriber to `handle` that updates a new tracker.
+pub(crate) fn track(handle: usize) -> DownloadTracker {
+    let tracker = DownloadTracker::new();
+    add(handle, Subscriber::Tracker(Arc::downgrade(tracker.shared())));
+    tracker
+}
+
+fn add(handle: usize, subscriber: Subscriber) {
+    lock(&SUBSCRIBERS).push(Arc::new(Subscription {
+        handle: handle,
+        subscriber: subscriber,
+    }));
+}
+
+/// The subscriptions that records from `handle` (or an unknown handle) go to.
+fn subscriptions(handle: Option<usize>) -> Vec<Arc<Subscription>> {
+    lock(&SUBSCRIBERS).iter()
+        .filter(|subscription| handle.map_or(true, |handle| subscription.handle == handle))
+        .cloned()
+        .collect()
+}
+
+/// Whether anyone is subscribed to `handle`, in which case its C callbacks must stay registered.
+pub(crate) fn is_active(handle: usize) -> bool {
+    lock(&SUBSCRIBERS).iter().any(|subscription| subscription.handle == handle)
+}
+
+/// Sends a record from `handle` (`None` if not known) to its subscribers, blocking while any of
+/// their buffers are full. Subscribers that have dropped their receiver (or tracker) are
+/// removed.
+///
+/// The record is only built if there are subscribers, and before anything is locked, so a panic
+/// building it doesn't affect later records. The subscribers are copied out before sending, so a
+/// full buffer doesn't hold up `subscribe` or `is_active`.
+pub(crate) fn publish<F>(handle: Option<usize>, record: F)
+    where F: FnOnce() -> Record
+{
+    if subscriptions(handle).is_empty() {
+        return;
+    }
+    let record = record();
+    let _publishing = lock(&PUBLISHING);
+    let gone: Vec<_> = subscriptions(handle).into_iter()
+        .filter(|subscription| !subscription.subscriber.send(&record))
+        .collect();
+    if !gone.is_empty() {
+        lock(&SUBSCRIBERS)
+            .retain(|subscription| !gone.iter().any(|gone| Arc::ptr_eq(subscription, gone)));
+    }
+}
+
+#[test]
+fn test_publish() {
+    use std::panic;
+    use std::thread;
+    use std::time::Duration;
+
+    // handles no real one can have, so other tests don't interfere
+    let (handle, other) = (1, 2);
+    let totals = |receiver: &Receiver<Record>, n| receiver.iter().filter_map(|record| {
+        match record {
+            Record::TotalDownload(n) => Some(n),
+            _ => None,
+        }
+    }).take(n).collect::<Vec<_>>();
+
+    // nothing is built without subscribers
+    publish(Some(handle), || panic!("no subscribers"));
+
+    // a small buffer slows publishing down, but nothing is lost
+    let readers: Vec<_> = [1, DEFAULT_CAPACITY].iter().map(|&capacity| {
+        let receiver = subscribe(handle, capacity);
+        thread::spawn(move || totals(&receiver, 1000))
+    }).collect();
+    assert!(is_active(handle));
+    assert!(!is_active(other));
+    for n in 0..1000 {
+        publish(Some(handle), || Record::TotalDownload(n));
+    }
+    for reader in readers {
+        assert_eq!(reader.join().unwrap(), (0..1000).collect::<Vec<_>>());
+    }
+
+    // records only go to subscribers of the handle they come from, unless it isn't known
+    let mine = subscribe(handle, DEFAULT_CAPACITY);
+    let theirs = subscribe(other, DEFAULT_CAPACITY);
+    publish(Some(other), || Record::TotalDownload(1));
+    publish(None, || Record::TotalDownload(2));
+    publish(Some(handle), || Record::TotalDownload(3));
+    assert_eq!(totals(&mine, 2), [2, 3]);
+    assert_eq!(totals(&theirs, 2), [1, 2]);
+
+    // a panic building a record doesn't stop later records
+    let result = panic::catch_unwind(|| {
+        publish(Some(handle), || panic!("building the record"));
+    });
+    assert!(result.is_err());
+    publish(Some(handle), || Record::TotalDownload(4));
+    assert_eq!(totals(&mine, 1), [4]);
+    drop((mine, theirs));
+
+    // a subscriber that isn't reading holds up publishing, but not subscribing
+    let stalled = subscribe(handle, 1);
+    let publisher = thread::spawn(move || for n in 0..3 {
+        publish(Some(handle), || Record::TotalDownload(n));
+    });
+    thread::sleep(Duration::from_millis(100));
+    let (done, subscribed) = mpsc::channel();
+    thread::spawn(move || done.send(subscribe(handle, DEFAULT_CAPACITY)).unwrap());
+    let late = subscribed.recv_timeout(Duration::from_secs(5)).expect("subscribe blocked");
+    assert!(is_active(handle));
+    assert_eq!(totals(&stalled, 3), [0, 1, 2]);
+    publisher.join().unwrap();
+    drop((stalled, late));
+
+    // the receivers have been dropped, so the next publish removes them
+    publish(Some(handle), || Record::TotalDownload(0));
+    publish(Some(other), || Record::TotalDownload(0));
+    assert!(!is_active(handle));
+    assert!(!is_active(other));
+}
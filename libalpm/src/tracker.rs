@@ -0,0 +1,249 @@
+//! Following all downloads in one place, rather than correlating the download callbacks.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use subscription::Record;
+
+/// How many seconds back the transfer rate is averaged over.
+const RATE_WINDOW: u64 = 5;
+
+/// The combined progress of the files being downloaded, as returned by `Alpm::track_downloads`.
+///
+/// Clones share the same state. The tracker is updated by the thread running libalpm, and can be
+/// polled with `state` or waited on with `wait` from any other thread. Dropping every clone stops
+/// the tracking.
+#[derive(Debug, Clone)]
+pub struct DownloadTracker {
+    shared: Arc<Shared>,
+}
+
+/// The state behind a `DownloadTracker`, also held (weakly) by the subscriptions.
+#[derive(Debug, Default)]
+pub(crate) struct Shared {
+    tracked: Mutex<Tracked>,
+    changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Tracked {
+    state: DownloadState,
+    /// The total announced by libalpm, if any.
+    announced: Option<u64>,
+    /// Recent (time, bytes downloaded) samples, for the transfer rate.
+    samples: VecDeque<(Instant, u64)>,
+}
+
+/// A snapshot of a `DownloadTracker`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DownloadState {
+    /// The files reported so far, in the order they were first reported.
+    pub files: Vec<FileProgress>,
+    /// The bytes downloaded so far, over all files.
+    pub downloaded: u64,
+    /// The bytes to download, as announced by libalpm, or else the total of the files reported
+    /// so far (0 if unknown).
+    pub total: u64,
+    /// The transfer rate in bytes per second, averaged over the last few seconds.
+    pub rate: f64,
+    /// How long the rest will take at the current rate, if the total is known.
+    pub eta: Option<Duration>,
+    /// The number of files downloaded successfully.
+    pub completed: usize,
+    /// The number of files that could not be downloaded.
+    pub failed: usize,
+    /// The number of times the state has changed, for `DownloadTracker::wait`.
+    pub updates: u64,
+}
+
+/// How one file is getting on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileProgress {
+    pub filename: String,
+    pub downloaded: u64,
+    /// The size of the file (0 if unknown).
+    pub total: u64,
+    pub status: FileStatus,
+}
+
+/// Where a file is up to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileStatus {
+    Downloading,
+    Completed,
+    Failed,
+}
+
+impl DownloadTracker {
+    pub(crate) fn new() -> DownloadTracker {
+        DownloadTracker { shared: Default::default() }
+    }
+
+    pub(crate) fn shared(&self) -> &Arc<Shared> {
+        &self.shared
+    }
+
+    /// Gets the current state.
+    pub fn state(&self) -> DownloadState {
+        self.shared.tracked.lock().unwrap().state.clone()
+    }
+
+    /// Waits until the state has changed more than `updates` times (see
+    /// `DownloadState::updates`), or `timeout` has passed, and returns the state at that point.
+    pub fn wait(&self, updates: u64, timeout: Duration) -> DownloadState {
+        let deadline = Instant::now() + timeout;
+        let mut tracked = self.shared.tracked.lock().unwrap();
+        while tracked.state.updates <= updates {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            tracked = self.shared.changed.wait_timeout(tracked, deadline - now).unwrap().0;
+        }
+        tracked.state.clone()
+    }
+
+    /// Forgets everything reported so far.
+    pub fn reset(&self) {
+        let mut tracked = self.shared.tracked.lock().unwrap();
+        let updates = tracked.state.updates;
+        *tracked = Tracked::default();
+        tracked.state.updates = updates + 1;
+        self.shared.changed.notify_all();
+    }
+}
+
+impl Shared {
+    /// Updates the state with a record, if it is about downloads.
+    pub(crate) fn update(&self, record: &Record) {
+        self.update_at(record, Instant::now());
+    }
+
+    fn update_at(&self, record: &Record, now: Instant) {
+        let mut tracked = self.tracked.lock().unwrap();
+        match *record {
+            Record::TotalDownload(0) => return,
+            // a new total starts a new set of downloads
+            Record::TotalDownload(total) => {
+                let updates = tracked.state.updates;
+                *tracked = Tracked::default();
+                tracked.state.updates = updates;
+                tracked.announced = Some(total);
+            }
+            Record::Download { ref filename, downloaded, total } => {
+                let file = tracked.file(filename);
+                file.downloaded = downloaded;
+                file.total = total;
+                if total > 0 && downloaded == total && file.status == FileStatus::Downloading {
+                    file.status = FileStatus::Completed;
+                }
+            }
+            Record::DownloadDone { ref filename, success } => {
+                let file = tracked.file(filename);
+                file.status = if success { FileStatus::Completed } else { FileStatus::Failed };
+            }
+            _ => return,
+        }
+        tracked.refresh(now);
+        self.changed.notify_all();
+    }
+}
+
+impl Tracked {
+    /// Gets a file's progress, adding it if it is new.
+    fn file(&mut self, filename: &str) -> &mut FileProgress {
+        let files = &mut self.state.files;
+        match files.iter().position(|file| file.filename == filename) {
+            Some(i) => &mut files[i],
+            None => {
+                files.push(FileProgress {
+                    filename: filename.to_owned(),
+                    downloaded: 0,
+                    total: 0,
+                    status: FileStatus::Downloading,
+                });
+                files.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Recomputes the totals after a change.
+    fn refresh(&mut self, now: Instant) {
+        let state = &mut self.state;
+        state.updates += 1;
+        state.downloaded = state.files.iter().map(|file| file.downloaded).sum();
+        state.total = self.announced
+            .unwrap_or_else(|| state.files.iter().map(|file| file.total).sum());
+        state.completed = state.files.iter()
+            .filter(|file| file.status == FileStatus::Completed)
+            .count();
+        state.failed = state.files.iter().filter(|file| file.status == FileStatus::Failed).count();
+
+        self.samples.push_back((now, state.downloaded));
+        while self.samples.len() > 2
+            && now.duration_since(self.samples[0].0) > Duration::from_secs(RATE_WINDOW) {
+            self.samples.pop_front();
+        }
+        let (then, before) = self.samples[0];
+        let elapsed = now.duration_since(then);
+        let elapsed = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9;
+        state.rate = if elapsed > 0.0 && state.downloaded >= before {
+            (state.downloaded - before) as f64 / elapsed
+        } else {
+            0.0
+        };
+        state.eta = if state.total >= state.downloaded && state.rate > 0.0 {
+            let secs = (state.total - state.downloaded) as f64 / state.rate;
+            Some(Duration::new(secs as u64, (secs.fract() * 1e9) as u32))
+        } else {
+            None
+        };
+    }
+}
+
+#[test]
+fn test_download_tracker() {
+    let tracker = DownloadTracker::new();
+    let shared = tracker.shared();
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+    let download = |filename: &str, downloaded, total| Record::Download {
+        filename: filename.to_owned(),
+        downloaded: downloaded,
+        total: total,
+    };
+
+    shared.update_at(&Record::TotalDownload(3000), at(0));
+    shared.update_at(&download("a", 0, 1000), at(0));
+    shared.update_at(&download("b", 0, 2000), at(0));
+    shared.update_at(&download("a", 500, 1000), at(500));
+    shared.update_at(&download("b", 500, 2000), at(1000));
+    // not about downloads
+    shared.update_at(&Record::Event(::event::EventRecord::CheckDepsStart), at(1000));
+    let state = tracker.state();
+    assert_eq!(state.files.len(), 2);
+    assert_eq!(state.downloaded, 1000);
+    assert_eq!(state.total, 3000);
+    assert_eq!(state.rate, 1000.0);
+    assert_eq!(state.eta, Some(Duration::from_secs(2)));
+    assert_eq!((state.completed, state.failed), (0, 0));
+    assert_eq!(state.updates, 5);
+
+    shared.update_at(&download("a", 1000, 1000), at(1500));
+    shared.update_at(&Record::DownloadDone { filename: "b".into(), success: false }, at(2000));
+    let state = tracker.wait(5, Duration::from_secs(1));
+    assert_eq!(state.files[0].status, FileStatus::Completed);
+    assert_eq!(state.files[1].status, FileStatus::Failed);
+    assert_eq!((state.completed, state.failed), (1, 1));
+    assert_eq!(state.updates, 7);
+
+    // nothing more happens
+    assert_eq!(tracker.wait(7, Duration::from_millis(10)), state);
+
+    // the next set of downloads starts afresh
+    shared.update_at(&Record::TotalDownload(10), at(3000));
+    let state = tracker.state();
+    assert!(state.files.is_empty());
+    assert_eq!(state.total, 10);
+}
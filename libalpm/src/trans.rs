@@ -1,12 +1,21 @@
 //! A module for transactions. Private
 
 use std::any::Any;
+use std::error::Error as StdError;
+use std::fmt;
 use std::ptr;
 use std::mem;
 use std::marker::PhantomData;
 
 use alpm_sys::*;
-use super::{Alpm, Package, PackageRef, Error, AlpmResult, util};
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::Path;
+use std::process;
+
+use super::{Alpm, Package, PackageRef, PackageFrom, Db, Error, AlpmResult, util};
+use download::{Downloader, DownloadError, DownloadOptions, DownloadReport, Job, Checksum,
+               download_parallel};
 use libc;
 
 /// A state marker for before a transaction is prepared
@@ -171,6 +180,60 @@ impl<'a> Transaction<'a, Initialized> {
 
 impl<'a> Transaction<'a, Prepared> {
 
+    /// Downloads the packages this transaction will install from their sync databases' servers,
+    /// several at once, using clones of `downloader`.
+    ///
+    /// Packages are saved in the first writable cache directory, and any that don't match their
+    /// sha256 (or md5, for databases without sha256) from the sync database are removed again,
+    /// so `commit` will not need to download anything that was downloaded successfully.
+    /// Packages already in a cache directory (with the right checksum) are skipped.
+    ///
+    /// Fails only if no cache directory is writable; failed packages are listed in the report.
+    pub fn download_packages<D>(&self, downloader: D, options: &DownloadOptions)
+        -> Result<DownloadReport, DownloadError>
+        where D: Downloader + Clone + 'static
+    {
+        let cache_dirs = self.alpm.cache_dirs();
+        let dir = cache_dirs.iter()
+            .map(Path::new)
+            .find(|dir| is_writable(dir))
+            .ok_or_else(|| DownloadError::Io(io::Error::new(io::ErrorKind::PermissionDenied,
+                                                            "no writable cache directory")))?;
+
+        let mut cached = Vec::new();
+        let mut jobs = Vec::new();
+        for pkg in self.added_packages() {
+            if pkg.origin() != PackageFrom::SyncDb {
+                continue;
+            }
+            let filename = pkg.filename();
+            let checksum = Checksum::new(pkg.sha256(), pkg.md5());
+            let in_cache = cache_dirs.iter().any(|cache_dir| {
+                let path = Path::new(cache_dir).join(filename);
+                path.is_file() && checksum.as_ref().map_or(true, |checksum| checksum.matches(&path))
+            });
+            if in_cache {
+                cached.push(filename.to_owned());
+                continue;
+            }
+            let db = unsafe { Db::new(alpm_pkg_get_db(pkg as *const _ as _), self.alpm) };
+            jobs.push(Job {
+                filename: filename.to_owned(),
+                urls: db.servers().iter()
+                    .map(|server| format!("{}/{}", server.trim_end_matches('/'), filename))
+                    .collect(),
+                checksum: checksum,
+            });
+        }
+
+        let handle = Some(self.alpm.handle as usize);
+        let mut report =
+            download_parallel(handle, jobs, dir, &downloader, options, Checksum::matches);
+        cached.sort();
+        report.cached = cached;
+        Ok(report)
+    }
+
     /// Commits the transaction and returns the alpm instance. TODO conflict type
     ///
     ///  - Download required new packages
@@ -194,7 +257,26 @@ impl<'a> Transaction<'a, Prepared> {
 
 }
 
+/// Checks whether files can be created in a directory, creating it if needed (like pacman does
+/// for cache directories).
+fn is_writable(dir: &Path) -> bool {
+    if fs::create_dir_all(dir).is_err() {
+        return false;
+    }
+    let probe = dir.join(format!(".libalpm-write-test-{}", process::id()));
+    match OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
 /// Configuration options for a transaction.
+///
+/// Build with `TransactionFlags::builder()`, which checks for combinations that make no sense.
+/// Each flag can be read with the method of the same name.
 #[derive(Default, Debug, PartialEq, Eq, Copy, Clone)]
 pub struct TransactionFlags {
     /// Ignore dependency checks
@@ -231,6 +313,148 @@ pub struct TransactionFlags {
     no_lock: bool,
 }
 
+/// Generates getters on `TransactionFlags` and setters on `TransactionFlagsBuilder`.
+macro_rules! transaction_flags {
+    ($($(#[$doc:meta])* $flag:ident,)*) => {
+        impl TransactionFlags {
+            $(
+                $(#[$doc])*
+                pub fn $flag(&self) -> bool {
+                    self.$flag
+                }
+            )*
+        }
+
+        impl TransactionFlagsBuilder {
+            $(
+                $(#[$doc])*
+                pub fn $flag(mut self, yes: bool) -> TransactionFlagsBuilder {
+                    self.flags.$flag = yes;
+                    self
+                }
+            )*
+        }
+    }
+}
+
+transaction_flags! {
+    /// Ignore dependency checks
+    no_deps,
+    /// Ignore file conflicts and overwrite files
+    force,
+    /// Delete files even if they are tagged as backup
+    no_save,
+    /// Ignore version numbers when checking dependencies
+    no_dep_version,
+    /// Remove also any packages depending on a package being removed
+    cascade,
+    /// Remove packages and their unneeded deps (not explicitally installed)
+    recurse,
+    /// Modify database but do not commit changes to filesystem
+    db_only,
+    /// Mark all installed packages as dependencies.
+    all_deps,
+    /// Only download packages and do not actually install.
+    download_only,
+    /// Do not execute install scriptlets after installing
+    no_scriptlet,
+    /// Ignore dependency conflicts
+    no_conflicts,
+    /// Do not install a package if it is already installed and up to date
+    needed,
+    /// Mark all installed packages as explicitally requested.
+    all_explicit,
+    /// Do not remove a package if it is needed by another one.
+    unneeded,
+    /// Remove also explicitly installed unneeded deps (requires `recurse`)
+    recurse_all,
+    /// Do not lock the database during the operation.
+    no_lock,
+}
+
+impl TransactionFlags {
+    /// Starts building a set of flags, with all flags off.
+    pub fn builder() -> TransactionFlagsBuilder {
+        TransactionFlagsBuilder::default()
+    }
+
+    /// Checks that no incompatible flags are set together.
+    ///
+    /// Flags built with `TransactionFlagsBuilder` have already been checked, but flags converted
+    /// from a `u32` have not.
+    pub fn validate(&self) -> Result<(), InvalidFlags> {
+        if self.recurse_all && !self.recurse {
+            Err(InvalidFlags::RecurseAllWithoutRecurse)
+        } else if self.all_deps && self.all_explicit {
+            Err(InvalidFlags::AllDepsAndAllExplicit)
+        } else if self.db_only && self.download_only {
+            Err(InvalidFlags::DbOnlyAndDownloadOnly)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A builder for `TransactionFlags`.
+///
+/// # Examples
+///
+/// ```
+/// use libalpm::TransactionFlags;
+///
+/// let flags = TransactionFlags::builder()
+///     .cascade(true)
+///     .recurse(true)
+///     .build()
+///     .unwrap();
+/// assert!(flags.cascade());
+/// assert!(!flags.needed());
+/// ```
+#[derive(Default, Debug, Clone)]
+pub struct TransactionFlagsBuilder {
+    flags: TransactionFlags,
+}
+
+impl TransactionFlagsBuilder {
+    /// Creates a builder with all flags off.
+    pub fn new() -> TransactionFlagsBuilder {
+        TransactionFlagsBuilder::default()
+    }
+
+    /// Checks the flags for incompatible combinations and returns them.
+    pub fn build(self) -> Result<TransactionFlags, InvalidFlags> {
+        self.flags.validate()?;
+        Ok(self.flags)
+    }
+}
+
+/// A combination of transaction flags that cannot be used together.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InvalidFlags {
+    /// `recurse_all` only makes sense as an extension of `recurse`.
+    RecurseAllWithoutRecurse,
+    /// Installed packages cannot be marked as both dependencies and explicitly installed.
+    AllDepsAndAllExplicit,
+    /// `db_only` skips the filesystem, but `download_only` skips the database.
+    DbOnlyAndDownloadOnly,
+}
+
+impl StdError for InvalidFlags {
+    fn description(&self) -> &str {
+        match *self {
+            InvalidFlags::RecurseAllWithoutRecurse => "recurse_all requires recurse",
+            InvalidFlags::AllDepsAndAllExplicit => "all_deps and all_explicit are exclusive",
+            InvalidFlags::DbOnlyAndDownloadOnly => "db_only and download_only are exclusive",
+        }
+    }
+}
+
+impl fmt::Display for InvalidFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
 impl Into<u32> for TransactionFlags {
     fn into(self) -> u32 {
         let mut acc = 0;
@@ -316,3 +540,49 @@ fn test_transaction_flags() {
     assert!(!t.no_lock);
 }
 
+#[test]
+fn test_transaction_flags_round_trip() {
+    let bits = [ALPM_TRANS_FLAG_NODEPS, ALPM_TRANS_FLAG_FORCE, ALPM_TRANS_FLAG_NOSAVE,
+                ALPM_TRANS_FLAG_NODEPVERSION, ALPM_TRANS_FLAG_CASCADE, ALPM_TRANS_FLAG_RECURSE,
+                ALPM_TRANS_FLAG_DBONLY, ALPM_TRANS_FLAG_ALLDEPS, ALPM_TRANS_FLAG_DOWNLOADONLY,
+                ALPM_TRANS_FLAG_NOSCRIPTLET, ALPM_TRANS_FLAG_NOCONFLICTS, ALPM_TRANS_FLAG_NEEDED,
+                ALPM_TRANS_FLAG_ALLEXPLICIT, ALPM_TRANS_FLAG_UNNEEDED,
+                ALPM_TRANS_FLAG_RECURSEALL, ALPM_TRANS_FLAG_NOLOCK];
+    let mut all = 0;
+    for &bit in bits.iter() {
+        let flags = TransactionFlags::from(bit);
+        assert_eq!(Into::<u32>::into(flags), bit);
+        all |= bit;
+    }
+    assert_eq!(Into::<u32>::into(TransactionFlags::from(all)), all);
+    assert_eq!(Into::<u32>::into(TransactionFlags::default()), 0);
+
+    let flags = TransactionFlags::builder()
+        .cascade(true)
+        .recurse(true)
+        .recurse_all(true)
+        .needed(true)
+        .db_only(true)
+        .no_scriptlet(true)
+        .build()
+        .unwrap();
+    let bits: u32 = flags.into();
+    assert_eq!(bits, ALPM_TRANS_FLAG_CASCADE | ALPM_TRANS_FLAG_RECURSE
+                     | ALPM_TRANS_FLAG_RECURSEALL | ALPM_TRANS_FLAG_NEEDED
+                     | ALPM_TRANS_FLAG_DBONLY | ALPM_TRANS_FLAG_NOSCRIPTLET);
+    assert_eq!(TransactionFlags::from(bits), flags);
+}
+
+#[test]
+fn test_transaction_flags_validation() {
+    assert_eq!(TransactionFlags::builder().recurse_all(true).build(),
+               Err(InvalidFlags::RecurseAllWithoutRecurse));
+    assert_eq!(TransactionFlags::builder().all_deps(true).all_explicit(true).build(),
+               Err(InvalidFlags::AllDepsAndAllExplicit));
+    assert_eq!(TransactionFlags::builder().db_only(true).download_only(true).build(),
+               Err(InvalidFlags::DbOnlyAndDownloadOnly));
+    assert!(TransactionFlags::builder().recurse(true).recurse_all(true).build().is_ok());
+    // conversions from raw flags are not checked until asked
+    assert!(TransactionFlags::from(ALPM_TRANS_FLAG_RECURSEALL).validate().is_err());
+}
+
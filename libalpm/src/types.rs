@@ -19,14 +19,3 @@ impl From<u32> for Caps {
         }
     }
 }
-
-/// The result of a download
-pub enum DownloadResult {
-    /// The download succeeded
-    Ok,
-    /// The download was not needed
-    NotNeeded,
-    /// The download failed
-    Err,
-}
-
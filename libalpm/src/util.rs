@@ -1,9 +1,12 @@
 //! Some helper functions not directly related to the API
 
 use libc;
+use std::cmp::Ordering;
 use std::mem;
 use std::ptr;
 use std::ffi::{CStr, CString};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
 use alpm_sys::*;
 
 /// A wrapper around a libc::utsname struct, holding information on the current computer and os.
@@ -164,11 +167,46 @@ pub(crate) unsafe fn str_to_unowned_char_array(s: *const &str) -> *const libc::c
     p as *const libc::c_void
 }
 
+/// Locks a mutex, even if a panic poisoned it, so one panic in a callback doesn't stop every
+/// later callback.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
 /// Convert a str to owned raw mem allocated (and deallocated) in rust
 pub(crate) fn cstring_to_owned_char_array(s: &CString) -> *const libc::c_void {
     s.as_ptr() as *const libc::c_void
 }
 
+/// Computes the sha256 checksum of a file (as lowercase hex), using libalpm.
+pub fn compute_sha256(path: &Path) -> Option<String> {
+    let path = CString::new(path.to_str()?).ok()?;
+    unsafe { owned_checksum(alpm_compute_sha256sum(path.as_ptr())) }
+}
+
+/// Computes the md5 checksum of a file (as lowercase hex), using libalpm.
+pub fn compute_md5(path: &Path) -> Option<String> {
+    let path = CString::new(path.to_str()?).ok()?;
+    unsafe { owned_checksum(alpm_compute_md5sum(path.as_ptr())) }
+}
+
+/// Compares two package versions (`[epoch:]pkgver[-pkgrel]`) like `vercmp`, using libalpm.
+pub fn vercmp(a: &str, b: &str) -> Ordering {
+    let a = CString::new(a).unwrap_or_default();
+    let b = CString::new(b).unwrap_or_default();
+    unsafe { alpm_pkg_vercmp(a.as_ptr(), b.as_ptr()).cmp(&0) }
+}
+
+/// Copies a checksum allocated by libalpm, then frees it.
+unsafe fn owned_checksum(sum: *const libc::c_char) -> Option<String> {
+    if sum.is_null() {
+        return None;
+    }
+    let owned = CStr::from_ptr(sum).to_str().ok().map(|sum| sum.to_owned());
+    libc::free(sum as *mut libc::c_void);
+    owned
+}
+
 /// A TEMPORARY helper function to extract server urls from a pacman conf file (until I've
 /// implemented `Options::from_ini`).
 #[deprecated]
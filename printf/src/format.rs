@@ -0,0 +1,693 @@
+//! The printf format string interpreter.
+//!
+//! Formatting happens in two steps: the arguments that the format string refers to are taken
+//! from the `va_list` (`decode`), then the output is written from the format string and the
+//! decoded arguments (`render`). Nothing here panics on bad input; conversions that aren't
+//! understood (e.g. `%Lf` or positional arguments) end the formatting, and the rest of the format
+//! string is output as is, since the arguments can't be read safely past that point.
+
+use std::ffi::CStr;
+use std::iter;
+use std::mem::size_of;
+
+use libc::{c_char, c_long};
+
+use va_list::VaList;
+
+/// An argument decoded from a `va_list`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    /// A signed integer (`%d`, `%i`, and `*` widths and precisions).
+    Int(i64),
+    /// An unsigned integer (`%u`, `%o`, `%x`, `%X`).
+    Uint(u64),
+    /// A floating point number (`%f`, `%e`, `%g`, `%a` and their upper case forms).
+    Double(f64),
+    /// A character (`%c`).
+    Char(u8),
+    /// A string (`%s`), or `None` for a null pointer. The bytes aren't necessarily utf-8.
+    Str(Option<Vec<u8>>),
+    /// A pointer (`%p`, `%n`).
+    Ptr(usize),
+}
+
+impl Arg {
+    fn as_i64(&self) -> i64 {
+        match *self {
+            Arg::Int(v) => v,
+            Arg::Uint(v) => v as i64,
+            Arg::Double(v) => v as i64,
+            Arg::Char(v) => v as i64,
+            Arg::Ptr(v) => v as i64,
+            Arg::Str(_) => 0,
+        }
+    }
+
+    fn as_u64(&self) -> u64 {
+        match *self {
+            Arg::Uint(v) => v,
+            ref arg => arg.as_i64() as u64,
+        }
+    }
+
+    fn as_f64(&self) -> f64 {
+        match *self {
+            Arg::Double(v) => v,
+            Arg::Uint(v) => v as f64,
+            ref arg => arg.as_i64() as f64,
+        }
+    }
+}
+
+/// A width or precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Count {
+    Fixed(usize),
+    /// `*`: taken from the arguments.
+    Arg,
+}
+
+/// A length modifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Length {
+    Default,
+    /// `hh`
+    Char,
+    /// `h`
+    Short,
+    /// `l`
+    Long,
+    /// `ll` or `q`
+    LongLong,
+    /// `j`
+    IntMax,
+    /// `z`
+    Size,
+    /// `t`
+    PtrDiff,
+    /// `L`
+    LongDouble,
+}
+
+impl Length {
+    /// The size of an integer argument with this length, after promotion to `int`.
+    fn size(self) -> usize {
+        match self {
+            Length::Long => size_of::<c_long>(),
+            Length::LongLong | Length::IntMax => 8,
+            Length::Size => size_of::<usize>(),
+            Length::PtrDiff => size_of::<isize>(),
+            _ => 4,
+        }
+    }
+}
+
+/// A conversion specification, e.g. `%-10.3ld`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spec {
+    /// `-`: left justify.
+    pub left: bool,
+    /// `+`: always print a sign.
+    pub plus: bool,
+    /// ` `: print a space instead of a `+` sign.
+    pub space: bool,
+    /// `#`: the alternate form.
+    pub alt: bool,
+    /// `0`: pad with zeros.
+    pub zero: bool,
+    pub width: Option<Count>,
+    pub precision: Option<Count>,
+    pub length: Length,
+    /// The conversion character, e.g. `b'd'`.
+    pub conversion: u8,
+}
+
+/// A part of a format string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Piece<'a> {
+    /// Text that's output as is.
+    Text(&'a [u8]),
+    Spec(Spec),
+}
+
+/// Splits a format string into text and conversion specifications.
+pub fn parse(format: &[u8]) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut rest = format;
+    while !rest.is_empty() {
+        let text_len = rest.iter().position(|&b| b == b'%').unwrap_or(rest.len());
+        if text_len > 0 {
+            pieces.push(Piece::Text(&rest[..text_len]));
+            rest = &rest[text_len..];
+            continue;
+        }
+        match parse_spec(&rest[1..]) {
+            Some((spec, len)) => {
+                pieces.push(Piece::Spec(spec));
+                rest = &rest[1 + len..];
+            }
+            None => {
+                pieces.push(Piece::Text(rest));
+                break;
+            }
+        }
+    }
+    pieces
+}
+
+/// Parses the conversion specification after a `%`, returning it and its length, or `None` if
+/// it isn't supported.
+fn parse_spec(bytes: &[u8]) -> Option<(Spec, usize)> {
+    let mut spec = Spec {
+        left: false,
+        plus: false,
+        space: false,
+        alt: false,
+        zero: false,
+        width: None,
+        precision: None,
+        length: Length::Default,
+        conversion: 0,
+    };
+    let mut i = 0;
+    loop {
+        match bytes.get(i) {
+            Some(&b'-') => spec.left = true,
+            Some(&b'+') => spec.plus = true,
+            Some(&b' ') => spec.space = true,
+            Some(&b'#') => spec.alt = true,
+            Some(&b'0') => spec.zero = true,
+            _ => break,
+        }
+        i += 1;
+    }
+    let (width, len) = parse_count(&bytes[i..]);
+    spec.width = width;
+    i += len;
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        let (precision, len) = parse_count(&bytes[i..]);
+        spec.precision = Some(precision.unwrap_or(Count::Fixed(0)));
+        i += len;
+    }
+    let (length, len) = match (bytes.get(i), bytes.get(i + 1)) {
+        (Some(&b'h'), Some(&b'h')) => (Length::Char, 2),
+        (Some(&b'h'), _) => (Length::Short, 1),
+        (Some(&b'l'), Some(&b'l')) => (Length::LongLong, 2),
+        (Some(&b'l'), _) => (Length::Long, 1),
+        (Some(&b'q'), _) => (Length::LongLong, 1),
+        (Some(&b'j'), _) => (Length::IntMax, 1),
+        (Some(&b'z'), _) | (Some(&b'Z'), _) => (Length::Size, 1),
+        (Some(&b't'), _) => (Length::PtrDiff, 1),
+        (Some(&b'L'), _) => (Length::LongDouble, 1),
+        _ => (Length::Default, 0),
+    };
+    spec.length = length;
+    i += len;
+    spec.conversion = *bytes.get(i)?;
+    let supported = match spec.conversion {
+        b'd' | b'i' | b'u' | b'o' | b'x' | b'X' | b'n' => length != Length::LongDouble,
+        // `l` has no effect on doubles, but `long double` isn't supported
+        b'f' | b'F' | b'e' | b'E' | b'g' | b'G' | b'a' | b'A' => {
+            length == Length::Default || length == Length::Long
+        }
+        // no wide characters or strings
+        b'c' | b's' | b'p' => length == Length::Default,
+        b'%' => true,
+        _ => false,
+    };
+    if supported {
+        Some((spec, i + 1))
+    } else {
+        None
+    }
+}
+
+fn parse_count(bytes: &[u8]) -> (Option<Count>, usize) {
+    if bytes.first() == Some(&b'*') {
+        return (Some(Count::Arg), 1);
+    }
+    let len = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+    if len == 0 {
+        return (None, 0);
+    }
+    let count = bytes[..len]
+        .iter()
+        .fold(0usize, |n, b| n.saturating_mul(10).saturating_add((b - b'0') as usize));
+    (Some(Count::Fixed(count)), len)
+}
+
+/// Takes the arguments for the conversions in `pieces` from a `va_list`, in order.
+///
+/// # Safety
+/// The arguments must match the conversions, as for `vprintf`.
+pub unsafe fn decode(pieces: &[Piece], args: &mut VaList) -> Vec<Arg> {
+    let mut decoded = Vec::new();
+    for piece in pieces {
+        let spec = match *piece {
+            Piece::Spec(ref spec) => spec,
+            Piece::Text(_) => continue,
+        };
+        if spec.width == Some(Count::Arg) {
+            decoded.push(Arg::Int(args.next_int(4) as i32 as i64));
+        }
+        let precision = match spec.precision {
+            Some(Count::Fixed(precision)) => Some(precision),
+            Some(Count::Arg) => {
+                let precision = args.next_int(4) as i32;
+                decoded.push(Arg::Int(precision as i64));
+                if precision < 0 {
+                    None
+                } else {
+                    Some(precision as usize)
+                }
+            }
+            None => None,
+        };
+        let size = spec.length.size();
+        let arg = match spec.conversion {
+            b'd' | b'i' => {
+                let raw = args.next_int(size);
+                Arg::Int(match spec.length {
+                    Length::Char => raw as i8 as i64,
+                    Length::Short => raw as i16 as i64,
+                    _ if size == 4 => raw as i32 as i64,
+                    _ => raw as i64,
+                })
+            }
+            b'u' | b'o' | b'x' | b'X' => {
+                let raw = args.next_int(size);
+                Arg::Uint(match spec.length {
+                    Length::Char => raw as u8 as u64,
+                    Length::Short => raw as u16 as u64,
+                    _ => raw,
+                })
+            }
+            b'f' | b'F' | b'e' | b'E' | b'g' | b'G' | b'a' | b'A' => {
+                Arg::Double(args.next_double())
+            }
+            b'c' => Arg::Char(args.next_int(4) as u8),
+            b's' => Arg::Str(read_str(args.next_ptr() as *const c_char, precision)),
+            b'p' | b'n' => Arg::Ptr(args.next_ptr() as usize),
+            _ => continue,
+        };
+        decoded.push(arg);
+    }
+    decoded
+}
+
+/// Reads a C string, stopping after `max` bytes (the string needn't be nul terminated then).
+unsafe fn read_str(ptr: *const c_char, max: Option<usize>) -> Option<Vec<u8>> {
+    if ptr.is_null() {
+        return None;
+    }
+    match max {
+        Some(max) => {
+            let mut bytes = Vec::new();
+            while bytes.len() < max {
+                match *ptr.add(bytes.len()) as u8 {
+                    0 => break,
+                    b => bytes.push(b),
+                }
+            }
+            Some(bytes)
+        }
+        None => Some(CStr::from_ptr(ptr).to_bytes().to_vec()),
+    }
+}
+
+/// A conversion specification with the `*` widths and precisions filled in.
+struct Format {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alt: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+/// Writes the output for `pieces`, taking arguments from `args` in order. Missing arguments are
+/// treated as zero (or empty).
+pub fn render(pieces: &[Piece], args: &[Arg]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut args = args.iter();
+    for piece in pieces {
+        let spec = match *piece {
+            Piece::Text(text) => {
+                out.extend_from_slice(text);
+                continue;
+            }
+            Piece::Spec(ref spec) => spec,
+        };
+        let mut fmt = Format {
+            left: spec.left,
+            plus: spec.plus,
+            space: spec.space,
+            alt: spec.alt,
+            zero: spec.zero,
+            width: 0,
+            precision: None,
+        };
+        match spec.width {
+            Some(Count::Fixed(width)) => fmt.width = width,
+            Some(Count::Arg) => {
+                // a negative width means left justify
+                let width = args.next().map_or(0, Arg::as_i64);
+                fmt.left |= width < 0;
+                fmt.width = (width as i32).wrapping_abs() as u32 as usize;
+            }
+            None => (),
+        }
+        match spec.precision {
+            Some(Count::Fixed(precision)) => fmt.precision = Some(precision),
+            Some(Count::Arg) => {
+                // a negative precision is taken as if it were omitted
+                let precision = args.next().map_or(0, Arg::as_i64);
+                if precision >= 0 {
+                    fmt.precision = Some(precision as usize);
+                }
+            }
+            None => (),
+        }
+        if spec.conversion == b'%' {
+            out.push(b'%');
+            continue;
+        }
+        let arg = args.next();
+        match spec.conversion {
+            b'd' | b'i' => {
+                let value = arg.map_or(0, Arg::as_i64);
+                let sign = sign(value < 0, &fmt);
+                let abs = if value < 0 {
+                    (value as u64).wrapping_neg()
+                } else {
+                    value as u64
+                };
+                integer(&mut out, &fmt, sign, format!("{}", abs), abs == 0, false);
+            }
+            b'u' => {
+                let value = arg.map_or(0, Arg::as_u64);
+                integer(&mut out, &fmt, b"", format!("{}", value), value == 0, false);
+            }
+            b'o' => {
+                let value = arg.map_or(0, Arg::as_u64);
+                integer(&mut out, &fmt, b"", format!("{:o}", value), value == 0, true);
+            }
+            b'x' | b'X' => {
+                let value = arg.map_or(0, Arg::as_u64);
+                let upper = spec.conversion == b'X';
+                let prefix: &[u8] = match (fmt.alt && value != 0, upper) {
+                    (false, _) => b"",
+                    (true, false) => b"0x",
+                    (true, true) => b"0X",
+                };
+                let digits = if upper {
+                    format!("{:X}", value)
+                } else {
+                    format!("{:x}", value)
+                };
+                integer(&mut out, &fmt, prefix, digits, value == 0, false);
+            }
+            b'f' | b'F' | b'e' | b'E' | b'g' | b'G' | b'a' | b'A' => {
+                float(&mut out, &fmt, spec.conversion, arg.map_or(0.0, Arg::as_f64));
+            }
+            b'c' => {
+                let value = arg.map_or(0, |arg| arg.as_i64() as u8);
+                pad(&mut out, &fmt, b"", &[value], false);
+            }
+            b's' => {
+                let value = match arg {
+                    Some(&Arg::Str(Some(ref bytes))) => &bytes[..],
+                    Some(&Arg::Str(None)) => b"(null)",
+                    _ => b"",
+                };
+                let len = fmt.precision.map_or(value.len(), |precision| {
+                    precision.min(value.len())
+                });
+                pad(&mut out, &fmt, b"", &value[..len], false);
+            }
+            b'p' => {
+                let value = arg.map_or(0, Arg::as_u64);
+                if value == 0 {
+                    pad(&mut out, &fmt, b"", b"(nil)", false);
+                } else {
+                    pad(&mut out, &fmt, b"0x", format!("{:x}", value).as_bytes(), false);
+                }
+            }
+            // `%n` is deliberately not written to
+            _ => (),
+        }
+    }
+    out
+}
+
+fn sign(negative: bool, fmt: &Format) -> &'static [u8] {
+    if negative {
+        b"-"
+    } else if fmt.plus {
+        b"+"
+    } else if fmt.space {
+        b" "
+    } else {
+        b""
+    }
+}
+
+/// Writes an integer conversion, applying the precision (minimum number of digits).
+fn integer(out: &mut Vec<u8>, fmt: &Format, prefix: &[u8], digits: String, zero: bool,
+           octal: bool) {
+    let mut digits = if zero && fmt.precision == Some(0) {
+        String::new()
+    } else {
+        digits
+    };
+    if let Some(precision) = fmt.precision {
+        if precision > digits.len() {
+            let zeros = "0".repeat(precision - digits.len());
+            digits.insert_str(0, &zeros);
+        }
+    }
+    // the alternate form of octal always starts with a 0
+    if octal && fmt.alt && !digits.starts_with('0') {
+        digits.insert(0, '0');
+    }
+    pad(out, fmt, prefix, digits.as_bytes(), fmt.precision.is_none());
+}
+
+/// Writes a floating point conversion.
+fn float(out: &mut Vec<u8>, fmt: &Format, conversion: u8, value: f64) {
+    let sign = sign(value.is_sign_negative(), fmt);
+    let value = value.abs();
+    if !value.is_finite() {
+        let body = match (value.is_nan(), conversion.is_ascii_uppercase()) {
+            (true, false) => "nan",
+            (true, true) => "NAN",
+            (false, false) => "inf",
+            (false, true) => "INF",
+        };
+        return pad(out, fmt, sign, body.as_bytes(), false);
+    }
+    let body = match conversion.to_ascii_lowercase() {
+        b'f' => fixed(value, fmt.precision.unwrap_or(6), fmt.alt),
+        b'e' => exponent(value, fmt.precision.unwrap_or(6), fmt.alt),
+        b'g' => general(value, fmt.precision, fmt.alt),
+        _ => hex_float(value, fmt.precision, fmt.alt),
+    };
+    let body = if conversion.is_ascii_uppercase() {
+        body.to_ascii_uppercase()
+    } else {
+        body
+    };
+    pad(out, fmt, sign, body.as_bytes(), true);
+}
+
+/// `%f`
+fn fixed(value: f64, precision: usize, alt: bool) -> String {
+    let mut s = format!("{:.*}", precision, value);
+    if alt && precision == 0 {
+        s.push('.');
+    }
+    s
+}
+
+/// `%e`: rust writes e.g. `1.5e-7`, where C wants `1.5e-07`.
+fn exponent(value: f64, precision: usize, alt: bool) -> String {
+    let s = format!("{:.*e}", precision, value);
+    let (mantissa, exp) = split_exponent(&s);
+    let point = if alt && precision == 0 { "." } else { "" };
+    let exp_sign = if exp < 0 { '-' } else { '+' };
+    format!("{}{}e{}{:02}", mantissa, point, exp_sign, exp.abs())
+}
+
+fn split_exponent(s: &str) -> (&str, i32) {
+    match s.find('e') {
+        Some(i) => (&s[..i], s[i + 1..].parse().unwrap_or(0)),
+        None => (s, 0),
+    }
+}
+
+/// `%g`: `%e` for very large or small numbers, `%f` otherwise, without trailing zeros.
+fn general(value: f64, precision: Option<usize>, alt: bool) -> String {
+    let precision = match precision {
+        None => 6,
+        Some(0) => 1,
+        Some(precision) => precision,
+    };
+    // the exponent after rounding to the precision decides the style
+    let (_, exp) = split_exponent(&format!("{:.*e}", precision - 1, value));
+    let mut s = if (exp as i64) < precision as i64 && exp >= -4 {
+        fixed(value, (precision as i64 - 1 - exp as i64) as usize, alt)
+    } else {
+        exponent(value, precision - 1, alt)
+    };
+    if !alt {
+        let (mantissa, exp) = match s.find('e') {
+            Some(i) => s.split_at(i),
+            None => (&s[..], ""),
+        };
+        let mantissa = if mantissa.contains('.') {
+            mantissa.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            mantissa
+        };
+        s = format!("{}{}", mantissa, exp);
+    } else if !s.contains('.') {
+        s.push('.');
+    }
+    s
+}
+
+/// `%a`: hexadecimal, e.g. `0x1.8p+1`. Without a precision, the exact value is written.
+fn hex_float(value: f64, precision: Option<usize>, alt: bool) -> String {
+    const MANTISSA_DIGITS: usize = 13;
+    let bits = value.to_bits();
+    let biased_exp = ((bits >> 52) & 0x7ff) as i32;
+    let mut mantissa = bits & ((1u64 << 52) - 1);
+    let (mut lead, exp) = match (biased_exp, mantissa) {
+        (0, 0) => (0, 0),
+        // subnormal
+        (0, _) => (0, -1022),
+        _ => (1, biased_exp - 1023),
+    };
+    let mut digits = MANTISSA_DIGITS;
+    if let Some(precision) = precision {
+        if precision < MANTISSA_DIGITS {
+            // round half to even at the last digit
+            let shift = 4 * (MANTISSA_DIGITS - precision);
+            let rest = mantissa & ((1u64 << shift) - 1);
+            let half = 1u64 << (shift - 1);
+            mantissa >>= shift;
+            if rest > half || rest == half && mantissa & 1 == 1 {
+                mantissa += 1;
+                if mantissa >> (4 * precision) != 0 {
+                    mantissa &= (1u64 << (4 * precision)) - 1;
+                    lead += 1;
+                }
+            }
+            digits = precision;
+        }
+    }
+    let mut frac = if digits == 0 {
+        String::new()
+    } else {
+        format!("{:01$x}", mantissa, digits)
+    };
+    match precision {
+        None => {
+            let len = frac.trim_end_matches('0').len();
+            frac.truncate(len);
+        }
+        Some(precision) => {
+            while frac.len() < precision {
+                frac.push('0');
+            }
+        }
+    }
+    let point = if !frac.is_empty() || alt { "." } else { "" };
+    let exp_sign = if exp < 0 { '-' } else { '+' };
+    format!("0x{}{}{}p{}{}", lead, point, frac, exp_sign, exp.abs())
+}
+
+/// Writes `prefix` (a sign or `0x`) and `body`, padded to the width. `zeroable` says whether
+/// the `0` flag applies.
+fn pad(out: &mut Vec<u8>, fmt: &Format, prefix: &[u8], body: &[u8], zeroable: bool) {
+    let fill = fmt.width.saturating_sub(prefix.len() + body.len());
+    if fmt.left {
+        out.extend_from_slice(prefix);
+        out.extend_from_slice(body);
+        out.extend(iter::repeat_n(b' ', fill));
+    } else if fmt.zero && zeroable {
+        out.extend_from_slice(prefix);
+        out.extend(iter::repeat_n(b'0', fill));
+        out.extend_from_slice(body);
+    } else {
+        out.extend(iter::repeat_n(b' ', fill));
+        out.extend_from_slice(prefix);
+        out.extend_from_slice(body);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(format: &str, args: &[Arg]) -> String {
+        String::from_utf8(render(&parse(format.as_bytes()), args)).unwrap()
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(format("%d|%5d|%-5d|%05d", &[Arg::Int(-3), Arg::Int(42), Arg::Int(7),
+                                               Arg::Int(-12)]),
+                   "-3|   42|7    |-0012");
+        assert_eq!(format("%+d % d %.3d %.0d", &[Arg::Int(1), Arg::Int(2), Arg::Int(3),
+                                                 Arg::Int(0)]),
+                   "+1  2 003 ");
+        assert_eq!(format("%x %X %#x %#o %o %#o", &[Arg::Uint(255), Arg::Uint(255),
+                                                    Arg::Uint(0), Arg::Uint(8), Arg::Uint(8),
+                                                    Arg::Uint(0)]),
+                   "ff FF 0 010 10 0");
+        assert_eq!(format("%*d|%-*d|%.*d", &[Arg::Int(4), Arg::Int(1), Arg::Int(3), Arg::Int(2),
+                                             Arg::Int(3), Arg::Int(5)]),
+                   "   1|2  |005");
+        assert_eq!(format("%*d", &[Arg::Int(-3), Arg::Int(1)]), "1  ");
+    }
+
+    #[test]
+    fn floats() {
+        let pi = Arg::Double(::std::f64::consts::PI);
+        assert_eq!(format("%f %.2f %8.3f %-8.1f|", &[pi.clone(), pi.clone(), pi.clone(),
+                                                     pi.clone()]),
+                   "3.141593 3.14    3.142 3.1     |");
+        assert_eq!(format("%e %.2E %.0e %#.0e", &[Arg::Double(1234.5), Arg::Double(1e-7),
+                                                  Arg::Double(5.0), Arg::Double(5.0)]),
+                   "1.234500e+03 1.00E-07 5e+00 5.e+00");
+        assert_eq!(format("%g %g %g %G %#g", &[Arg::Double(100000.0), Arg::Double(1000000.0),
+                                               Arg::Double(0.0001), Arg::Double(1e-5),
+                                               Arg::Double(1.5)]),
+                   "100000 1e+06 0.0001 1E-05 1.50000");
+        assert_eq!(format("%a %A %.1a %a", &[Arg::Double(3.0), Arg::Double(1.0),
+                                             Arg::Double(1.96875), Arg::Double(0.0)]),
+                   "0x1.8p+1 0X1P+0 0x2.0p+0 0x0p+0");
+        assert_eq!(format("%f %5.1f %F %05f", &[Arg::Double(-0.0), Arg::Double(f64::NAN),
+                                                Arg::Double(f64::INFINITY),
+                                                Arg::Double(-1.0)]),
+                   "-0.000000   nan INF -1.000000");
+    }
+
+    #[test]
+    fn others() {
+        assert_eq!(format("%c%c %5s|%-5s|%.2s %s %%", &[Arg::Char(b'o'), Arg::Char(b'k'),
+                                                        Arg::Str(Some(b"abc".to_vec())),
+                                                        Arg::Str(Some(b"abc".to_vec())),
+                                                        Arg::Str(Some(b"abc".to_vec())),
+                                                        Arg::Str(None)]),
+                   "ok   abc|abc  |ab (null) %");
+        assert_eq!(format("%p %p", &[Arg::Ptr(0x1000), Arg::Ptr(0)]), "0x1000 (nil)");
+        // unsupported conversions stop the formatting
+        assert_eq!(format("%d %Lf %d", &[Arg::Int(1)]), "1 %Lf %d");
+        assert_eq!(format("%1$d", &[Arg::Int(1)]), "%1$d");
+        assert_eq!(format("100%", &[]), "100%");
+    }
+}
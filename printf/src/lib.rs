@@ -1,14 +1,20 @@
 //! This crate provides a method to convert printf-style calls to a rust formatter
+//!
+//! The format string is interpreted in rust, and the arguments are read straight out of the
+//! `va_list`, so only the architectures whose `va_list` layout is known (x86_64, aarch64 and x86)
+//! are supported.
 extern crate libc;
 
+mod format;
+mod va_list;
+
 use std::ffi::CStr;
 
 use libc as c;
 
-#[link(name = "printf_wrapper")]
-extern "C" {
-    fn printf_wrapper(format: *const c::c_char, args: *mut c::c_void) -> *const c::c_char;
-}
+use va_list::VaList;
+
+pub use format::Arg;
 
 /// Take a printf c-string and variadic array, and write equiv. out to the formatter
 ///
@@ -18,8 +24,37 @@ extern "C" {
 /// There must be no panics in this function, so quite often errors are deliberately ignored
 pub unsafe fn printf(format: *const c::c_char, args: *mut c::c_void) -> String
 {
-    let out_char_p = printf_wrapper(format, args);
-    let output = CStr::from_ptr(out_char_p).to_string_lossy().into_owned();
-    c::free(out_char_p as *mut c::c_void);
-    output
+    if format.is_null() {
+        return String::new();
+    }
+    let pieces = format::parse(CStr::from_ptr(format).to_bytes());
+    let args = format::decode(&pieces, &mut VaList::new(args));
+    String::from_utf8_lossy(&format::render(&pieces, &args)).into_owned()
+}
+
+/// Take a printf c-string and variadic array, and return the arguments the format refers to,
+/// without formatting them
+///
+/// `*` widths and precisions are included as `Arg::Int`, in the order they appear. The output of
+/// `printf` is `format(format, &decode_args(format, args))`.
+///
+/// # Safety
+/// This function is UB if the va_list doesn't match the format (c printf syntax)
+pub unsafe fn decode_args(format: *const c::c_char, args: *mut c::c_void) -> Vec<Arg>
+{
+    if format.is_null() {
+        return Vec::new();
+    }
+    let pieces = format::parse(CStr::from_ptr(format).to_bytes());
+    format::decode(&pieces, &mut VaList::new(args))
+}
+
+/// Format a printf format string with already decoded arguments
+///
+/// Missing arguments are treated as zero (or an empty string), and arguments of the wrong type
+/// are converted.
+pub fn format(format: &[u8], args: &[Arg]) -> String
+{
+    let pieces = format::parse(format);
+    String::from_utf8_lossy(&format::render(&pieces, args)).into_owned()
 }
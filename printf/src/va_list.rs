@@ -0,0 +1,155 @@
+//! Reading arguments out of a C `va_list`, like the `va_arg` macro.
+//!
+//! The layout of `va_list` depends on the platform ABI, so only the platforms below are
+//! supported. In each case the `*mut c_void` received from C points at the argument state, and
+//! the state is copied so the caller's `va_list` is left untouched (like `va_copy`).
+
+use libc::c_void;
+
+/// The System V x86_64 `va_list`: registers first, then the stack.
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct State {
+    gp_offset: u32,
+    fp_offset: u32,
+    overflow_arg_area: *const u8,
+    reg_save_area: *const u8,
+}
+
+/// The AArch64 (AAPCS64) `va_list`. The offsets are negative while there are arguments left in
+/// the saved registers.
+#[cfg(target_arch = "aarch64")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct State {
+    stack: *const u8,
+    gr_top: *const u8,
+    vr_top: *const u8,
+    gr_offs: i32,
+    vr_offs: i32,
+}
+
+/// The i386 `va_list`, which is just a pointer to the arguments on the stack.
+#[cfg(target_arch = "x86")]
+#[derive(Clone, Copy)]
+struct State {
+    stack: *const u8,
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "x86")))]
+compile_error!("printf does not know the va_list layout for this architecture");
+
+/// A copy of a `va_list` that arguments can be taken from in order.
+pub struct VaList {
+    state: State,
+}
+
+impl VaList {
+    /// Copies the state of a `va_list` as received from C.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    pub unsafe fn new(args: *mut c_void) -> VaList {
+        VaList { state: *(args as *const State) }
+    }
+
+    /// Copies the state of a `va_list` as received from C.
+    #[cfg(target_arch = "x86")]
+    pub unsafe fn new(args: *mut c_void) -> VaList {
+        VaList { state: State { stack: args as *const u8 } }
+    }
+
+    /// Takes an integer or pointer argument of `size` bytes (at most 8). Arguments smaller than
+    /// an `int` have already been promoted by the caller, so `size` is at least 4.
+    #[cfg(target_arch = "x86_64")]
+    pub unsafe fn next_int(&mut self, size: usize) -> u64 {
+        let ptr = if self.state.gp_offset < 48 {
+            let ptr = self.state.reg_save_area.offset(self.state.gp_offset as isize);
+            self.state.gp_offset += 8;
+            ptr
+        } else {
+            let ptr = self.state.overflow_arg_area;
+            self.state.overflow_arg_area = ptr.offset(8);
+            ptr
+        };
+        read_int(ptr, size)
+    }
+
+    /// Takes a `double` argument (`float`s are promoted by the caller).
+    #[cfg(target_arch = "x86_64")]
+    pub unsafe fn next_double(&mut self) -> f64 {
+        // 6 general purpose registers (48 bytes) then 8 vector registers of 16 bytes each
+        if self.state.fp_offset < 176 {
+            let ptr = self.state.reg_save_area.offset(self.state.fp_offset as isize);
+            self.state.fp_offset += 16;
+            (ptr as *const f64).read_unaligned()
+        } else {
+            let ptr = self.state.overflow_arg_area;
+            self.state.overflow_arg_area = ptr.offset(8);
+            (ptr as *const f64).read_unaligned()
+        }
+    }
+
+    /// Takes an integer or pointer argument of `size` bytes (at most 8). Arguments smaller than
+    /// an `int` have already been promoted by the caller, so `size` is at least 4.
+    #[cfg(target_arch = "aarch64")]
+    pub unsafe fn next_int(&mut self, size: usize) -> u64 {
+        let offs = self.state.gr_offs;
+        let ptr = if offs < 0 && offs + 8 <= 0 {
+            self.state.gr_offs = offs + 8;
+            self.state.gr_top.offset(offs as isize)
+        } else {
+            self.state.gr_offs = 0;
+            let ptr = self.state.stack;
+            self.state.stack = ptr.offset(8);
+            ptr
+        };
+        read_int(ptr, size)
+    }
+
+    /// Takes a `double` argument (`float`s are promoted by the caller).
+    #[cfg(target_arch = "aarch64")]
+    pub unsafe fn next_double(&mut self) -> f64 {
+        let offs = self.state.vr_offs;
+        let ptr = if offs < 0 && offs + 16 <= 0 {
+            self.state.vr_offs = offs + 16;
+            self.state.vr_top.offset(offs as isize)
+        } else {
+            self.state.vr_offs = 0;
+            let ptr = self.state.stack;
+            self.state.stack = ptr.offset(8);
+            ptr
+        };
+        (ptr as *const f64).read_unaligned()
+    }
+
+    /// Takes an integer or pointer argument of `size` bytes (at most 8). Arguments smaller than
+    /// an `int` have already been promoted by the caller, so `size` is at least 4.
+    #[cfg(target_arch = "x86")]
+    pub unsafe fn next_int(&mut self, size: usize) -> u64 {
+        let ptr = self.state.stack;
+        self.state.stack = ptr.offset(if size > 4 { 8 } else { 4 });
+        read_int(ptr, size)
+    }
+
+    /// Takes a `double` argument (`float`s are promoted by the caller).
+    #[cfg(target_arch = "x86")]
+    pub unsafe fn next_double(&mut self) -> f64 {
+        let ptr = self.state.stack;
+        self.state.stack = ptr.offset(8);
+        (ptr as *const f64).read_unaligned()
+    }
+
+    /// Takes a pointer argument.
+    pub unsafe fn next_ptr(&mut self) -> *const c_void {
+        self.next_int(::std::mem::size_of::<*const c_void>()) as usize as *const c_void
+    }
+}
+
+/// Reads a little endian integer of 4 or 8 bytes, zero extended.
+unsafe fn read_int(ptr: *const u8, size: usize) -> u64 {
+    if size > 4 {
+        (ptr as *const u64).read_unaligned()
+    } else {
+        (ptr as *const u32).read_unaligned() as u64
+    }
+}